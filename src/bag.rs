@@ -0,0 +1,245 @@
+//! Reads the connection records of ROS bag files (format version 2.0), i.e. the topics a bag
+//! contains and their types, without decompressing any messages.
+//!
+//! ```no_run
+//! for connection in ros_core_rs::bag::read_connections("session.bag".as_ref())? {
+//!     println!("{} [{}]", connection.topic, connection.topic_type);
+//! }
+//! # Ok::<(), ros_core_rs::Error>(())
+//! ```
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{Error, Result};
+
+const MAGIC: &[u8] = b"#ROSBAG V2.0\n";
+const OP_FILE_HEADER: u8 = 0x03;
+const OP_CONNECTION: u8 = 0x07;
+
+/// A connection recorded in a bag, i.e. a topic as published by one node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagConnection {
+    pub topic: String,
+    pub topic_type: String,
+    pub md5sum: String,
+    /// The node that published the topic while recording, if the bag knows it.
+    pub caller_id: Option<String>,
+    pub latching: bool,
+}
+
+/// Reads the connections of the bag at `path` from its index. Bags that are still being
+/// recorded or were not closed properly have no index yet and need `rosbag reindex` first.
+pub fn read_connections(path: &Path) -> Result<Vec<BagConnection>> {
+    let io = |source| Error::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let mut file = std::io::BufReader::new(std::fs::File::open(path).map_err(io)?);
+    parse_connections(&mut file).map_err(|e| match e {
+        BagError::Io(source) => io(source),
+        BagError::Invalid(reason) => Error::InvalidBag {
+            path: path.to_owned(),
+            reason,
+        },
+    })
+}
+
+enum BagError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl From<std::io::Error> for BagError {
+    fn from(error: std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::UnexpectedEof {
+            BagError::Invalid("unexpected end of file".to_owned())
+        } else {
+            BagError::Io(error)
+        }
+    }
+}
+
+fn invalid<T>(reason: &str) -> Result<T, BagError> {
+    Err(BagError::Invalid(reason.to_owned()))
+}
+
+fn parse_connections<R: Read + Seek>(bag: &mut R) -> Result<Vec<BagConnection>, BagError> {
+    let mut magic = [0; MAGIC.len()];
+    bag.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return invalid("not a version 2.0 bag");
+    }
+    let (header, _) = read_record(bag)?;
+    if op(&header)? != OP_FILE_HEADER {
+        return invalid("missing file header");
+    }
+    let index_pos = u64::from_le_bytes(field(&header, "index_pos")?);
+    let conn_count = u32::from_le_bytes(field(&header, "conn_count")?);
+    if index_pos == 0 {
+        return invalid("the bag has no index, run `rosbag reindex` on it");
+    }
+    bag.seek(SeekFrom::Start(index_pos))?;
+    let mut connections = Vec::with_capacity(conn_count as usize);
+    while connections.len() < conn_count as usize {
+        let (header, data) = read_record(bag)?;
+        if op(&header)? != OP_CONNECTION {
+            continue;
+        }
+        let topic = string(&header, "topic")?;
+        let data = read_fields(&data)?;
+        let optional = |name| string(&data, name).ok();
+        connections.push(BagConnection {
+            topic,
+            topic_type: string(&data, "type")?,
+            md5sum: string(&data, "md5sum")?,
+            caller_id: optional("callerid").filter(|id| !id.is_empty()),
+            latching: optional("latching").is_some_and(|latching| latching == "1"),
+        });
+    }
+    Ok(connections)
+}
+
+type Fields = HashMap<String, Vec<u8>>;
+
+/// Reads a record, i.e. its header fields and its data.
+fn read_record<R: Read>(bag: &mut R) -> Result<(Fields, Vec<u8>), BagError> {
+    let header = read_fields(&read_block(bag)?)?;
+    let data = read_block(bag)?;
+    Ok((header, data))
+}
+
+fn read_block<R: Read>(bag: &mut R) -> Result<Vec<u8>, BagError> {
+    let mut len = [0; 4];
+    bag.read_exact(&mut len)?;
+    let mut block = Vec::new();
+    bag.take(u32::from_le_bytes(len).into())
+        .read_to_end(&mut block)?;
+    if block.len() != u32::from_le_bytes(len) as usize {
+        return invalid("unexpected end of file");
+    }
+    Ok(block)
+}
+
+/// Splits a sequence of length prefixed `name=value` fields.
+fn read_fields(mut block: &[u8]) -> Result<Fields, BagError> {
+    let mut fields = HashMap::new();
+    while !block.is_empty() {
+        let Some((len, rest)) = block.split_first_chunk::<4>() else {
+            return invalid("truncated field");
+        };
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return invalid("truncated field");
+        }
+        let (field, rest) = rest.split_at(len);
+        let Some(eq) = field.iter().position(|&b| b == b'=') else {
+            return invalid("field without a name");
+        };
+        let name = String::from_utf8_lossy(&field[..eq]).into_owned();
+        fields.insert(name, field[eq + 1..].to_vec());
+        block = rest;
+    }
+    Ok(fields)
+}
+
+fn field<const N: usize>(fields: &Fields, name: &str) -> Result<[u8; N], BagError> {
+    match fields
+        .get(name)
+        .map(|value| <[u8; N]>::try_from(value.as_slice()))
+    {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(BagError::Invalid(format!(
+            "missing or malformed field {name}"
+        ))),
+    }
+}
+
+fn op(fields: &Fields) -> Result<u8, BagError> {
+    field::<1>(fields, "op").map(|[op]| op)
+}
+
+fn string(fields: &Fields, name: &str) -> Result<String, BagError> {
+    match fields
+        .get(name)
+        .map(|value| String::from_utf8(value.clone()))
+    {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(BagError::Invalid(format!(
+            "missing or malformed field {name}"
+        ))),
+    }
+}
+
+#[test]
+fn test_parse_connections() {
+    fn block(fields: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut block = Vec::new();
+        for (name, value) in fields {
+            block.extend((name.len() as u32 + 1 + value.len() as u32).to_le_bytes());
+            block.extend(name.as_bytes());
+            block.push(b'=');
+            block.extend(*value);
+        }
+        block
+    }
+    fn record(bag: &mut Vec<u8>, header: &[(&str, &[u8])], data: &[u8]) {
+        let header = block(header);
+        bag.extend((header.len() as u32).to_le_bytes());
+        bag.extend(header);
+        bag.extend((data.len() as u32).to_le_bytes());
+        bag.extend(data);
+    }
+
+    let mut bag = MAGIC.to_vec();
+    let index_pos_at = bag.len() + 4 + 4 + "index_pos=".len();
+    let header: &[(&str, &[u8])] = &[
+        ("index_pos", &[0; 8]),
+        ("op", &[OP_FILE_HEADER]),
+        ("conn_count", &2u32.to_le_bytes()),
+        ("chunk_count", &0u32.to_le_bytes()),
+    ];
+    record(&mut bag, header, &[b' '; 16]);
+    // An unindexed bag is rejected.
+    let parsed = parse_connections(&mut std::io::Cursor::new(&bag));
+    assert!(matches!(parsed, Err(BagError::Invalid(_))));
+
+    let index_pos = bag.len() as u64;
+    bag[index_pos_at..index_pos_at + 8].copy_from_slice(&index_pos.to_le_bytes());
+    let chatter = block(&[
+        ("topic", b"/chatter"),
+        ("type", b"std_msgs/String"),
+        ("md5sum", b"992ce8a1687cec8c8bd883ec73ca41d1"),
+        ("callerid", b"/talker"),
+    ]);
+    let header: &[(&str, &[u8])] = &[
+        ("op", &[OP_CONNECTION]),
+        ("conn", &0u32.to_le_bytes()),
+        ("topic", b"/chatter"),
+    ];
+    record(&mut bag, header, &chatter);
+    let tf = block(&[
+        ("topic", b"/tf_static"),
+        ("type", b"tf2_msgs/TFMessage"),
+        ("md5sum", b"94810edda583a504dfda3829e70d7eec"),
+        ("latching", b"1"),
+    ]);
+    let header: &[(&str, &[u8])] = &[
+        ("op", &[OP_CONNECTION]),
+        ("conn", &1u32.to_le_bytes()),
+        ("topic", b"/tf_static"),
+    ];
+    record(&mut bag, header, &tf);
+
+    let Ok(connections) = parse_connections(&mut std::io::Cursor::new(&bag)) else {
+        panic!("failed to parse the bag");
+    };
+    assert_eq!(connections.len(), 2);
+    assert_eq!(connections[0].topic, "/chatter");
+    assert_eq!(connections[0].topic_type, "std_msgs/String");
+    assert_eq!(connections[0].caller_id.as_deref(), Some("/talker"));
+    assert!(!connections[0].latching);
+    assert_eq!(connections[1].topic_type, "tf2_msgs/TFMessage");
+    assert_eq!(connections[1].caller_id, None);
+    assert!(connections[1].latching);
+}
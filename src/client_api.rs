@@ -1,7 +1,16 @@
+use std::time::Duration;
+
 use dxr::Value;
 use dxr_client::{Client, ClientBuilder, Url};
 
+use crate::{Error, Result};
+
+/// The answer of a node to `getPublications` or `getSubscriptions`: the code, the status message
+/// and the topics with their types.
+pub type TopicsResponse = (i32, String, Vec<(String, String)>);
+
 pub struct ClientApi {
+    uri: String,
     client: Client,
 }
 
@@ -21,7 +30,39 @@ impl ClientApi {
         let client = ClientBuilder::new(url.clone())
             .user_agent("ros-core-rs-client-api")
             .build();
-        Self { client }
+        Self {
+            uri: uri.to_owned(),
+            client,
+        }
+    }
+
+    /// Creates a new `ClientApi` whose requests fail if the node doesn't answer within `timeout`.
+    pub fn with_timeout(uri: &str, timeout: Duration) -> Self {
+        Self::with_http(uri, reqwest::Client::builder().timeout(timeout))
+    }
+
+    /// Like [`ClientApi::with_timeout`], but verifies `https://` nodes and authenticates to them
+    /// according to `tls`.
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    pub fn with_tls(uri: &str, timeout: Duration, tls: &crate::tls::TlsConfig) -> Self {
+        Self::with_http(uri, tls.apply(reqwest::Client::builder().timeout(timeout)))
+    }
+
+    /// The URI of the node API.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn with_http(uri: &str, builder: reqwest::ClientBuilder) -> Self {
+        let url = Url::parse(uri).expect("Failed to parse client-api URL.");
+        let http = builder
+            .user_agent("ros-core-rs-client-api")
+            .build()
+            .expect("Failed to initialize HTTP client.");
+        Self {
+            uri: uri.to_owned(),
+            client: Client::with_client(url, http),
+        }
     }
 
     /// Sends a "publisherUpdate" request to the ROS node.
@@ -34,16 +75,15 @@ impl ClientApi {
     ///
     /// # Returns
     ///
-    /// An `anyhow::Result` indicating whether the request was successful.
+    /// A `Result` indicating whether the request was successful.
     pub async fn publisher_update(
         &self,
         caller_id: &str,
         topic: &str,
         publisher_apis: &Vec<String>,
-    ) -> anyhow::Result<Value> {
+    ) -> Result<Value> {
         let result = self.client.call::<_, _>("publisherUpdate", (caller_id, topic, publisher_apis)).await;
-        
-        Ok(result?)
+        result.map_err(|e| Error::from_client(&self.uri, e))
     }
 
     /// Sends a "paramUpdate" request to the ROS node.
@@ -56,15 +96,57 @@ impl ClientApi {
     ///
     /// # Returns
     ///
-    /// An `anyhow::Result` indicating whether the request was successful.
+    /// A `Result` indicating whether the request was successful.
     pub async fn param_update(
         &self,
         caller_id: &str,
         key: &str,
         value: &Value,
-    ) -> anyhow::Result<Value> {
+    ) -> Result<Value> {
         let result = self.client.call("paramUpdate", (caller_id, key, value)).await;
-        Ok(result?)
+        result.map_err(|e| Error::from_client(&self.uri, e))
+    }
+
+    /// Sends a "getPid" request to the ROS node.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller_id` - A string slice representing the ID of the caller.
+    ///
+    /// # Returns
+    ///
+    /// The response of the node, including its process ID.
+    pub async fn get_pid(&self, caller_id: &str) -> Result<Value> {
+        let result = self.client.call("getPid", (caller_id,)).await;
+        result.map_err(|e| Error::from_client(&self.uri, e))
+    }
+
+    /// Sends a "getPublications" request to the ROS node.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller_id` - A string slice representing the ID of the caller.
+    ///
+    /// # Returns
+    ///
+    /// The response of the node, including the topics it publishes and their types.
+    pub async fn get_publications(&self, caller_id: &str) -> Result<TopicsResponse> {
+        let result = self.client.call("getPublications", (caller_id,)).await;
+        result.map_err(|e| Error::from_client(&self.uri, e))
+    }
+
+    /// Sends a "getSubscriptions" request to the ROS node.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller_id` - A string slice representing the ID of the caller.
+    ///
+    /// # Returns
+    ///
+    /// The response of the node, including the topics it subscribes to and their types.
+    pub async fn get_subscriptions(&self, caller_id: &str) -> Result<TopicsResponse> {
+        let result = self.client.call("getSubscriptions", (caller_id,)).await;
+        result.map_err(|e| Error::from_client(&self.uri, e))
     }
 
     /// Requests the node to shut down
@@ -76,14 +158,16 @@ impl ClientApi {
     ///
     /// # Returns
     ///
-    /// An `anyhow::Result` indicating whether the request was successful.
-
+    /// A `Result` indicating whether the request was successful.
     pub async fn shutdown(
         &self,
         caller_id: &str,
         reason: &str,
-    ) -> anyhow::Result<()> {
-        let result = self.client.call("shutdown", (caller_id, reason)).await;
-        Ok(result?)
+    ) -> Result<()> {
+        // Nodes answer with `(code, statusMessage, ignore)`, which is of no interest here.
+        let result = self.client.call::<_, Value>("shutdown", (caller_id, reason)).await;
+        result
+            .map(|_| ())
+            .map_err(|e| Error::from_client(&self.uri, e))
     }
 }
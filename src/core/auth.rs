@@ -0,0 +1,126 @@
+//! Authentication and authorization of calls to the master, see [`MasterBuilder::auth`].
+//!
+//! [`MasterBuilder::auth`]: super::MasterBuilder::auth
+use std::collections::HashMap;
+
+use dxr::{Fault, TryFromValue, Value};
+use dxr_server::axum::http::{header, HeaderMap};
+
+/// A call to the master API, as seen by an [`AuthProvider`].
+pub struct AuthRequest<'a> {
+    method: &'a str,
+    params: &'a [Value],
+    headers: &'a HeaderMap,
+}
+
+impl<'a> AuthRequest<'a> {
+    pub(crate) fn new(method: &'a str, params: &'a [Value], headers: &'a HeaderMap) -> Self {
+        Self {
+            method,
+            params,
+            headers,
+        }
+    }
+
+    /// The name of the called method, e.g. `registerPublisher`.
+    pub fn method(&self) -> &str {
+        self.method
+    }
+
+    /// The caller ID the call claims to come from. It is not verified by the master.
+    pub fn caller_id(&self) -> Option<String> {
+        self.params
+            .first()
+            .and_then(|v| String::try_from_value(v).ok())
+    }
+
+    /// The arguments of the call, starting with the caller ID.
+    pub fn params(&self) -> &[Value] {
+        self.params
+    }
+
+    /// The value of the HTTP header `name`, if it is present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// The token of an `Authorization: Bearer <token>` header.
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.header(header::AUTHORIZATION.as_str())?
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+    }
+}
+
+/// Why a call was rejected. The caller gets a fault with code 401 or 403 and the message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthError {
+    /// The call carries no or invalid credentials.
+    Unauthenticated(String),
+    /// The caller is known, but not allowed to make the call.
+    Forbidden(String),
+}
+
+impl From<AuthError> for Fault {
+    fn from(e: AuthError) -> Fault {
+        match e {
+            AuthError::Unauthenticated(msg) => Fault::new(401, msg),
+            AuthError::Forbidden(msg) => Fault::new(403, msg),
+        }
+    }
+}
+
+/// Decides who may call the master, e.g. against JWTs, client certificates or static tokens.
+///
+/// Both methods run for every call before it is handled, so they should not block for long.
+pub trait AuthProvider: Send + Sync {
+    /// Validates the credentials of `request` and returns the identity of the caller, e.g. the
+    /// subject of a token.
+    fn authenticate(&self, request: &AuthRequest) -> Result<String, AuthError>;
+
+    /// Decides whether `identity` may make the call. Allows every call by default.
+    fn authorize(&self, identity: &str, request: &AuthRequest) -> Result<(), AuthError> {
+        let _ = (identity, request);
+        Ok(())
+    }
+}
+
+/// Accepts calls that carry one of a fixed set of bearer tokens.
+///
+/// # Example
+///
+/// ```
+/// use ros_core_rs::core::{MasterBuilder, StaticTokenAuth};
+///
+/// let master = MasterBuilder::new("0.0.0.0:11311".parse().unwrap())
+///     .auth(StaticTokenAuth::new().token("s3cret", "robot"))
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StaticTokenAuth {
+    tokens: HashMap<String, String>,
+}
+
+impl StaticTokenAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts `token` and identifies its bearer as `identity`.
+    pub fn token(mut self, token: impl Into<String>, identity: impl Into<String>) -> Self {
+        self.tokens.insert(token.into(), identity.into());
+        self
+    }
+}
+
+impl AuthProvider for StaticTokenAuth {
+    fn authenticate(&self, request: &AuthRequest) -> Result<String, AuthError> {
+        let token = request
+            .bearer_token()
+            .ok_or_else(|| AuthError::Unauthenticated("missing bearer token".to_owned()))?;
+        self.tokens
+            .get(token)
+            .cloned()
+            .ok_or_else(|| AuthError::Unauthenticated("invalid bearer token".to_owned()))
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dxr_client::{Client, ClientBuilder, Url};
+use paste::paste;
+
+use dxr::{TryFromValue, TryToParams, Value};
+
+use super::*;
+use crate::{Error, Result};
+
+pub struct MasterClient {
+    url: Url,
+    client: Client,
+    hooks: Option<Arc<dyn ClientHooks>>,
+}
+
+/// Observes the calls a [`MasterClient`] makes, e.g. to record metrics or count retries, see
+/// [`MasterClient::with_hooks`]. Both methods do nothing by default.
+///
+/// # Example
+///
+/// ```
+/// use ros_core_rs::core::{ClientHooks, MasterClient};
+/// use std::time::Duration;
+///
+/// struct LogCalls;
+///
+/// impl ClientHooks for LogCalls {
+///     fn after_call(
+///         &self,
+///         method: &str,
+///         duration: Duration,
+///         result: Result<&dxr::Value, &ros_core_rs::Error>,
+///     ) {
+///         let outcome = if result.is_ok() { "ok" } else { "failed" };
+///         eprintln!("{method} {outcome} after {duration:?}");
+///     }
+/// }
+///
+/// let uri = "http://localhost:11311".parse().unwrap();
+/// let client = MasterClient::new(&uri).with_hooks(LogCalls);
+/// ```
+pub trait ClientHooks: Send + Sync {
+    /// Called before `method`, e.g. `registerPublisher`, is called on the master.
+    fn before_call(&self, method: &str) {
+        let _ = method;
+    }
+
+    /// Called once `method` returned or failed, with how long the call took. The response is the
+    /// value returned by the master, before it is decoded.
+    fn after_call(&self, method: &str, duration: Duration, result: Result<&Value, &Error>) {
+        let _ = (method, duration, result);
+    }
+}
+
+macro_rules! implement_client_fn {
+    ($name:ident($($v:ident: $t:ty),*)->$response_type:ident) => {
+        paste!{
+            pub async fn [<$name:snake>](&self, $($v: $t),*) -> Result<$response_type>{
+                self.call(MasterEndpoints::$name, ($($v,)*)).await
+            }
+        }
+    };
+}
+
+macro_rules! make_client{
+    ($($name:tt($($v:ident: $t:ty),*)-> $response_type:ident),*) => {
+        $(implement_client_fn!($name($($v: $t),*)-> $response_type);)*
+
+    }
+
+}
+
+impl MasterClient {
+    /// Constructs a new instance of `MasterClient` with the provided `Url`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A `Url` struct representing the ROS master URI to connect to.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterClient;
+    /// use url::Url;
+    ///
+    /// let uri = Url::parse("http://localhost:11311").unwrap();
+    /// let client = MasterClient::new(&uri);
+    /// ```
+    ///
+    /// On Unix, masters listening on a Unix domain socket are reached with URIs such as
+    /// `unix:///run/ros/master.sock`.
+    pub fn new(url: &Url) -> Self {
+        #[cfg(unix)]
+        if let Some(path) = crate::url_to_unix_path(url) {
+            return Self::with_unix_socket(url, path);
+        }
+        let client = ClientBuilder::new(url.clone())
+            .user_agent("master-client")
+            .build();
+        Self {
+            url: url.clone(),
+            client,
+            hooks: None,
+        }
+    }
+
+    /// Like [`MasterClient::new`], but verifies `https://` masters and authenticates to them
+    /// according to `tls`.
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    pub fn with_tls(url: &Url, tls: &crate::tls::TlsConfig) -> Self {
+        let http = tls
+            .apply(Self::http_builder())
+            .build()
+            .expect("Failed to initialize HTTP client.");
+        Self {
+            url: url.clone(),
+            client: Client::with_client(url.clone(), http),
+            hooks: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn with_unix_socket(url: &Url, path: std::path::PathBuf) -> Self {
+        let http = Self::http_builder()
+            .unix_socket(path)
+            .build()
+            .expect("Failed to initialize HTTP client.");
+        // The host is ignored, requests go to the socket.
+        let endpoint = Url::parse("http://localhost/").expect("static URL is valid");
+        Self {
+            url: url.clone(),
+            client: Client::with_client(endpoint, http),
+            hooks: None,
+        }
+    }
+
+    /// Calls `hooks` before and after every call of this client, see [`ClientHooks`].
+    pub fn with_hooks(mut self, hooks: impl ClientHooks + 'static) -> Self {
+        self.hooks = Some(Arc::new(hooks));
+        self
+    }
+
+    /// An HTTP client with the headers `ClientBuilder` of dxr sets.
+    fn http_builder() -> reqwest::ClientBuilder {
+        use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/xml"));
+        reqwest::Client::builder()
+            .user_agent("master-client")
+            .default_headers(headers)
+    }
+
+    async fn call<P: TryToParams, R: TryFromValue>(
+        &self,
+        endpoint: MasterEndpoints,
+        params: P,
+    ) -> Result<R> {
+        if let Some(hooks) = &self.hooks {
+            hooks.before_call(endpoint.as_str());
+        }
+        let start = Instant::now();
+        let response: Result<Value> = self
+            .client
+            .call(endpoint.as_str(), params)
+            .await
+            .map_err(|e| Error::from_client(self.url.as_str(), e));
+        if let Some(hooks) = &self.hooks {
+            hooks.after_call(endpoint.as_str(), start.elapsed(), response.as_ref());
+        }
+        Ok(R::try_from_value(&response?)?)
+    }
+
+    /// Takes a snapshot of the graph with `getSystemState` and `getTopicTypes`.
+    pub async fn graph_snapshot(&self, caller_id: &str) -> Result<crate::graph::GraphSnapshot> {
+        let (_, _, (publishers, subscribers, services)) =
+            self.get_system_state(caller_id).await?;
+        let (_, _, topic_types) = self.get_topic_types(caller_id).await?;
+        let state = SystemState {
+            publishers,
+            subscribers,
+            services,
+        };
+        Ok(crate::graph::GraphSnapshot::new(
+            &state,
+            &topic_types.into_iter().collect(),
+        ))
+    }
+
+    /// Gets the names of all parameters in `namespace`, which is resolved relative to the
+    /// namespace of `caller_id`. This relies on an extension of `getParamNames` that is not
+    /// supported by other ROS masters.
+    pub async fn get_param_names_in(
+        &self,
+        caller_id: &str,
+        namespace: &str,
+    ) -> Result<GetParamNamesResponse> {
+        self.call(MasterEndpoints::GetParamNames, (caller_id, namespace))
+            .await
+    }
+
+    make_client!(
+        RegisterService(caller_id: &str, service: &str, service_api: &str, caller_api: &str) -> RegisterServiceResponse,
+        UnRegisterService(caller_id: &str, service: &str, service_api:  &str) -> UnRegisterServiceResponse,
+        RegisterSubscriber(caller_id: &str, topic: &str, topic_type: &str, caller_api: &str) -> RegisterSubscriberResponse,
+        UnregisterSubscriber(caller_id: &str, topic: &str, caller_api: &str) -> UnRegisterSubscriberResponse,
+        RegisterPublisher(caller_id: &str, topic: &str, topic_type: &str, caller_api: &str) -> RegisterPublisherResponse,
+        UnregisterPublisher(caller_id: &str, topic: &str, caller_api: &str) -> UnRegisterPublisherResponse,
+        LookupNode(caller_id: &str, node_name: &str) -> LookupNodeResponse,
+        GetPublishedTopics(caller_id: &str, subgraph: &str) -> GetPublishedTopicsResponse,
+        GetTopicTypes(caller_id: &str) -> GetTopicTypesResponse,
+        GetSystemState(caller_id: &str) -> GetSystemStateResponse,
+        GetUri(caller_id: &str) -> GetUriResponse,
+        GetPid(caller_id: &str) -> GetPidResponse,
+        LookupService(caller_id: &str, service: &str) -> LookupServiceResponse,
+        DeleteParam(caller_id: &str, key: &str) -> DeleteParamResponse,
+        // TODO():  correct args
+        SetParam(caller_id: &str, key: &str, value: &Value) -> SetParamResponse,
+        GetParam(caller_id: &str, key: &str) -> GetParamResponse,
+        SearchParam(caller_id: &str, key: &str) -> SearchParamResponse,
+        // TODO():  correct args
+        SubscribeParam(caller_id: &str, caller_api: &str, keys: &str) -> SubscribeParamResponse,
+        UnsubscribeParam(caller_id: &str, caller_api: &str, key: &str) -> UnSubscribeParamResponse,
+        HasParam(caller_id: &str, key: &str) -> HasParamResponse,
+        GetParamNames(caller_id: &str) -> GetParamNamesResponse,
+        GetTopicInfo(caller_id: &str, topic: &str) -> GetTopicInfoResponse,
+        GetServiceInfo(caller_id: &str, service: &str) -> GetServiceInfoResponse,
+        GetTopics(caller_id: &str, subgraph: &str) -> GetTopicsResponse,
+        GetParamSubscriptions(caller_id: &str, key: &str) -> GetParamSubscriptionsResponse,
+        GetNodeInfo(caller_id: &str, node: &str) -> GetNodeInfoResponse,
+        GetSessions(caller_id: &str) -> GetSessionsResponse,
+        GetTopologyViolations(caller_id: &str) -> GetTopologyViolationsResponse,
+        SetTopicHints(caller_id: &str, topic: &str, hints: &TopicHints) -> SetTopicHintsResponse,
+        GetTopicHints(caller_id: &str, topic: &str) -> GetTopicHintsResponse,
+        GetShutdownHistory(caller_id: &str) -> GetShutdownHistoryResponse,
+        ReloadConfig(caller_id: &str) -> ReloadConfigResponse,
+        GetNamespaceStats(caller_id: &str) -> GetNamespaceStatsResponse,
+        SubscribeParamWithHistory(caller_id: &str, caller_api: &str, key: &str) -> SubscribeParamWithHistoryResponse,
+        GetParamCached(caller_id: &str, caller_api: &str, key: &str) -> GetParamCachedResponse,
+        Heartbeat(caller_id: &str) -> HeartbeatResponse
+    );
+}
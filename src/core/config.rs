@@ -0,0 +1,354 @@
+//! Settings of a running master that can be changed without restarting it, see
+//! [`MasterBuilder::config_file`].
+//!
+//! [`MasterBuilder::config_file`]: super::MasterBuilder::config_file
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use dxr::{TryFromValue, TryToValue, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// What unauthorized callers and the logs get instead of sensitive parameter values.
+pub const REDACTED: &str = "<redacted>";
+
+/// Access rules, blocked topics, sensitive parameters, rate limits, the networks the master calls
+/// nodes in, how it notifies them and the log level of a master.
+///
+/// Name patterns are either exact names, `ns/*` for everything below the namespace `ns`, or `*`
+/// for everything. Networks are given in CIDR notation, e.g. `10.0.0.0/8`, or as single
+/// addresses.
+///
+/// # Example
+///
+/// ```
+/// use ros_core_rs::core::RuntimeConfig;
+///
+/// let config = RuntimeConfig::from_yaml(
+///     "log_level: info
+/// blocked_topics: [/debug/*]
+/// acl:
+///   setParam: [/teleop, /admin/*]
+/// rate_limit: 50
+/// sensitive_params: [/credentials/*]
+/// sensitive_readers: [/uploader]
+/// callback_networks: [10.0.0.0/8, 192.168.0.0/16, 127.0.0.0/8, '::1']
+/// callback_timeout: 2.5",
+/// )
+/// .unwrap();
+/// assert!(config.is_topic_blocked("/debug/image"));
+/// assert!(!config.is_allowed("setParam", "/camera"));
+/// assert!(config.is_sensitive("/credentials/token"));
+/// assert!(!config.may_read_sensitive("/camera"));
+/// assert!(!config.may_call("203.0.113.7".parse().unwrap()));
+/// assert_eq!(config.callback_timeout(), Some(std::time::Duration::from_millis(2500)));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// Caps the verbosity of all log output, e.g. `info`. The level is left as is if unset.
+    pub log_level: Option<String>,
+    /// Topics nodes can't publish or subscribe to.
+    pub blocked_topics: Vec<String>,
+    /// The caller IDs that may call each method of the master API, by method name. Methods that
+    /// aren't listed can be called by everybody.
+    pub acl: BTreeMap<String, Vec<String>>,
+    /// How many calls per second each caller ID may make, with bursts of up to a second's worth.
+    /// Unlimited if unset.
+    pub rate_limit: Option<f64>,
+    /// Parameters whose values are redacted in logs, kept from callers that aren't
+    /// `sensitive_readers`, and encrypted or left out when the parameters are dumped.
+    pub sensitive_params: Vec<String>,
+    /// The caller IDs that may read sensitive parameters. Everybody can if empty.
+    pub sensitive_readers: Vec<String>,
+    /// The networks of the node APIs the master may call, e.g. to send `publisherUpdate`. Nodes
+    /// are called anywhere if empty.
+    pub callback_networks: Vec<String>,
+    /// Networks the master never calls node APIs in, even if they are in `callback_networks`.
+    pub blocked_callback_networks: Vec<String>,
+    /// How many seconds the master waits for nodes to answer its calls, e.g. `publisherUpdate`.
+    /// Overrides [`MasterBuilder::callback_timeout`] if set.
+    ///
+    /// [`MasterBuilder::callback_timeout`]: super::MasterBuilder::callback_timeout
+    pub callback_timeout: Option<f64>,
+    /// How many parameter subscriptions the master accepts. Overrides
+    /// [`MasterBuilder::max_param_subscriptions`] if set.
+    ///
+    /// [`MasterBuilder::max_param_subscriptions`]: super::MasterBuilder::max_param_subscriptions
+    pub max_param_subscriptions: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// Parses and validates a configuration from YAML or JSON.
+    pub fn from_yaml(contents: &str) -> Result<Self> {
+        let config: RuntimeConfig =
+            serde_yaml::from_str(contents).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        config.log_level()?;
+        if let Some(rate) = config.rate_limit {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(Error::InvalidConfig(format!(
+                    "rate_limit must be a positive number of calls per second, got {rate}"
+                )));
+            }
+        }
+        if let Some(timeout) = config.callback_timeout {
+            if !timeout.is_finite() || timeout <= 0.0 {
+                return Err(Error::InvalidConfig(format!(
+                    "callback_timeout must be a positive number of seconds, got {timeout}"
+                )));
+            }
+        }
+        for network in config
+            .callback_networks
+            .iter()
+            .chain(&config.blocked_callback_networks)
+        {
+            parse_network(network)?;
+        }
+        Ok(config)
+    }
+
+    /// The configured log level, parsed.
+    pub(crate) fn log_level(&self) -> Result<Option<log::LevelFilter>> {
+        self.log_level
+            .as_deref()
+            .map(|level| {
+                level
+                    .parse()
+                    .map_err(|_| Error::InvalidConfig(format!("unknown log level '{level}'")))
+            })
+            .transpose()
+    }
+
+    /// The configured callback timeout, if any.
+    pub fn callback_timeout(&self) -> Option<Duration> {
+        self.callback_timeout.map(Duration::from_secs_f64)
+    }
+
+    /// Whether `topic` matches one of the blocked topics.
+    pub fn is_topic_blocked(&self, topic: &str) -> bool {
+        self.blocked_topics.iter().any(|p| matches(p, topic))
+    }
+
+    /// Whether the parameter `key` matches one of the sensitive parameters.
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        self.sensitive_params.iter().any(|p| matches(p, key))
+    }
+
+    /// Whether `caller_id` may read the values of sensitive parameters.
+    pub fn may_read_sensitive(&self, caller_id: &str) -> bool {
+        self.sensitive_readers.is_empty()
+            || self.sensitive_readers.iter().any(|p| matches(p, caller_id))
+    }
+
+    /// Passes the sensitive parameters in `value`, the parameter `key`, through `f`, which
+    /// returns their replacement or `None` to leave them out.
+    pub(crate) fn map_sensitive<E>(
+        &self,
+        key: &str,
+        value: Value,
+        f: &mut impl FnMut(&str, Value) -> std::result::Result<Option<Value>, E>,
+    ) -> std::result::Result<Option<Value>, E> {
+        if self.is_sensitive(key) {
+            return f(key, value);
+        }
+        if self.sensitive_params.is_empty() {
+            return Ok(Some(value));
+        }
+        let Ok(members) = HashMap::<String, Value>::try_from_value(&value) else {
+            return Ok(Some(value));
+        };
+        let mut mapped = HashMap::with_capacity(members.len());
+        for (name, member) in members {
+            let member_key = format!("{}/{name}", key.trim_end_matches('/'));
+            if let Some(member) = self.map_sensitive(&member_key, member, f)? {
+                mapped.insert(name, member);
+            }
+        }
+        // A dictionary of values converts back into a value.
+        Ok(Some(mapped.try_to_value().unwrap_or(value)))
+    }
+
+    /// Replaces the sensitive parameters in `value`, the parameter `key`, with [`REDACTED`].
+    pub(crate) fn redact(&self, key: &str, value: Value) -> Value {
+        let mut redact = |_: &str, _| Ok::<_, std::convert::Infallible>(Some(Value::string(REDACTED.to_owned())));
+        match self.map_sensitive(key, value, &mut redact) {
+            Ok(Some(value)) => value,
+            _ => Value::string(REDACTED.to_owned()),
+        }
+    }
+
+    /// Whether the master may call node APIs at `ip`.
+    pub fn may_call(&self, ip: IpAddr) -> bool {
+        let within = |networks: &[String]| {
+            networks
+                .iter()
+                .filter_map(|network| parse_network(network).ok())
+                .any(|(network, prefix)| in_network(ip, network, prefix))
+        };
+        (self.callback_networks.is_empty() || within(&self.callback_networks))
+            && !within(&self.blocked_callback_networks)
+    }
+
+    /// Whether any networks are configured, i.e. whether [`RuntimeConfig::may_call`] can refuse
+    /// an address.
+    pub(crate) fn restricts_callbacks(&self) -> bool {
+        !self.callback_networks.is_empty() || !self.blocked_callback_networks.is_empty()
+    }
+
+    /// Whether `caller_id` may call `method`.
+    pub fn is_allowed(&self, method: &str, caller_id: &str) -> bool {
+        match self.acl.get(method) {
+            Some(callers) => callers.iter().any(|p| matches(p, caller_id)),
+            None => true,
+        }
+    }
+}
+
+/// Whether `name` matches the pattern, see [`RuntimeConfig`].
+fn matches(pattern: &str, name: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(namespace) => name
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.starts_with('/')),
+        None => pattern == name,
+    }
+}
+
+/// Parses a network such as `10.0.0.0/8` into its address and prefix length.
+fn parse_network(network: &str) -> Result<(IpAddr, u8)> {
+    let invalid = || Error::InvalidConfig(format!("invalid network '{network}'"));
+    let (addr, prefix) = match network.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (network, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+    let bits = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+        None => bits,
+    };
+    if prefix > bits {
+        return Err(invalid());
+    }
+    Ok((addr, prefix))
+}
+
+/// Whether the first `prefix` bits of `ip` and `network` are equal. IPv4 addresses mapped to IPv6
+/// belong to IPv4 networks.
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip.to_canonical(), network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// The calls a caller may still make right away under the rate limit.
+pub(crate) struct RateBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateBucket {
+    pub(crate) fn new(rate: f64, now: Instant) -> Self {
+        Self {
+            tokens: rate.max(1.0),
+            updated: now,
+        }
+    }
+
+    /// Takes a call out of the bucket at `now`, refilled at `rate` calls per second. Returns false
+    /// if the caller exceeded the limit.
+    pub(crate) fn take(&mut self, rate: f64, now: Instant) -> bool {
+        let refill = now.saturating_duration_since(self.updated).as_secs_f64() * rate;
+        self.tokens = (self.tokens + refill).min(rate.max(1.0));
+        self.updated = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[test]
+fn test_redact() {
+    let config = RuntimeConfig {
+        sensitive_params: vec!["/robot/secrets/*".to_owned(), "/token".to_owned()],
+        ..Default::default()
+    };
+    let value = crate::rosparam::parse_yaml(
+        "robot: {name: r2, secrets: {key: abc, pin: 1234}}
+token: xyz",
+    )
+    .unwrap();
+    let expected = crate::rosparam::parse_yaml(
+        "robot: {name: r2, secrets: {key: <redacted>, pin: <redacted>}}
+token: <redacted>",
+    )
+    .unwrap();
+    assert_eq!(config.redact("/", value), expected);
+    assert_eq!(
+        config.redact("/token", Value::string("xyz".to_owned())),
+        Value::string(REDACTED.to_owned())
+    );
+}
+
+#[test]
+fn test_networks() {
+    let config = RuntimeConfig {
+        callback_networks: vec!["10.0.0.0/8".to_owned(), "fd00::/8".to_owned()],
+        blocked_callback_networks: vec!["10.0.0.1".to_owned()],
+        ..Default::default()
+    };
+    assert!(config.may_call("10.1.2.3".parse().unwrap()));
+    assert!(config.may_call("::ffff:10.1.2.3".parse().unwrap()));
+    assert!(config.may_call("fd12::1".parse().unwrap()));
+    assert!(!config.may_call("10.0.0.1".parse().unwrap()));
+    assert!(!config.may_call("8.8.8.8".parse().unwrap()));
+    assert!(RuntimeConfig::default().may_call("8.8.8.8".parse().unwrap()));
+    assert!(parse_network("0.0.0.0/0").is_ok());
+    assert!(parse_network("10.0.0.0/33").is_err());
+    assert!(parse_network("robot.local").is_err());
+}
+
+#[test]
+fn test_matches() {
+    assert!(matches("*", "/anything"));
+    assert!(matches("/debug/*", "/debug/image"));
+    assert!(matches("/debug/*", "/debug/a/b"));
+    assert!(!matches("/debug/*", "/debug"));
+    assert!(!matches("/debug/*", "/debugger/image"));
+    assert!(matches("/teleop", "/teleop"));
+    assert!(!matches("/teleop", "/teleop2"));
+}
+
+#[test]
+fn test_from_yaml() {
+    assert!(matches!(
+        RuntimeConfig::from_yaml("rate_limit: [fast]"),
+        Err(Error::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn test_callback_timeout() {
+    let config = RuntimeConfig::from_yaml("callback_timeout: 0.5\nmax_param_subscriptions: 10").unwrap();
+    assert_eq!(config.callback_timeout(), Some(Duration::from_millis(500)));
+    assert_eq!(config.max_param_subscriptions, Some(10));
+    assert_eq!(RuntimeConfig::default().callback_timeout(), None);
+    assert!(RuntimeConfig::from_yaml("callback_timeout: 0").is_err());
+    assert!(RuntimeConfig::from_yaml("callback_timeout: -1").is_err());
+}
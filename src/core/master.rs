@@ -0,0 +1,5402 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+use url::Url;
+use uuid::ContextV1;
+
+use dxr_server::{async_trait, Handler, HandlerResult};
+use dxr_server::{
+    axum::{
+        self,
+        extract::{DefaultBodyLimit, Request, State},
+        http::{header, HeaderMap, StatusCode},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
+    },
+    RouteBuilder, Server,
+};
+
+use dxr::{TryFromParams, TryFromValue, TryToValue, Value};
+
+use super::config::RateBucket;
+use super::names::{check_names, is_legal_name, param_path, resolve};
+use super::namespace_stats::{Change, NamespaceActivity};
+use super::param_subscriptions::{ParamSubscription, ParamSubscriptions};
+use super::*;
+use crate::client_api::ClientApi;
+use crate::graph::{GraphDiff, GraphSnapshot, ServiceProvider, ServiceRegistry};
+use crate::log_throttle::{warn_throttled, LogThrottle};
+use crate::{Error, Result};
+
+/// Default for [`MasterBuilder::max_request_size`]. Large enough for big robot descriptions.
+pub const DEFAULT_MAX_REQUEST_SIZE: usize = 16 * 1024 * 1024;
+
+/// Default for [`MasterBuilder::log_throttle`].
+pub const DEFAULT_LOG_THROTTLE: Duration = Duration::from_secs(10);
+
+/// Default for [`MasterBuilder::callback_timeout`].
+pub const DEFAULT_CALLBACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often nodes should call `heartbeat` if the master doesn't monitor the liveness of nodes,
+/// see [`MasterBuilder::monitor_liveness`].
+pub const DEFAULT_HEARTBEAT_PERIOD: Duration = Duration::from_secs(10);
+
+/// How long `getNodeInfo` waits for a node to answer before considering it unhealthy.
+const NODE_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many graph events a receiver may fall behind before it misses some, see
+/// [`Master::subscribe_events`].
+const EVENT_CAPACITY: usize = 1024;
+
+/// How long the session of a caller that never registered is kept after its last call. Tools like
+/// `rostopic` use a new caller ID for every invocation.
+const UNREGISTERED_SESSION_TIMEOUT: chrono::Duration = chrono::Duration::minutes(10);
+
+/// How many `shutdown` calls are kept for `getShutdownHistory`.
+const SHUTDOWN_HISTORY_LEN: usize = 100;
+
+/// A node registering the same topic or service again within this window is reported as a
+/// possibly crash-looping node.
+const REREGISTRATION_WARN_WINDOW: chrono::Duration = chrono::Duration::seconds(5);
+
+/// How long after a registration the master checks whether the subscribers of its topic know all
+/// publishers, see [`backfill_publisher_updates`].
+const PUBLISHER_BACKFILL_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+struct Session {
+    api_uri: String,
+    first_seen: NaiveDateTime,
+    last_seen: NaiveDateTime,
+    requests: usize,
+    faults: usize,
+}
+
+/// A change of the graph or the parameters, see [`Master::subscribe_events`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphEvent {
+    PublisherRegistered { topic: String, node: String },
+    PublisherUnregistered { topic: String, node: String },
+    SubscriberRegistered { topic: String, node: String },
+    SubscriberUnregistered { topic: String, node: String },
+    ServiceRegistered { service: String, node: String },
+    ServiceUnregistered { service: String, node: String },
+    ParamChanged { key: String, value: Value },
+    ParamDeleted { key: String },
+    /// A node registered from a new API URI, the previous instance was asked to shut down.
+    NodeReplaced { node: String, old_api: String, new_api: String },
+    /// A node registered a service that `old_node` provided, which was unregistered, see
+    /// [`ServiceOwnership::Replace`].
+    ServiceReplaced { service: String, old_node: String, new_node: String },
+    /// A node stopped answering at `api` and all its registrations were removed, see
+    /// [`MasterBuilder::monitor_liveness`].
+    NodeUnresponsive { node: String, api: String },
+    /// The differences of the graph from the expected topology changed, see
+    /// [`MasterBuilder::expected_topology`]. Empty `violations` mean the graph matches again.
+    TopologyChanged { violations: GraphDiff },
+}
+
+/// The role a node registered itself in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Registration {
+    Publisher,
+    Subscriber,
+    Service,
+}
+
+impl Registration {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Registration::Publisher => "publisher",
+            Registration::Subscriber => "subscriber",
+            Registration::Service => "service provider",
+        }
+    }
+
+    fn registered_event(&self, name: &str, node: &str) -> GraphEvent {
+        let (name, node) = (name.to_owned(), node.to_owned());
+        match self {
+            Registration::Publisher => GraphEvent::PublisherRegistered { topic: name, node },
+            Registration::Subscriber => GraphEvent::SubscriberRegistered { topic: name, node },
+            Registration::Service => GraphEvent::ServiceRegistered { service: name, node },
+        }
+    }
+
+    fn unregistered_event(&self, name: &str, node: &str) -> GraphEvent {
+        let (name, node) = (name.to_owned(), node.to_owned());
+        match self {
+            Registration::Publisher => GraphEvent::PublisherUnregistered { topic: name, node },
+            Registration::Subscriber => GraphEvent::SubscriberUnregistered { topic: name, node },
+            Registration::Service => GraphEvent::ServiceUnregistered { service: name, node },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RegistrationTimes {
+    first: NaiveDateTime,
+    last: NaiveDateTime,
+}
+
+impl RegistrationTimes {
+    fn at(now: NaiveDateTime) -> Self {
+        Self {
+            first: now,
+            last: now,
+        }
+    }
+}
+
+/// Struct containing information about ROS data.
+pub struct RosData {
+    // RwLocks to allow for concurrent read/write access to data
+    service_list: RwLock<Services>, // stores information about available services
+    nodes: RwLock<Nodes>,           // stores information about nodes connected to the ROS network
+    topics: RwLock<Topics>,         // stores information about available topics
+    subscriptions: RwLock<Subscriptions>, // stores information about topic subscriptions
+    publications: RwLock<Publishers>, // stores information about topic publishers
+    parameters: RwLock<Parameters>, // stores information about ROS parameters
+    parameter_keys: RwLock<Option<Arc<HashSet<String>>>>, // cached names of all parameters, reset on every write
+    parameter_subscriptions: RwLock<ParamSubscriptions>, // stores information about parameter subscriptions
+    topic_times: RwLock<HashMap<String, RegistrationTimes>>, // first/last registration on each topic
+    topic_hints: RwLock<HashMap<String, TopicHints>>, // QoS hints set with setTopicHints
+    namespace_activity: Mutex<NamespaceActivity>, // registration churn and param writes per namespace
+    reserved_services: HashMap<String, String>, // services only the given node may provide
+    expected_topology: Option<GraphSnapshot>, // the graph the system should converge to
+    topology_violations: RwLock<GraphDiff>, // differences from the expected topology
+    registration_times: RwLock<HashMap<(Registration, String, String), RegistrationTimes>>, // first/last registration per (role, name, node)
+    registration_counts: Mutex<HashMap<String, usize>>, // publishers, subscribers, services and parameter subscriptions of each node
+    sessions: RwLock<HashMap<String, Session>>, // calls made by each caller ID
+    events: broadcast::Sender<GraphEvent>,      // changes for in-process subscribers
+    shutdown_history: RwLock<VecDeque<ShutdownRecord>>, // the latest shutdown calls to nodes
+    param_history: Mutex<VecDeque<ParamChange>>, // the latest parameter changes
+    param_history_len: usize, // how many parameter changes to keep
+    service_types: Mutex<HashMap<(String, String), String>>, // probed type per (service, service API)
+    shutdown_hook: Option<ShutdownHook>, // called before every shutdown call to a node
+    clock: Arc<dyn TimeSource>, // timestamps, timeouts and rates are measured with this
+    config: RwLock<RuntimeConfig>, // access rules and limits that can be reloaded
+    config_file: Option<PathBuf>,  // where the runtime configuration is reloaded from
+    rate_buckets: Mutex<HashMap<String, RateBucket>>, // calls left under the rate limit per caller
+    log_throttle: LogThrottle, // rate limit for recurring warnings
+    prune_param_subscribers_after: Option<Duration>, // drop param subscriptions failing this long
+    max_param_subscriptions: Option<usize>, // reject new param subscriptions beyond this many
+    topic_retention: TopicRetention, // when to drop topics without publishers and subscribers
+    service_ownership: ServiceOwnership, // what happens when a second node registers a service
+    name_policy: Option<Box<dyn NamePolicy>>, // rewrites or rejects names after they are resolved
+    idle_topics: Mutex<HashMap<String, Instant>>, // since when topics have had no publishers and subscribers
+    callback_timeout: Duration, // how long to wait for nodes to answer calls of the master
+    order_publishers_by_latency: bool, // return the fastest publishers first
+    probe_service_providers: bool, // skip service providers that don't answer in lookupService
+    liveness: LivenessCheck, // unregister nodes that stop answering getPid
+    heartbeats: Mutex<HashMap<String, Instant>>, // last heartbeat call of each node
+    node_latency: RwLock<HashMap<String, NodeLatency>>, // round-trip times of calls to each node API
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    client_tls: Option<crate::tls::TlsConfig>, // TLS settings for calls to https:// nodes
+    #[cfg(feature = "param-encryption")]
+    param_key: Option<super::param_crypto::ParamKey>, // encrypts sensitive parameters in dumps
+    advertised_uri: Url, // the URI of the master as seen by nodes, returned by getUri
+    uri: SocketAddr,                                         // the address of the ROS network
+}
+
+/// Round-trip times of the master's calls to a node API, see
+/// [`MasterBuilder::order_publishers_by_latency`].
+#[derive(Debug, Default)]
+struct NodeLatency {
+    average: Duration, // moving average of the successful calls
+    failures: usize,   // failed calls since the last successful one
+}
+
+/// Called with the node, its API URI and the reason before the master asks a node to shut down,
+/// see [`MasterBuilder::on_shutdown_node`].
+type ShutdownHook = Arc<dyn Fn(&str, &str, &str) + Send + Sync>;
+
+/// Called with the local address once the master listens, see [`MasterBuilder::on_ready`].
+type ReadyHook = Arc<dyn Fn(SocketAddr) + Send + Sync>;
+
+impl RosData {
+    /// Returns a client for the API of the node at `uri` that gives up after the callback timeout.
+    fn client_api(&self, uri: &str) -> ClientApi {
+        let timeout = self
+            .config
+            .read()
+            .unwrap()
+            .callback_timeout()
+            .unwrap_or(self.callback_timeout);
+        #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+        if let Some(tls) = &self.client_tls {
+            return ClientApi::with_tls(uri, timeout, tls);
+        }
+        ClientApi::with_timeout(uri, timeout)
+    }
+
+    /// Awaits `call` to the node API `api_uri` and records how long it took, if publishers are
+    /// ordered by latency.
+    async fn timed<T, E>(
+        &self,
+        api_uri: &str,
+        call: impl std::future::Future<Output = std::result::Result<T, E>>,
+    ) -> std::result::Result<T, E> {
+        let start = self.clock.instant();
+        let result = call.await;
+        let elapsed = self.clock.instant().saturating_duration_since(start);
+        if self.order_publishers_by_latency {
+            let mut latencies = self.node_latency.write().unwrap();
+            let latency = latencies.entry(api_uri.to_owned()).or_default();
+            match result {
+                Ok(_) if latency.failures == 0 && !latency.average.is_zero() => {
+                    latency.average = (latency.average * 3 + elapsed) / 4;
+                }
+                Ok(_) => {
+                    latency.average = elapsed;
+                    latency.failures = 0;
+                }
+                Err(_) => latency.failures += 1,
+            }
+        }
+        result
+    }
+
+    /// Orders the publisher API URIs `apis` by the latency measured to them if enabled, with
+    /// nodes whose last call failed last and those that were never called in between.
+    fn order_publishers(&self, apis: &mut [String]) {
+        if !self.order_publishers_by_latency {
+            return;
+        }
+        let latencies = self.node_latency.read().unwrap();
+        apis.sort_by_key(|api| match latencies.get(api) {
+            Some(latency) if latency.failures == 0 => (0, latency.average),
+            None => (1, Duration::ZERO),
+            Some(latency) => (2, Duration::from_secs(latency.failures as u64)),
+        });
+    }
+
+    /// Replaces the runtime configuration and applies its log level. The calls callers have left
+    /// under the rate limit are kept unless the limit changed.
+    fn apply_config(&self, config: RuntimeConfig) {
+        if let Ok(Some(level)) = config.log_level() {
+            log::set_max_level(level);
+        }
+        let previous = std::mem::replace(&mut *self.config.write().unwrap(), config);
+        if previous.rate_limit != self.config.read().unwrap().rate_limit {
+            self.rate_buckets.lock().unwrap().clear();
+        }
+    }
+
+    /// Reads the runtime configuration file again and applies it. The current configuration stays
+    /// in place if the file can't be read or is invalid.
+    fn reload_config(&self) -> Result<()> {
+        let path = self.config_file.as_ref().ok_or_else(|| {
+            Error::InvalidConfig("the master has no configuration file".to_owned())
+        })?;
+        let config = read_config(path)?;
+        self.apply_config(config);
+        log::info!("Reloaded the configuration from {}", path.display());
+        Ok(())
+    }
+
+    /// Checks a call against the access rules and the rate limit of the runtime configuration.
+    fn check_policy(&self, method: &str, caller_id: &str) -> std::result::Result<(), dxr::Fault> {
+        let config = self.config.read().unwrap();
+        if !config.is_allowed(method, caller_id) {
+            return Err(dxr::Fault::new(403, format!("{caller_id} may not call {method}")));
+        }
+        if let Some(rate) = config.rate_limit {
+            let mut buckets = self.rate_buckets.lock().unwrap();
+            let bucket = buckets
+                .entry(caller_id.to_owned())
+                .or_insert_with(|| RateBucket::new(rate, self.clock.instant()));
+            if !bucket.take(rate, self.clock.instant()) {
+                warn_throttled!(
+                    self.log_throttle,
+                    format!("rate limit {caller_id}"),
+                    "{caller_id} exceeds the rate limit of {rate} calls per second"
+                );
+                return Err(dxr::Fault::new(429, "rate limit exceeded".to_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if the configuration doesn't allow calling the node API at `api_uri`. Host names are
+    /// resolved, and all their addresses must be allowed.
+    async fn check_callback(&self, api_uri: &str) -> Result<()> {
+        let config = self.config.read().unwrap().clone();
+        if !config.restricts_callbacks() {
+            return Ok(());
+        }
+        let refused = || {
+            warn_throttled!(
+                self.log_throttle,
+                format!("callback {api_uri}"),
+                "Not calling {api_uri}, which is in a network the master may not call"
+            );
+            Err(Error::CallbackRefused {
+                uri: api_uri.to_owned(),
+            })
+        };
+        let Some((host, port)) = Url::parse(api_uri)
+            .ok()
+            .and_then(|url| Some((url.host()?.to_owned(), url.port_or_known_default()?)))
+        else {
+            return refused();
+        };
+        let ips: Vec<IpAddr> = match host {
+            url::Host::Ipv4(ip) => vec![ip.into()],
+            url::Host::Ipv6(ip) => vec![ip.into()],
+            url::Host::Domain(domain) => match tokio::net::lookup_host((domain, port)).await {
+                Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+                Err(_) => Vec::new(),
+            },
+        };
+        if ips.is_empty() || !ips.iter().all(|ip| config.may_call(*ip)) {
+            return refused();
+        }
+        Ok(())
+    }
+
+    /// Asks the node at `api_uri` to shut down and records the call in the shutdown history.
+    async fn shutdown_node(&self, node: &str, api_uri: &str, reason: &str) -> Result<()> {
+        self.check_callback(api_uri).await?;
+        if let Some(hook) = &self.shutdown_hook {
+            hook(node, api_uri, reason);
+        }
+        let time = self.clock.now().naive_utc();
+        let res = self.client_api(api_uri).shutdown("/master", reason).await;
+        let mut history = self.shutdown_history.write().unwrap();
+        if history.len() == SHUTDOWN_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(ShutdownRecord {
+            node: node.to_owned(),
+            api_uri: api_uri.to_owned(),
+            reason: reason.to_owned(),
+            time,
+            acknowledged: res.is_ok(),
+        });
+        res
+    }
+
+    /// Returns the names of all parameters.
+    ///
+    /// The set is built once and cached until the parameters are modified again.
+    fn param_keys(&self) -> Arc<HashSet<String>> {
+        if let Some(keys) = self.parameter_keys.read().unwrap().as_ref() {
+            return keys.clone();
+        }
+        // Lock the parameters before the cache, like writers do, so the cached set can't be stale.
+        let params = self.parameters.read().unwrap();
+        let mut cache = self.parameter_keys.write().unwrap();
+        cache
+            .get_or_insert_with(|| Arc::new(params.get_keys().into_iter().collect()))
+            .clone()
+    }
+
+    /// Whether the parameter or namespace `key`, a resolved name, is set. The root `/` always is.
+    fn has_param(&self, key: &str) -> bool {
+        key == "/" || self.param_keys().contains(key)
+    }
+
+    /// Drops the cached parameter names. Must be called while still holding the write lock on the
+    /// parameters.
+    fn invalidate_param_keys(&self) {
+        *self.parameter_keys.write().unwrap() = None;
+    }
+
+    /// Sends `event` to the receivers of [`Master::subscribe_events`], if there are any.
+    fn emit(&self, event: GraphEvent) {
+        let change = match &event {
+            GraphEvent::PublisherRegistered { topic: name, .. }
+            | GraphEvent::SubscriberRegistered { topic: name, .. }
+            | GraphEvent::ServiceRegistered { service: name, .. } => Some((name, Change::Registered)),
+            GraphEvent::PublisherUnregistered { topic: name, .. }
+            | GraphEvent::SubscriberUnregistered { topic: name, .. }
+            | GraphEvent::ServiceUnregistered { service: name, .. } => {
+                Some((name, Change::Unregistered))
+            }
+            GraphEvent::ParamChanged { key: name, .. } | GraphEvent::ParamDeleted { key: name } => {
+                Some((name, Change::ParamWritten))
+            }
+            GraphEvent::NodeReplaced { .. }
+            | GraphEvent::ServiceReplaced { .. }
+            | GraphEvent::NodeUnresponsive { .. }
+            | GraphEvent::TopologyChanged { .. } => None,
+        };
+        if let Some((name, change)) = change {
+            let now = self.clock.instant();
+            self.namespace_activity.lock().unwrap().record(name, change, now);
+        }
+        let _ = self.events.send(event);
+    }
+
+    fn topology_violations(&self) -> Option<GraphDiff> {
+        self.expected_topology.as_ref()?;
+        Some(self.topology_violations.read().unwrap().clone())
+    }
+
+    /// Compares the graph with the expected topology and reports when the differences change.
+    fn check_topology(&self) {
+        let Some(expected) = &self.expected_topology else {
+            return;
+        };
+        let topic_types = self.topics.read().unwrap().clone();
+        let violations = expected.diff(&GraphSnapshot::new(&self.system_state(), &topic_types));
+        let mut current = self.topology_violations.write().unwrap();
+        if *current == violations {
+            return;
+        }
+        if violations.is_empty() {
+            log::info!("The graph matches the expected topology");
+        } else {
+            log::warn!(
+                "The graph differs from the expected topology:\n{}",
+                violations.to_string().trim_end()
+            );
+        }
+        *current = violations.clone();
+        drop(current);
+        self.emit(GraphEvent::TopologyChanged { violations });
+    }
+
+    /// Forgets the types and registration times of topics that have had neither publishers nor
+    /// subscribers for `period`. Topics that were declared but never registered are kept.
+    fn collect_idle_topics(&self, period: Duration) {
+        // Registrations take the publications before the topics, so a topic can't be forgotten
+        // while a publisher is registering it.
+        let publications = self.publications.read().unwrap();
+        let subscriptions = self.subscriptions.read().unwrap();
+        let mut topics = self.topics.write().unwrap();
+        let mut topic_times = self.topic_times.write().unwrap();
+        let mut idle_topics = self.idle_topics.lock().unwrap();
+        let now = self.clock.instant();
+        for topic in topics.keys().filter(|topic| topic_times.contains_key(*topic)) {
+            if publications.contains_key(topic) || subscriptions.contains_key(topic) {
+                idle_topics.remove(topic);
+            } else {
+                idle_topics.entry(topic.clone()).or_insert(now);
+            }
+        }
+        idle_topics.retain(|topic, since| {
+            if !topics.contains_key(topic) {
+                return false;
+            }
+            if now.duration_since(*since) < period {
+                return true;
+            }
+            log::debug!("Forgetting the idle topic {topic}");
+            topics.remove(topic);
+            topic_times.remove(topic);
+            false
+        });
+    }
+
+    /// Returns the status message to ignore the unregistration of `name` by `caller_id` with, in
+    /// the words of rosmaster, if the node is not registered or registered from another API than
+    /// `caller_api`. Such calls come from an earlier instance of the node and must not remove the
+    /// registrations of the current one.
+    fn check_unregistering_node(
+        &self,
+        caller_id: &str,
+        caller_api: Option<&str>,
+        name: &str,
+    ) -> Option<String> {
+        let nodes = self.nodes.read().unwrap();
+        let Some(registered) = nodes.get(caller_id) else {
+            return Some(format!("[{caller_id}] is not a registered node"));
+        };
+        match caller_api {
+            Some(caller_api) if registered != caller_api => {
+                log::debug!("[{caller_id}] is registered at {registered}, not at {caller_api}, ignoring");
+                Some(format!("[{caller_id}] is not a provider of [{name}]"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks `name`, a name of a `kind` used by `caller_id`, resolves it and lets the
+    /// [`NamePolicy`] rewrite or reject it. Returns the status message to reject the call with if
+    /// the name is illegal or rejected.
+    fn resolve_name(
+        &self,
+        caller_id: &str,
+        kind: NameKind,
+        name: &str,
+    ) -> std::result::Result<String, String> {
+        if let Some(err_msg) = check_names(caller_id, kind, name) {
+            return Err(err_msg);
+        }
+        self.apply_name_policy(caller_id, kind, resolve(caller_id, name))
+    }
+
+    /// Lets the [`NamePolicy`] rewrite or reject the resolved `name`, see
+    /// [`MasterBuilder::name_policy`].
+    fn apply_name_policy(
+        &self,
+        caller_id: &str,
+        kind: NameKind,
+        name: String,
+    ) -> std::result::Result<String, String> {
+        let Some(policy) = &self.name_policy else {
+            return Ok(name);
+        };
+        let rewritten = policy.check(caller_id, kind, &name)?;
+        if rewritten == name {
+            return Ok(name);
+        }
+        // The rewritten name is stored as it is, so it has to be global and legal itself.
+        if !rewritten.starts_with('/') || !is_legal_name(&rewritten) {
+            log::warn!("The name policy rewrote {kind} {name} to the illegal name {rewritten}");
+            return Err(format!("{kind} name [{name}] was rewritten to the illegal name [{rewritten}]"));
+        }
+        log::debug!("The name policy rewrote {kind} {name} to {rewritten}");
+        Ok(rewritten)
+    }
+
+    /// Records that `node`, whose API is `api`, registered as `role` of `name`.
+    fn record_registration(&self, role: Registration, name: &str, node: &str, api: &str) {
+        self.emit(role.registered_event(name, node));
+        let now = RegistrationTimes::at(self.clock.now().naive_utc());
+        if role != Registration::Service {
+            self.topic_times
+                .write()
+                .unwrap()
+                .entry(name.to_owned())
+                .and_modify(|t| t.last = now.last)
+                .or_insert(now);
+        }
+        let mut registration_times = self.registration_times.write().unwrap();
+        let added = match registration_times.entry((role, name.to_owned(), node.to_owned())) {
+            Entry::Vacant(v) => {
+                v.insert(now);
+                true
+            }
+            Entry::Occupied(mut o) => {
+                let since_last = now.last - o.get().last;
+                if since_last < REREGISTRATION_WARN_WINDOW {
+                    warn_throttled!(
+                        self.log_throttle,
+                        format!("reregistration {node} {name}"),
+                        "Node '{node}' registered as {} of '{name}' again after {} ms. It may be restarting repeatedly.",
+                        role.as_str(),
+                        since_last.num_milliseconds()
+                    );
+                }
+                o.get_mut().last = now.last;
+                false
+            }
+        };
+        drop(registration_times);
+        if added {
+            self.count_registration(node, api);
+        }
+    }
+
+    fn forget_registration(&self, role: Registration, name: &str, node: &str) {
+        self.emit(role.unregistered_event(name, node));
+        let removed = self
+            .registration_times
+            .write()
+            .unwrap()
+            .remove(&(role, name.to_owned(), node.to_owned()))
+            .is_some();
+        if role != Registration::Service && self.topic_retention == TopicRetention::WhileUsed {
+            self.forget_unused_topic(name);
+        }
+        if removed {
+            self.uncount_registrations(node, 1);
+        }
+    }
+
+    /// Counts a publication, subscription, service or parameter subscription of `node`, whose API
+    /// is `api`, like rosmaster's `NodeRef` does.
+    fn count_registration(&self, node: &str, api: &str) {
+        let mut counts = self.registration_counts.lock().unwrap();
+        let count = counts.entry(node.to_owned()).or_default();
+        *count += 1;
+        // The last registration of the node may have gone since it registered, which forgot
+        // the node as well.
+        if *count == 1 {
+            self.nodes
+                .write()
+                .unwrap()
+                .entry(node.to_owned())
+                .or_insert_with(|| api.to_owned());
+        }
+    }
+
+    /// Counts `n` registrations of `node` as gone and forgets the node once it has none left.
+    fn uncount_registrations(&self, node: &str, n: usize) {
+        let mut counts = self.registration_counts.lock().unwrap();
+        if let Entry::Occupied(mut count) = counts.entry(node.to_owned()) {
+            *count.get_mut() = count.get().saturating_sub(n);
+            if *count.get() == 0 {
+                count.remove();
+                self.forget_node(node);
+            }
+        }
+    }
+
+    /// Forgets `node` once it has neither publications, subscriptions, services nor parameter
+    /// subscriptions left, like rosmaster does. Called with the registration counts locked, so
+    /// that the node can't register something else meanwhile.
+    fn forget_node(&self, node: &str) {
+        if let Some(api) = self.nodes.write().unwrap().remove(node) {
+            self.node_latency.write().unwrap().remove(&api);
+        }
+        self.heartbeats.lock().unwrap().remove(node);
+        log::debug!("Forgot node '{node}', it has no registrations left");
+    }
+
+    /// Forgets `node` if it has no registrations left, e.g. because it never registered anything
+    /// but its API.
+    fn forget_node_if_unregistered(&self, node: &str) {
+        let counts = self.registration_counts.lock().unwrap();
+        if !counts.contains_key(node) {
+            self.forget_node(node);
+        }
+    }
+
+    /// Forgets the type and registration times of `topic` if it has neither publishers nor
+    /// subscribers left, see [`TopicRetention::WhileUsed`].
+    fn forget_unused_topic(&self, topic: &str) {
+        // Same order as registrations, see `collect_idle_topics`.
+        let publications = self.publications.read().unwrap();
+        let subscriptions = self.subscriptions.read().unwrap();
+        let used = |nodes: Option<&HashSet<String>>| nodes.is_some_and(|n| !n.is_empty());
+        if used(publications.get(topic)) || used(subscriptions.get(topic)) {
+            return;
+        }
+        log::debug!("Forgetting the unused topic {topic}");
+        self.topics.write().unwrap().remove(topic);
+        self.topic_times.write().unwrap().remove(topic);
+    }
+
+    /// Returns the parameter subscriptions that are notified when `key` changes, or all of them if
+    /// `key` is empty, sorted by key and node.
+    fn param_subscriptions(&self, key: &str) -> Vec<ParamSubscriptionInfo> {
+        let now = self.clock.instant();
+        self.parameter_subscriptions
+            .read()
+            .unwrap()
+            .affected_by(key)
+            .map(|s| ParamSubscriptionInfo {
+                node: s.node_id.clone(),
+                key: s.param.clone(),
+                api_uri: s.api_uri.clone(),
+                updates_succeeded: i32::try_from(s.updates_succeeded).unwrap_or(i32::MAX),
+                updates_failed: i32::try_from(s.updates_failed).unwrap_or(i32::MAX),
+                failing_for: s.failing_since.map_or(0.0, |t| {
+                    now.saturating_duration_since(t).as_secs_f64()
+                }),
+            })
+            .collect()
+    }
+
+    /// Counts the outcome of a `paramUpdate` call to `node_id` about `param`. Subscriptions whose
+    /// updates have failed for longer than the pruning period are dropped, so that every later
+    /// `setParam` doesn't wait for the dead node again.
+    fn record_param_update(&self, node_id: &str, param: &str, succeeded: bool) {
+        let mut subscriptions = self.parameter_subscriptions.write().unwrap();
+        let Some(subscription) = subscriptions.get_mut(node_id, param) else {
+            return;
+        };
+        if succeeded {
+            subscription.updates_succeeded += 1;
+            subscription.failing_since = None;
+            return;
+        }
+        subscription.updates_failed += 1;
+        let now = self.clock.instant();
+        let failing_since = *subscription.failing_since.get_or_insert(now);
+        if let Some(period) = self.prune_param_subscribers_after {
+            let failing_for = now.saturating_duration_since(failing_since);
+            if failing_for >= period {
+                log::warn!(
+                    "Dropping subscription of node '{node_id}' to param {param}, updates have failed for {:?}",
+                    failing_for
+                );
+                if subscriptions.remove_node(node_id, param) {
+                    self.uncount_registrations(node_id, 1);
+                }
+            }
+        }
+    }
+
+    /// Returns the API URIs of the publishers of `topic` in the order subscribers are told.
+    fn publisher_apis(&self, topic: &str) -> Vec<String> {
+        let publishers = self
+            .publications
+            .read()
+            .unwrap()
+            .get(topic)
+            .cloned()
+            .unwrap_or_default();
+        let mut apis: Vec<String> = {
+            let nodes = self.nodes.read().unwrap();
+            publishers.iter().filter_map(|p| nodes.get(p).cloned()).collect()
+        };
+        self.order_publishers(&mut apis);
+        apis
+    }
+
+    /// Returns the API URIs of the subscribers of `topic`.
+    fn subscriber_apis(&self, topic: &str) -> Vec<String> {
+        let subscribers = self
+            .subscriptions
+            .read()
+            .unwrap()
+            .get(topic)
+            .cloned()
+            .unwrap_or_default();
+        let nodes = self.nodes.read().unwrap();
+        subscribers.iter().filter_map(|s| nodes.get(s).cloned()).collect()
+    }
+
+    fn namespace_stats(&self) -> Vec<NamespaceStats> {
+        let state = self.system_state();
+        let now = self.clock.instant();
+        self.namespace_activity.lock().unwrap().stats(&state, now)
+    }
+
+    fn topics(&self) -> Topics {
+        self.topics.read().unwrap().clone()
+    }
+
+    fn nodes(&self) -> Nodes {
+        self.nodes.read().unwrap().clone()
+    }
+
+    fn services(&self) -> Services {
+        self.service_list.read().unwrap().clone()
+    }
+
+    fn system_state(&self) -> SystemState {
+        fn sorted<'a>(
+            entries: impl Iterator<Item = (&'a String, Vec<String>)>,
+        ) -> Vec<(String, Vec<String>)> {
+            let mut entries: Vec<_> = entries
+                .map(|(name, mut nodes)| {
+                    nodes.sort();
+                    (name.clone(), nodes)
+                })
+                .collect();
+            entries.sort();
+            entries
+        }
+        // The locks are taken one after the other, like the handlers that modify them do.
+        let publishers = sorted(
+            self.publications.read().unwrap().iter().map(|(k, v)| (k, v.iter().cloned().collect())),
+        );
+        let subscribers = sorted(
+            self.subscriptions.read().unwrap().iter().map(|(k, v)| (k, v.iter().cloned().collect())),
+        );
+        let services = sorted(
+            self.service_list.read().unwrap().iter().map(|(k, v)| (k, v.keys().cloned().collect())),
+        );
+        SystemState {
+            publishers,
+            subscribers,
+            services,
+        }
+    }
+
+    fn graph_snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot::new(&self.system_state(), &self.topics())
+    }
+
+    /// Returns the providers of every service with the types probed so far, see
+    /// [`MasterBuilder::export_services`].
+    fn service_registry(&self) -> ServiceRegistry {
+        let services = self.service_list.read().unwrap().clone();
+        let nodes = self.nodes.read().unwrap().clone();
+        let service_types = self.service_types.lock().unwrap();
+        let mut registry = ServiceRegistry::default();
+        for (service, providers) in services {
+            let mut providers: Vec<ServiceProvider> = providers
+                .into_iter()
+                .map(|(node, service_api)| ServiceProvider {
+                    node_api: nodes.get(&node).cloned().unwrap_or_default(),
+                    service_type: service_types.get(&(service.clone(), service_api.clone())).cloned(),
+                    node,
+                    service_api,
+                })
+                .collect();
+            providers.sort_by(|a, b| a.node.cmp(&b.node));
+            registry.services.insert(service, providers);
+        }
+        registry
+    }
+
+    /// Asks the providers whose service type isn't known yet for it, and forgets the types of the
+    /// providers that are gone. Providers that don't answer are asked again the next time.
+    async fn probe_service_types(&self) {
+        let providers: Vec<(String, String)> = self
+            .service_list
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|(service, providers)| {
+                providers.values().map(move |api| (service.clone(), api.clone()))
+            })
+            .collect();
+        self.service_types
+            .lock()
+            .unwrap()
+            .retain(|provider, _| providers.contains(provider));
+        for provider in providers {
+            if self.service_types.lock().unwrap().contains_key(&provider) {
+                continue;
+            }
+            let (service, service_api) = provider.clone();
+            let probe = tokio::task::spawn_blocking(move || {
+                crate::rosrpc::ServiceClient::with_timeout(&service_api, &service, NODE_PROBE_TIMEOUT)?
+                    .probe("/master")
+            });
+            match probe.await {
+                Ok(Ok(mut header)) => {
+                    if let Some(service_type) = header.remove("type") {
+                        self.service_types.lock().unwrap().insert(provider, service_type);
+                    }
+                }
+                Ok(Err(e)) => log::debug!("Failed to probe the type of {} at {}: {e}", provider.0, provider.1),
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Writes [`RosData::service_registry`] to `path` as JSON. The file is replaced at once, so
+    /// readers never see a partial registry.
+    fn write_service_registry(&self, path: &Path) -> Result<()> {
+        let registry = serde_json::to_string_pretty(&self.service_registry())
+            .map_err(|e| Error::InvalidConfig(format!("cannot serialize the service registry: {e}")))?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".tmp");
+        let partial = PathBuf::from(partial);
+        std::fs::write(&partial, registry + "\n").map_err(|source| Error::Io {
+            path: partial.clone(),
+            source,
+        })?;
+        std::fs::rename(&partial, path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Returns the registrations of all nodes registered as `role` of `name`, sorted by node.
+    fn node_registrations(&self, role: Registration, name: &str) -> Vec<NodeRegistration> {
+        let mut registrations: Vec<_> = self
+            .registration_times
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|((r, n, _), _)| *r == role && n == name)
+            .map(|((_, _, node), times)| NodeRegistration {
+                node: node.clone(),
+                first_registered: times.first,
+                last_registered: times.last,
+            })
+            .collect();
+        registrations.sort_by(|a, b| a.node.cmp(&b.node));
+        registrations
+    }
+
+    /// Counts a call of `caller_id` in its session.
+    fn record_request(&self, caller_id: &str, fault: bool) {
+        let api_uri = self.nodes.read().unwrap().get(caller_id).cloned();
+        let now = self.clock.now().naive_utc();
+        let mut sessions = self.sessions.write().unwrap();
+        // A node that registers again from another API is a new process.
+        let new_session = match (sessions.get(caller_id), &api_uri) {
+            (None, _) => true,
+            (Some(session), Some(uri)) => !session.api_uri.is_empty() && session.api_uri != *uri,
+            (Some(_), None) => false,
+        };
+        if new_session {
+            // Sessions of callers that never registered would pile up otherwise.
+            let nodes = self.nodes.read().unwrap();
+            sessions.retain(|id, s| {
+                nodes.contains_key(id) || now - s.last_seen < UNREGISTERED_SESSION_TIMEOUT
+            });
+            drop(nodes);
+            sessions.insert(
+                caller_id.to_owned(),
+                Session {
+                    api_uri: String::new(),
+                    first_seen: now,
+                    last_seen: now,
+                    requests: 0,
+                    faults: 0,
+                },
+            );
+        }
+        let session = sessions.get_mut(caller_id).unwrap();
+        if let Some(uri) = api_uri {
+            session.api_uri = uri;
+        }
+        session.last_seen = now;
+        session.requests += 1;
+        if fault {
+            session.faults += 1;
+        }
+    }
+
+    /// Serializes all parameters to a rosparam YAML document.
+    fn dump_params(&self) -> Result<String> {
+        let params = self.parameters.read().unwrap().try_to_value()?;
+        let mut omitted = 0;
+        let mut protect = |_: &str, value: Value| -> Result<Option<Value>> {
+            #[cfg(feature = "param-encryption")]
+            if let Some(key) = &self.param_key {
+                return key.encrypt(&value).map(Some);
+            }
+            let _ = value;
+            omitted += 1;
+            Ok(None)
+        };
+        let config = self.config.read().unwrap();
+        let params = config.map_sensitive("/", params, &mut protect)?;
+        if omitted > 0 {
+            log::warn!("Left {omitted} sensitive parameters out of the dump, there is no encryption key");
+        }
+        crate::rosparam::to_yaml(&params.unwrap_or(Value::string(REDACTED.to_owned())))
+    }
+
+    /// Returns the parameter `key` as `caller_id` may see it, with sensitive values redacted if
+    /// it isn't one of the sensitive readers.
+    fn visible_param(&self, caller_id: &str, key: &str, value: Value) -> Value {
+        let config = self.config.read().unwrap();
+        if config.may_read_sensitive(caller_id) {
+            return value;
+        }
+        config.redact(key, value)
+    }
+
+    /// Whether `key` is a sensitive parameter that `caller_id` may not read. Such parameters are
+    /// refused instead of sent redacted, so that nodes don't take the placeholder for the value.
+    fn is_restricted(&self, caller_id: &str, key: &str) -> bool {
+        let config = self.config.read().unwrap();
+        config.is_sensitive(key) && !config.may_read_sensitive(caller_id)
+    }
+
+    /// Remembers that `key` changed to `value`, if parameter changes are kept, see
+    /// [`MasterBuilder::param_history`].
+    fn record_param_change(&self, key: &str, value: Value) {
+        if self.param_history_len == 0 {
+            return;
+        }
+        let mut history = self.param_history.lock().unwrap();
+        if history.len() == self.param_history_len {
+            history.pop_front();
+        }
+        history.push_back(ParamChange {
+            key: key.to_owned(),
+            value,
+            time: self.clock.now().naive_utc(),
+        });
+    }
+
+    /// Returns the kept changes of `key`, of the namespaces containing it and of the parameters in
+    /// it as `caller_id` may see them, oldest first.
+    fn param_history(&self, caller_id: &str, key: &str) -> Vec<ParamChange> {
+        let within = |name: &str, namespace: &str| {
+            name == namespace
+                || name
+                    .strip_prefix(namespace.trim_end_matches('/'))
+                    .is_some_and(|rest| rest.starts_with('/'))
+        };
+        self.param_history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|change| within(&change.key, key) || within(key, &change.key))
+            .filter(|change| !self.is_restricted(caller_id, &change.key))
+            .map(|change| ParamChange {
+                value: self.visible_param(caller_id, &change.key, change.value.clone()),
+                ..change.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the latest shutdown calls to nodes, oldest first.
+    fn shutdown_history(&self) -> Vec<ShutdownRecord> {
+        self.shutdown_history.read().unwrap().iter().cloned().collect()
+    }
+
+    /// Returns the sessions of all callers, sorted by caller ID.
+    fn sessions(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<_> = self
+            .sessions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(caller_id, s)| SessionInfo {
+                caller_id: caller_id.clone(),
+                api_uri: s.api_uri.clone(),
+                first_seen: s.first_seen,
+                last_seen: s.last_seen,
+                requests: i32::try_from(s.requests).unwrap_or(i32::MAX),
+                faults: i32::try_from(s.faults).unwrap_or(i32::MAX),
+            })
+            .collect();
+        sessions.sort_by(|a, b| a.caller_id.cmp(&b.caller_id));
+        sessions
+    }
+}
+
+pub struct Master {
+    data: Arc<RosData>,
+    max_request_size: usize,
+    auth: Option<Arc<dyn AuthProvider>>,
+    shutdown_reason: Option<String>,
+    ready_hook: Option<ReadyHook>,
+    exports: Option<ExportSchedule>,
+    service_export: Option<PathBuf>,
+    warm_start: Option<GraphSnapshot>,
+    dual_stack: bool,
+    #[cfg(feature = "ui")]
+    ui_dir: Option<PathBuf>,
+    #[cfg(feature = "cors")]
+    cors: Option<CorsConfig>,
+    #[cfg(feature = "unix-socket")]
+    unix_socket: Option<PathBuf>,
+    #[cfg(feature = "tls-server")]
+    tls: Option<(PathBuf, PathBuf)>,
+}
+
+/// What happens when a node registers a service that another node provides already, see
+/// [`MasterBuilder::service_ownership`]. A node registering its own service again, e.g. after a
+/// restart, always replaces its previous registration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ServiceOwnership {
+    /// Both nodes provide the service, `lookupService` returns the one that registered first.
+    AllowMultiple,
+    /// The registration fails, the service stays with the node that provides it.
+    Reject,
+    /// The new node provides the service from now on, like rosmaster does. The previous provider
+    /// is unregistered and [`GraphEvent::ServiceReplaced`] is sent.
+    #[default]
+    Replace,
+    /// Like `Replace`, and the previous provider is asked to shut down, like the previous instance
+    /// of a node that registers again. Use this if a stale provider must not keep serving the
+    /// clients that looked it up before.
+    ReplaceAndShutdown,
+}
+
+/// How long the master remembers the type of a topic without publishers and subscribers, see
+/// [`MasterBuilder::topic_retention`]. The type shows in `getTopicTypes` as long as it's
+/// remembered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TopicRetention {
+    /// Topics are never forgotten, like rosmaster does.
+    #[default]
+    Keep,
+    /// Topics are forgotten as soon as their last publisher or subscriber unregisters.
+    WhileUsed,
+    /// Topics are forgotten once they had neither publishers nor subscribers for the duration.
+    /// They are checked every quarter of it, so they are forgotten up to a quarter late.
+    IdleFor(Duration),
+}
+
+/// Where and how often the graph and the parameters are exported, see
+/// [`MasterBuilder::export_snapshots`].
+#[derive(Clone, Debug)]
+struct ExportSchedule {
+    directory: PathBuf,
+    every: Duration,
+    keep: usize,
+}
+
+/// When the master unregisters nodes that stopped answering, see
+/// [`MasterBuilder::monitor_liveness`] and [`Master::prune_dead_nodes`].
+#[derive(Clone, Debug, Default)]
+struct LivenessCheck {
+    every: Option<Duration>, // how often nodes are probed, if they are probed in the background
+    failures: u32,           // failed probes in a row before a node is unregistered
+    grace: Duration,         // how long a node has to fail before it is unregistered
+    exempt: Vec<String>,     // namespaces whose nodes are never unregistered
+}
+
+impl LivenessCheck {
+    /// How often nodes are expected to call `heartbeat`.
+    fn heartbeat_period(&self) -> Duration {
+        self.every.unwrap_or(DEFAULT_HEARTBEAT_PERIOD)
+    }
+
+    fn is_exempt(&self, node: &str) -> bool {
+        self.exempt.iter().any(|namespace| {
+            namespace == "/"
+                || node
+                    .strip_prefix(namespace.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// Which browsers may call the master directly, see [`MasterBuilder::cors`].
+#[cfg(feature = "cors")]
+#[derive(Clone, Debug)]
+pub struct CorsConfig {
+    /// Origins such as `http://tablet.local:8080` that may call the master, or `*` for any.
+    pub allowed_origins: Vec<String>,
+    /// HTTP methods that may be used, `GET` and `POST` by default. XML-RPC only uses `POST`.
+    pub allowed_methods: Vec<String>,
+}
+
+#[cfg(feature = "cors")]
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_owned(), "POST".to_owned()],
+        }
+    }
+}
+
+#[cfg(feature = "cors")]
+impl CorsConfig {
+    fn layer(&self) -> Result<tower_http::cors::CorsLayer> {
+        use tower_http::cors::{AllowOrigin, CorsLayer};
+
+        let invalid =
+            |what: &str, v: &str| Error::InvalidConfig(format!("invalid CORS {what} '{v}'"));
+        let origins = if self.allowed_origins.iter().any(|o| o == "*") {
+            AllowOrigin::any()
+        } else {
+            let origins = self
+                .allowed_origins
+                .iter()
+                .map(|o| o.parse().map_err(|_| invalid("origin", o)))
+                .collect::<Result<Vec<axum::http::HeaderValue>>>()?;
+            AllowOrigin::list(origins)
+        };
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|m| m.parse().map_err(|_| invalid("method", m)))
+            .collect::<Result<Vec<axum::http::Method>>>()?;
+        Ok(CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            // XML-RPC requests are sent as text/xml, which requires a preflight request.
+            .allow_headers([header::CONTENT_TYPE]))
+    }
+}
+
+/// Handler for registering the caller as a provider of the specified service.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `service` - Fully-qualified name of service (string)
+/// - `service_api` - ROSRPC Service URI (string)
+/// - `caller_api` - XML-RPC URI of caller node (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `ignore` - ignore (integer)
+struct RegisterServiceHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for RegisterServiceHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("RegisterServiceHandler {:?} ", params);
+        type Request = (String, String, String, String);
+        let (caller_id, service, service_api, caller_api) = Request::try_from_params(params)?;
+        let service = match self.data.resolve_name(&caller_id, NameKind::Service, &service) {
+            Ok(service) => service,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        if let Some(owner) = self.data.reserved_services.get(&service) {
+            if *owner != caller_id {
+                let err_msg = format!("service [{service}] is reserved for node [{owner}]");
+                return Ok((-1, err_msg, 0).try_to_value()?);
+            }
+        }
+
+        let replaced = {
+            let mut service_list = self.data.service_list.write().unwrap();
+            let providers = service_list.entry(service.clone()).or_default();
+            let others: Vec<String> =
+                providers.keys().filter(|node| **node != caller_id).cloned().collect();
+            let replaced = match self.data.service_ownership {
+                ServiceOwnership::Reject if !others.is_empty() => {
+                    let err_msg = format!("service [{service}] is already provided by [{}]", others.join(", "));
+                    return Ok((-1, err_msg, 0).try_to_value()?);
+                }
+                ServiceOwnership::Replace | ServiceOwnership::ReplaceAndShutdown => {
+                    for node in &others {
+                        providers.remove(node);
+                    }
+                    others
+                }
+                _ => Vec::new(),
+            };
+            providers.insert(caller_id.clone(), service_api);
+            replaced
+        };
+        for old_node in replaced {
+            log::warn!("Node '{caller_id}' registered service '{service}', replacing its provider '{old_node}'.");
+            // The node is forgotten along with its last registration, so look up its API first.
+            let old_api = self.data.nodes.read().unwrap().get(&old_node).cloned();
+            self.data
+                .forget_registration(Registration::Service, &service, &old_node);
+            if let (ServiceOwnership::ReplaceAndShutdown, Some(old_api)) = (self.data.service_ownership, old_api) {
+                let reason = format!("[{old_node}] Reason: service [{service}] was taken over by [{caller_id}]");
+                if let Err(e) = self.data.shutdown_node(&old_node, &old_api, &reason).await {
+                    log::warn!("Error shutting down the previous provider '{old_node}' of service '{service}': {e:?}");
+                }
+            }
+            self.data.emit(GraphEvent::ServiceReplaced {
+                service: service.clone(),
+                old_node,
+                new_node: caller_id.clone(),
+            });
+        }
+        self.data
+            .record_registration(Registration::Service, &service, &caller_id, &caller_api);
+
+        register_node(&self.data, &caller_id, &caller_api).await;
+
+        let msg = format!("Registered [{caller_id}] as provider of [{service}]");
+        Ok((1, msg, 1).try_to_value()?)
+    }
+}
+
+async fn register_node(data: &RosData, caller_id: &str, caller_api : &str) -> () {
+    let shutdown_api_url;
+    {
+        let mut nodes = data.nodes.write().unwrap();
+        match nodes.entry(caller_id.to_owned()) {
+            Entry::Vacant(v) => {
+                v.insert(caller_api.to_owned());
+                return
+            },
+            Entry::Occupied(mut e) => {
+                let e = e.get_mut();
+                if e == caller_api {
+                    return
+                } else {
+                    log::warn!("Node '{caller_id}' registered from {caller_api}, but it is already registered from {e}. Shutting down the previous instance.");
+                    shutdown_api_url = std::mem::replace(e, caller_api.to_owned());
+                    data.node_latency.write().unwrap().remove(&shutdown_api_url);
+                    data.emit(GraphEvent::NodeReplaced {
+                        node: caller_id.to_owned(),
+                        old_api: shutdown_api_url.clone(),
+                        new_api: caller_api.to_owned(),
+                    });
+                }
+            }
+        }
+    }
+    let reason = format!("[{caller_id}] Reason: new node registered with same name");
+    let res = data.shutdown_node(caller_id, &shutdown_api_url, &reason).await;
+    if let Err(e) = res {
+        log::warn!("Error shutting down previous instance of node '{caller_id}': {e:?}. New node will be registered regardless. Check for stray processes.");
+    }
+}
+
+/// Measures the latency to a new publisher in the background, so that it can be ordered among
+/// the other publishers, see [`MasterBuilder::order_publishers_by_latency`].
+fn probe_latency(data: &Arc<RosData>, api_uri: &str) {
+    if !data.order_publishers_by_latency || data.node_latency.read().unwrap().contains_key(api_uri)
+    {
+        return;
+    }
+    let data = data.clone();
+    let api_uri = api_uri.to_owned();
+    tokio::spawn(async move {
+        is_alive(&data, &api_uri).await;
+    });
+}
+
+/// Whether the node at `api_uri` answers `getPid` in time, see
+/// [`MasterBuilder::probe_service_providers`]. Nodes the master may not call count as alive.
+async fn is_alive(data: &RosData, api_uri: &str) -> bool {
+    if data.check_callback(api_uri).await.is_err() {
+        return true;
+    }
+    let client_api = data.client_api(api_uri);
+    let probe = async {
+        match tokio::time::timeout(NODE_PROBE_TIMEOUT, client_api.get_pid("/master")).await {
+            Ok(Ok(pid)) => Ok(pid),
+            _ => Err(()),
+        }
+    };
+    data.timed(api_uri, probe).await.is_ok()
+}
+
+/// Tells the subscriber at `api_uri` that `publisher_apis` are the publishers of `topic`.
+async fn publisher_update(data: &RosData, topic: &str, api_uri: &str, publisher_apis: &Vec<String>) {
+    let client_api = data.client_api(api_uri);
+    log::debug!("Call {}", api_uri);
+    let update = client_api.publisher_update("/master", topic, publisher_apis);
+    let result = match data.check_callback(api_uri).await {
+        Ok(()) => data.timed(api_uri, update).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) => warn_throttled!(
+            data.log_throttle,
+            format!("publisherUpdate {api_uri}"),
+            "publisherUpdate call to {} failed: {}",
+            api_uri,
+            e
+        ),
+        Ok(v) => log::debug!("publisherUpdate call to {} succeeded, returning: {:?}", api_uri, v),
+    }
+}
+
+/// Makes sure that the subscribers of `topic` know its current publishers a short while after a
+/// publisher registered or unregistered, which announced `publisher_apis` to the subscribers
+/// `notified`.
+///
+/// Registrations on the same topic are handled concurrently, so a subscriber can register between
+/// a new publisher reading the subscribers and announcing itself, or receive the updates of two
+/// new publishers in the wrong order. Subscribers that weren't notified get the publishers then,
+/// and all of them do if the publishers changed in the meantime.
+fn backfill_publisher_updates(
+    data: &Arc<RosData>,
+    topic: &str,
+    notified: Vec<String>,
+    publisher_apis: Vec<String>,
+) {
+    let data = data.clone();
+    let topic = topic.to_owned();
+    tokio::spawn(async move {
+        tokio::time::sleep(PUBLISHER_BACKFILL_DELAY).await;
+        let current = data.publisher_apis(&topic);
+        let changed = {
+            let mut announced = publisher_apis;
+            let mut current = current.clone();
+            announced.sort();
+            current.sort();
+            announced != current
+        };
+        for api_uri in data.subscriber_apis(&topic) {
+            if changed || !notified.contains(&api_uri) {
+                log::debug!("Backfilling the publishers of {topic} to {api_uri}");
+                publisher_update(&data, &topic, &api_uri, &current).await;
+            }
+        }
+    });
+}
+
+/// Handler for unregistering the caller as a provider of the specified service.
+///
+/// # Parameters
+///
+/// - caller_id - ROS caller ID (string)
+/// - service - Fully-qualified name of service (string)
+/// - service_api - API URI of service to unregister. Unregistration will only occur if current
+///   registration matches. (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - code - response code (integer)
+/// - statusMessage - status message (string)
+/// - numUnregistered - number of unregistrations (either 0 or 1). If this is zero it means that the
+///   caller was not registered as a service provider. The call still succeeds as the intended final
+///   state is reached. (integer)
+struct UnRegisterServiceHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for UnRegisterServiceHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("UnRegisterServiceHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, service, service_api) = Request::try_from_params(params)?;
+        let service = match self.data.resolve_name(&caller_id, NameKind::Service, &service) {
+            Ok(service) => service,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        if let Some(msg) = self.data.check_unregistering_node(&caller_id, None, &service) {
+            return Ok((1, msg, 0).try_to_value()?);
+        }
+        let not_registered = format!("[{caller_id}] is not a provider of [{service}]");
+        let mut service_list = self.data.service_list.write().unwrap();
+        let Some(providers) = service_list.get_mut(&service) else {
+            return Ok((1, not_registered, 0).try_to_value()?);
+        };
+        match providers.get(&caller_id) {
+            Some(registered) if *registered != service_api => {
+                let msg = format!(
+                    "[{service_api}] is no longer the current service api handle for [{service}]"
+                );
+                log::debug!("{msg}, [{caller_id}] provides it at {registered}");
+                return Ok((1, msg, 0).try_to_value()?);
+            }
+            Some(_) => {}
+            None => return Ok((1, not_registered, 0).try_to_value()?),
+        }
+        providers.remove(&caller_id);
+        if providers.is_empty() {
+            service_list.remove(&service);
+        }
+        drop(service_list);
+        self.data
+            .forget_registration(Registration::Service, &service, &caller_id);
+
+        let msg = format!("Unregistered [{caller_id}] as provider of [{service}]");
+        Ok((1, msg, 1).try_to_value()?)
+    }
+}
+
+/// Handler for registering the caller as a subscriber to the specified topic.
+///
+/// # Parameters
+///
+/// - caller_id - ROS caller ID (string)
+/// - topic - Fully-qualified name of the topic (string)
+/// - topic_type - Datatype for topic. Must be a package-resource name, i.e. the .msg name (string)
+/// - caller_api - API URI of subscriber to register. Will be used for new publisher notifications (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a vector of strings representing the response:
+///
+/// - code - response code (integer)
+/// - statusMessage - status message (string)
+/// - publishers - a list of XMLRPC API URIs for nodes currently publishing the specified topic (vector of strings).
+struct RegisterSubscriberHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for RegisterSubscriberHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("RegisterSubscriberHandler {:?} ", params);
+        type Request = (String, String, String, String);
+        let (caller_id, topic, topic_type, caller_api) = Request::try_from_params(params)?;
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, Vec::<String>::new()).try_to_value()?),
+        };
+
+        if self.data.config.read().unwrap().is_topic_blocked(&topic) {
+            let err_msg = format!("topic [{topic}] is blocked");
+            return Ok((-1, err_msg, Vec::<String>::new()).try_to_value()?);
+        }
+
+        if topic_type != "*" {
+            // Like rosmaster, subscribers only set the type of topics that have none yet.
+            match self.data.topics.write().unwrap().entry(topic.clone()) {
+                Entry::Occupied(known_topic_type) => {
+                    if known_topic_type.get() != &topic_type {
+                        log::warn!("Topic '{topic}' is known as '{}', but subscriber '{caller_id}' wants it as '{topic_type}'.", known_topic_type.get());
+                    }
+                }
+                Entry::Vacant(v) => {
+                    v.insert(topic_type.clone());
+                }
+            }
+        }
+
+        self.data
+            .subscriptions
+            .write()
+            .unwrap()
+            .entry(topic.clone())
+            .or_default()
+            .insert(caller_id.clone());
+        self.data
+            .record_registration(Registration::Subscriber, &topic, &caller_id, &caller_api);
+
+        register_node(&self.data, &caller_id, &caller_api).await;
+
+        let publisher_apis = self.data.publisher_apis(&topic);
+        backfill_publisher_updates(
+            &self.data,
+            &topic,
+            self.data.subscriber_apis(&topic),
+            publisher_apis.clone(),
+        );
+
+        let msg = format!("Subscribed to [{topic}]");
+        return Ok((1, msg, publisher_apis).try_to_value()?);
+    }
+}
+
+/// Handler for unregistering the caller as a subscriber of the topic.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `topic` - Fully-qualified name of topic (string)
+/// - `caller_api` - API URI of subscriber to unregister. Unregistration will only occur if current
+///   registration matches. (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `numUnsubscribed` - number of unsubscriptions (either 0 or 1). If this is zero it means that the caller was not
+///   registered as a subscriber. The call still succeeds as the intended final state is reached.
+struct UnRegisterSubscriberHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for UnRegisterSubscriberHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("UnRegisterSubscriberHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, topic, caller_api) = Request::try_from_params(params)?;
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        if let Some(msg) = self.data.check_unregistering_node(&caller_id, Some(&caller_api), &topic) {
+            return Ok((1, msg, 0).try_to_value()?);
+        }
+        let removed = self
+            .data
+            .subscriptions
+            .write()
+            .unwrap()
+            .entry(topic.clone())
+            .or_default()
+            .remove(&caller_id);
+        if removed {
+            self.data
+                .forget_registration(Registration::Subscriber, &topic, &caller_id);
+        }
+
+        self.data
+            .subscriptions
+            .write()
+            .unwrap()
+            .retain(|_, v| !v.is_empty());
+
+        Ok(match removed {
+            true => (1, format!("Unregistered [{caller_id}] as provider of [{topic}]"), 1),
+            false => (1, format!("[{caller_id}] is not a provider of [{topic}]"), 0),
+        }
+        .try_to_value()?)
+    }
+}
+
+/// Handler for registering the caller as a publisher of the specified topic.
+///
+/// # Parameters
+///
+/// - caller_id - ROS caller ID (string)
+/// - topic - Fully-qualified name of topic to register (string)
+/// - topic_type - Datatype for topic. Must be a package-resource name, i.e. the .msg name (string)
+/// - caller_api - API URI of publisher to register (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string, and a list of strings representing the response:
+///
+/// - code - response code (integer)
+/// - statusMessage - status message (string)
+/// - subscriberApis - list of current subscribers of topic in the form of XMLRPC URIs (list of strings)
+struct RegisterPublisherHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for RegisterPublisherHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("RegisterPublisherHandler {:?} ", params);
+        type Request = (String, String, String, String);
+        let (caller_id, topic, topic_type, caller_api) = Request::try_from_params(params)?;
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, Vec::<String>::new()).try_to_value()?),
+        };
+
+        if self.data.config.read().unwrap().is_topic_blocked(&topic) {
+            let err_msg = format!("topic [{topic}] is blocked");
+            return Ok((-1, err_msg, Vec::<String>::new()).try_to_value()?);
+        }
+
+        if let Some(v) = self.data.topics.read().unwrap().get(&topic.clone()) {
+            if v != &topic_type {
+                log::warn!("New publisher for topic '{topic}' has type '{topic_type}', but it is already published as '{v}'.");
+            }
+        }
+
+        register_node(&self.data, &caller_id, &caller_api).await;
+        probe_latency(&self.data, &caller_api);
+
+        // TODO(patwie): Maybe holding the lock for a longer time?
+        // let mut publications = self.data.publications.write().unwrap();
+        self.data
+            .publications
+            .write()
+            .unwrap()
+            .entry(topic.clone())
+            .or_default()
+            .insert(caller_id.clone());
+        self.data
+            .topics
+            .write()
+            .unwrap()
+            .insert(topic.clone(), topic_type.clone());
+        self.data
+            .record_registration(Registration::Publisher, &topic, &caller_id, &caller_api);
+
+        let subscribers_api_urls = self.data.subscriber_apis(&topic);
+        // Note: This should not be a race condition, because for every publisher, the node has to
+        // be there first, and we're reading "nodes" after "publishers".
+        let publisher_apis = self.data.publisher_apis(&topic);
+
+        // Inform all subscribers of the new publisher.
+        for client_api_url in &subscribers_api_urls {
+            publisher_update(&self.data, &topic, client_api_url, &publisher_apis).await;
+        }
+        backfill_publisher_updates(
+            &self.data,
+            &topic,
+            subscribers_api_urls.clone(),
+            publisher_apis,
+        );
+
+        let msg = format!("Registered [{caller_id}] as publisher of [{topic}]");
+        return Ok((1, msg, subscribers_api_urls).try_to_value()?);
+    }
+}
+
+/// Handler for unregistering the caller as a publisher of the topic. The subscribers of the topic
+/// are sent the remaining publishers.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `topic` - Fully-qualified name of topic to unregister (string)
+/// - `caller_api` - API URI of publisher to unregister. Unregistration will only occur if current
+///   registration matches. (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `numUnregistered` - number of unregistrations (either 0 or 1). If this is zero it means that the
+///   caller was not registered as a publisher. The call still succeeds as the intended final state is reached.
+struct UnRegisterPublisherHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for UnRegisterPublisherHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("UnRegisterPublisherHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, topic, caller_api) = Request::try_from_params(params)?;
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        log::debug!("Called {caller_id} with {topic} {caller_api}");
+
+        if let Some(msg) = self.data.check_unregistering_node(&caller_id, Some(&caller_api), &topic) {
+            return Ok((1, msg, 0).try_to_value()?);
+        }
+
+        if self
+            .data
+            .publications
+            .write()
+            .unwrap()
+            .get(&topic.clone())
+            .is_none()
+        {
+            let msg = format!("[{caller_id}] is not a provider of [{topic}]");
+            return Ok((1, msg, 0).try_to_value()?);
+        }
+        let removed = self
+            .data
+            .publications
+            .write()
+            .unwrap()
+            .entry(topic.clone())
+            .or_default()
+            .remove(&caller_id);
+        if removed {
+            self.data
+                .forget_registration(Registration::Publisher, &topic, &caller_id);
+        }
+        self.data
+            .publications
+            .write()
+            .unwrap()
+            .retain(|_, v| !v.is_empty());
+
+        if removed {
+            // Inform all subscribers that the publisher is gone.
+            let subscribers_api_urls = self.data.subscriber_apis(&topic);
+            let publisher_apis = self.data.publisher_apis(&topic);
+            for client_api_url in &subscribers_api_urls {
+                publisher_update(&self.data, &topic, client_api_url, &publisher_apis).await;
+            }
+            backfill_publisher_updates(&self.data, &topic, subscribers_api_urls, publisher_apis);
+        }
+        Ok(match removed {
+            true => (1, format!("Unregistered [{caller_id}] as provider of [{topic}]"), 1),
+            false => (1, format!("[{caller_id}] is not a provider of [{topic}]"), 0),
+        }
+        .try_to_value()?)
+    }
+}
+
+/// Handler for looking up the XML-RPC URI of the node with the associated name/caller_id.
+///
+/// # Parameters
+///
+/// - caller_id - ROS caller ID (string)
+/// - node_name - Name of node to lookup (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - code - response code (integer)
+/// - statusMessage - status message (string)
+/// - URI - XML-RPC URI of the node (string). This API is for looking up information about publishers
+///   and subscribers. Use lookupService instead to lookup ROS-RPC URIs.
+struct LookupNodeHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for LookupNodeHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("LookupNodeHandler {:?} ", params);
+        type Request = (String, String);
+        let (_caller_id, node_name) = Request::try_from_params(params)?;
+
+        if let Some(node_api) = self.data.nodes.read().unwrap().get(&node_name) {
+            return Ok((1, "node api", node_api).try_to_value()?);
+        } else {
+            let err_msg = format!("unknown node [{}]", node_name);
+            return Ok((-1, err_msg, "").try_to_value()?);
+        }
+    }
+}
+
+/// Handler for getting the list of topics that can be subscribed to.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `subgraph` - Restrict topic names to match within the specified subgraph. Subgraph namespace
+///   is resolved relative to the caller's namespace. Use empty string to specify all names (string).
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `topics` - a list of lists containing topic names and types, e.g. `[[topic1, type1], [topic2, type2]]`.
+///   The list represents topics that can be subscribed to, but not necessarily all topics available in the system.
+///   Use `getSystemState()` for a more comprehensive list.
+struct GetPublishedTopicsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetPublishedTopicsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetPublishedTopicsHandler {:?} ", params);
+        type Request = (String, String);
+        let (_caller_id, _subgraph) = Request::try_from_params(params)?;
+        let mut result = Vec::<(String, String)>::new();
+        let topics = self.data.topics.read().unwrap().clone();
+        for topic in self.data.publications.read().unwrap().keys() {
+            let data_type = topics.get(&topic.clone());
+            if let Some(data_type) = data_type {
+                result.push((topic.clone(), data_type.to_owned()));
+            }
+        }
+        return Ok((1, "current topics", result).try_to_value()?);
+    }
+}
+
+/// Handler for getting the list of all topics that have publishers or subscribers. Unlike
+/// `getPublishedTopics`, this includes topics that are only subscribed to. This is an extension to
+/// the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `subgraph` - Restrict topic names to match within the specified subgraph. Subgraph namespace
+///   is resolved relative to the caller's namespace. Use empty string to specify all names (string).
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `topics` - a list of lists containing topic names and types, e.g. `[[topic1, type1], [topic2, type2]]`,
+///   sorted by topic name. The type is empty if no publisher or subscriber has announced it yet.
+struct GetTopicsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetTopicsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetTopicsHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, subgraph) = Request::try_from_params(params)?;
+
+        let subgraph = resolve(&caller_id, &subgraph);
+        let subgraph = subgraph.trim_end_matches('/');
+        let in_subgraph = |topic: &str| match topic.strip_prefix(subgraph) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        };
+
+        let topics = self.data.topics.read().unwrap().clone();
+        let mut names: Vec<String> = self
+            .data
+            .publications
+            .read()
+            .unwrap()
+            .keys()
+            .chain(self.data.subscriptions.read().unwrap().keys())
+            .filter(|topic| in_subgraph(topic))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let result: Vec<(String, String)> = names
+            .into_iter()
+            .map(|topic| {
+                let data_type = topics.get(&topic).cloned().unwrap_or_default();
+                (topic, data_type)
+            })
+            .collect();
+        Ok((1, "current topics", result).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving the list of topic names and their types.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string representing the response, and a list of lists of strings:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `topicTypes` - a list of lists of strings representing the [topicName, topicType] pairs.
+struct GetTopicTypesHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetTopicTypesHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetTopicTypesHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        let result: Vec<_> = self
+            .data
+            .topics
+            .read()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+        // rosmaster answers getTopicTypes with this message, too.
+        return Ok((1, "current system state", result).try_to_value()?);
+    }
+}
+
+/// Handler for retrieving a list representation of the ROS system state.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `systemState` - a list of tuples representing the system state:
+///     - Each tuple contains two elements: a string representing the topic/service name, and a list of strings
+///       representing the associated publishers/subscribers/servers/clients.
+///     - The first tuple contains the list of publishers, the second contains the list of subscribers, and the third
+///       contains the list of services.
+struct GetSystemStateHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetSystemStateHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetSystemStateHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        let state = self.data.system_state();
+        return Ok((1, "current system state", (state.publishers, state.subscribers, state.services)).try_to_value()?);
+    }
+}
+
+/// Handler for getting the URI of the master.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `masterURI` - URI of the ROS master (string)
+struct GetUriHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetUriHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetUriHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        return Ok((1, "", self.data.advertised_uri.to_string()).try_to_value()?);
+    }
+}
+
+/// Handler for getting the PID of the master.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `pid` - PID of the ROS master (integer)
+struct GetPidHandler {
+    #[allow(unused)]
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetPidHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetPidHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        let result = std::process::id() as i32; // max pid on linux is 2^22, so the typecast should have no unintended side effects
+        return Ok((1, "", result).try_to_value()?);
+    }
+}
+
+/// Handler for telling the master that a node is still alive, without registering anything
+/// again. This is an extension to the ROS Master API.
+///
+/// Nodes that call it at least once per returned period count as alive for
+/// [`MasterBuilder::monitor_liveness`] without being called, e.g. nodes behind a gateway or a
+/// flaky link that the master can't reach reliably.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer), -1 if the node is not registered
+/// - `statusMessage` - status message (string)
+/// - `period` - how often the node should call `heartbeat`, in seconds (integer)
+struct HeartbeatHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for HeartbeatHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("HeartbeatHandler {:?} ", params);
+        type Request = String;
+        let caller_id = Request::try_from_params(params)?;
+        let period = self.data.liveness.heartbeat_period();
+        let period = i32::try_from(period.as_secs().max(1)).unwrap_or(i32::MAX);
+        // Locked like in `forget_node`, so that a node that is being forgotten isn't kept alive.
+        let nodes = self.data.nodes.read().unwrap();
+        if !nodes.contains_key(&caller_id) {
+            let msg = format!("[{caller_id}] is not a registered node");
+            return Ok((-1, msg, period).try_to_value()?);
+        }
+        let now = self.data.clock.instant();
+        self.data.heartbeats.lock().unwrap().insert(caller_id, now);
+        Ok((1, "", period).try_to_value()?)
+    }
+}
+
+/// Handler for looking up all providers of a particular service.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `service` - Fully-qualified name of service (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `serviceUrl` - URL that provides the address and port of the service. The function fails if there
+///   is no provider, or none answers if [`MasterBuilder::probe_service_providers`] is enabled.
+struct LookupServiceHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for LookupServiceHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("LookupServiceHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, service) = Request::try_from_params(params)?;
+        let service = match self.data.resolve_name(&caller_id, NameKind::Service, &service) {
+            Ok(service) => service,
+            Err(err_msg) => return Ok((-1, err_msg, "").try_to_value()?),
+        };
+
+        let services = self
+            .data
+            .service_list
+            .read()
+            .unwrap()
+            .get(&service)
+            .cloned()
+            .unwrap_or_default();
+        // With several providers, see `ServiceOwnership::AllowMultiple`, the one that registered
+        // first is returned.
+        let mut providers = self.data.node_registrations(Registration::Service, &service);
+        providers.sort_by_key(|provider| provider.first_registered);
+        for provider in providers {
+            let Some(service_url) = services.get(&provider.node) else {
+                continue;
+            };
+            let node_api = match self.data.probe_service_providers {
+                true => self.data.nodes.read().unwrap().get(&provider.node).cloned(),
+                false => None,
+            };
+            if let Some(node_api) = node_api {
+                if !is_alive(&self.data, &node_api).await {
+                    log::debug!("Skipping provider '{}' of {service}, it doesn't answer at {node_api}", provider.node);
+                    continue;
+                }
+            }
+            let msg = format!("rosrpc URI: [{service_url}]");
+            return Ok((1, msg, service_url.clone()).try_to_value()?);
+        }
+        return Ok((-1, "no provider", "").try_to_value()?);
+    }
+}
+
+/// Handler for deleting a parameter.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `key` - Parameter name (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `ignore` - an integer indicating the number of parameters deleted. This is always 0, since a delete
+///   operation deletes only one parameter.
+struct DeleteParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for DeleteParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("DeleteParamHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, key) = Request::try_from_params(params)?;
+        let key = match self.data.resolve_name(&caller_id, NameKind::Parameter, &key) {
+            Ok(key) => key,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+        let update_futures = {
+            let key_split = param_path(&key);
+            let mut params = self.data.parameters.write().unwrap();
+            if !params.remove(key_split) {
+                return Ok((-1, format!("parameter [{key}] is not set"), 0).try_to_value()?);
+            }
+            self.data.invalidate_param_keys();
+            self.data
+                .record_param_change(&key, HashMap::<String, Value>::new().try_to_value()?);
+            self.data.emit(GraphEvent::ParamDeleted { key: key.clone() });
+            spawn_param_updates(&self.data, &params, &caller_id, &key)?
+        };
+        await_param_updates(&self.data, &key, update_futures).await;
+        return Ok((1, format!("parameter {key} deleted"), 0).try_to_value()?);
+    }
+}
+
+async fn update_client_with_new_param_value(
+    client_api: ClientApi,
+    updating_node_id: String,
+    subscribing_node_id: String,
+    param_name: String,
+    new_value: Value,
+) -> Result<Value> {
+    let request = client_api.param_update(&updating_node_id, &param_name, &new_value);
+    let res = request.await;
+    match res {
+        Ok(ref v) => log::debug!(
+            "Sent new value for param '{}' to node '{}'. response: {:?}",
+            param_name,
+            subscribing_node_id,
+            &v
+        ),
+        Err(ref e) => log::debug!(
+            "Error sending new value for param '{}' to node '{}': {:?}",
+            param_name,
+            subscribing_node_id,
+            e
+        ),
+    }
+
+    res
+}
+
+/// Handler for setting a ROS parameter.
+///
+/// # Parameters
+///
+/// - caller_id - ROS caller ID (string)
+/// - key - Parameter name (string)
+/// - value - Parameter value. If it's a dictionary, it will be treated as a parameter tree, where
+///   the key is the parameter namespace. For example {'x':1,'y':2,'sub':{'z':3}} will set
+///   key/x=1, key/y=2, and key/sub/z=3. Furthermore, it will replace all existing parameters
+///   in the key parameter namespace with the parameters in value. You must set parameters individually
+///   if you wish to perform a union update (XMLRPCLegalValue)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - code - response code (integer)
+/// - statusMessage - status message (string)
+/// - ignore - ignored (integer). Returns 0 in all cases.
+struct SetParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for SetParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        type Request = (String, String, Value);
+        let (caller_id, key, value) = Request::try_from_params(params)?;
+        let key = match self.data.resolve_name(&caller_id, NameKind::Parameter, &key) {
+            Ok(key) => key,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+        log::debug!(
+            "SetParamHandler {:?} ",
+            (&caller_id, &key, self.data.config.read().unwrap().redact(&key, value.clone()))
+        );
+        if key == "/" && HashMap::<String, Value>::try_from_value(&value).is_err() {
+            let message = "cannot set the root of the parameter tree to a non-dictionary";
+            return Ok((-1, message, 0).try_to_value()?);
+        }
+
+        let update_futures = {
+            let mut params = self.data.parameters.write().unwrap();
+            let key_split = param_path(&key);
+            params.update_inner(key_split, value.clone());
+            self.data.record_param_change(&key, value.clone());
+            self.data.emit(GraphEvent::ParamChanged {
+                key: key.clone(),
+                value,
+            });
+            self.data.invalidate_param_keys();
+            log::info!("updating param {}", &key);
+            spawn_param_updates(&self.data, &params, &caller_id, &key)?
+        };
+        await_param_updates(&self.data, &key, update_futures).await;
+
+        Ok((1, format!("parameter {key} set"), 0).try_to_value()?)
+    }
+}
+
+type ParamUpdateResult = (String, String, Result<Value>);
+
+/// Sends the subscribers affected by a change of `key` the new values of their parameters, as of
+/// `params`. Subscribed parameters that are gone are sent as an empty dictionary, like rosmaster
+/// does.
+fn spawn_param_updates(
+    data: &Arc<RosData>,
+    params: &Parameters,
+    caller_id: &str,
+    key: &str,
+) -> std::result::Result<JoinSet<ParamUpdateResult>, dxr::DxrError> {
+    let mut update_futures = JoinSet::new();
+    let param_subscriptions = data.parameter_subscriptions.read().unwrap();
+    for subscription in param_subscriptions.affected_by(key) {
+        // The parameter may have become sensitive since the node subscribed.
+        if data.is_restricted(&subscription.node_id, &subscription.param) {
+            continue;
+        }
+        log::debug!("updating subscriber {:?}", &subscription);
+        let new_value = params
+            .get(param_path(&subscription.param))
+            .map_or_else(|| HashMap::<String, Value>::new().try_to_value(), Ok)?;
+        let new_value = data.visible_param(&subscription.node_id, &subscription.param, new_value);
+        let update = update_client_with_new_param_value(
+            data.client_api(&subscription.api_uri),
+            caller_id.to_owned(),
+            subscription.node_id.clone(),
+            subscription.param.clone(),
+            new_value,
+        );
+        let node_id = subscription.node_id.clone();
+        let param = subscription.param.clone();
+        let api_uri = subscription.api_uri.clone();
+        let data = data.clone();
+        update_futures.spawn(async move {
+            let result = match data.check_callback(&api_uri).await {
+                Ok(()) => data.timed(&api_uri, update).await,
+                Err(e) => Err(e),
+            };
+            (node_id, param, result)
+        });
+    }
+    Ok(update_futures)
+}
+
+/// Waits for the `paramUpdate` calls about a change of `key` and records their outcome.
+async fn await_param_updates(
+    data: &RosData,
+    key: &str,
+    mut update_futures: JoinSet<ParamUpdateResult>,
+) {
+    while let Some(res) = update_futures.join_next().await {
+        match res {
+            Ok((node_id, param, Ok(v))) => {
+                log::debug!("a subscriber has been updated (res: {:#?})", &v);
+                data.record_param_update(&node_id, &param, true);
+            }
+            Ok((node_id, param, Err(err))) => {
+                data.record_param_update(&node_id, &param, false);
+                // The error names the node API that could not be updated.
+                warn_throttled!(
+                    data.log_throttle,
+                    format!("paramUpdate {err}"),
+                    "Error updating a subscriber of changed param {}:\n{:#?}",
+                    key,
+                    err
+                );
+            }
+            Err(err) => {
+                log::warn!(
+                    "Error updating a subscriber of changed param {}:\n{:#?}",
+                    key,
+                    err
+                );
+            }
+        }
+    }
+
+    log::info!("done updating subscribers");
+}
+
+/// Handler for retrieving a parameter value from the server.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `key` - Parameter name. If `key` is a namespace, `getParam()` will return a parameter tree (string).
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `parameterValue` - the value of the requested parameter (of type `XMLRPCLegalValue`). If `code` is not 1,
+///   `parameterValue` should be ignored. If `key` is a namespace, the return value will be a dictionary, where each
+///   key is a parameter in that namespace. Sub-namespaces are also represented as dictionaries.
+struct GetParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetParamHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, key) = Request::try_from_params(params)?;
+        let key_full = match self.data.resolve_name(&caller_id, NameKind::Parameter, &key) {
+            Ok(key_full) => key_full,
+            Err(err_msg) => return Ok((-1, err_msg, Value::i4(0)).try_to_value()?),
+        };
+        let params = self.data.parameters.read().unwrap();
+        let key_path = param_path(&key_full);
+
+        if self.data.is_restricted(&caller_id, &key_full) {
+            let message = format!("Parameter [{}] is restricted", &key_full);
+            return Ok((-1, message, Value::i4(0)).try_to_value()?);
+        }
+
+        Ok(match params.get(key_path) {
+            Some(value) => {
+                let value = self.data.visible_param(&caller_id, &key_full, value);
+                (1, format!("Parameter [{}]", &key_full), value)
+            }
+            None => (-1, format!("Parameter [{}] is not set", &key_full), Value::i4(0)),
+        }
+        .try_to_value()?)
+    }
+}
+
+struct SearchParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for SearchParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("SearchParamHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, key) = Request::try_from_params(params)?;
+        if let Some(err_msg) = check_names(&caller_id, NameKind::Parameter, &key) {
+            return Ok((-1, err_msg, "").try_to_value()?);
+        }
+        if key.starts_with('~') {
+            let message = format!("Cannot search for private parameter [{key}]");
+            return Ok((-1, message, "").try_to_value()?);
+        }
+        // Like for other calls, the policy sees the resolved, global name that was found.
+        let found = match search_param(&caller_id, &key, |name| self.data.has_param(name)) {
+            Some(found) => {
+                let found = resolve(&caller_id, &found);
+                self.data.apply_name_policy(&caller_id, NameKind::Parameter, found)
+            }
+            None => Err(format!("Cannot find parameter [{key}] in an upwards search")),
+        };
+        Ok(match found {
+            Ok(found) => (1, format!("Found [{found}]"), found),
+            Err(message) => (-1, message, String::new()),
+        }
+        .try_to_value()?)
+    }
+}
+
+/// Searches for the parameter `key` like rosmaster does, see
+/// <https://github.com/ros/ros_comm/blob/9ae132c/tools/rosmaster/src/rosmaster/paramserver.py#L82>.
+/// `has_param` tells whether a global parameter or namespace, given without trailing slash, is set.
+///
+/// Global keys are not searched for, they either exist or they don't. Otherwise only the first
+/// element of `key` is searched for, starting in the namespace `caller_id` itself, i.e. the private
+/// namespace of the node, and going up to the root. For `robot/arm` the closest `robot` is found
+/// and `robot/arm` within its namespace is returned, whether it exists or not.
+fn search_param(caller_id: &str, key: &str, has_param: impl Fn(&str) -> bool) -> Option<String> {
+    let canonical = |name: &str| match name.trim_end_matches('/') {
+        "" => "/".to_owned(),
+        name => name.to_owned(),
+    };
+    if key.starts_with('/') {
+        return has_param(&canonical(key)).then(|| key.to_owned());
+    }
+
+    let key_first_element = param_path(key).next()?;
+    let namespaces = param_path(caller_id).collect::<Vec<&str>>();
+    (0..=namespaces.len()).rev().find_map(|depth| {
+        let mut namespace = String::from("/");
+        for element in &namespaces[..depth] {
+            namespace.push_str(element);
+            namespace.push('/');
+        }
+        has_param(&format!("{namespace}{key_first_element}")).then(|| format!("{namespace}{key}"))
+    })
+}
+
+/// Handler for subscribing to a parameter value and updates.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `caller_api` - Node API URI of subscriber for paramUpdate callbacks (string)
+/// - `key` - Parameter name (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string representing the response, and the parameter value:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `parameterValue` - the parameter value (XML-RPC legal value). If the parameter has not been set yet,
+///   the value will be an empty dictionary.
+struct SubscribeParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for SubscribeParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("SubscribeParamHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, caller_api, key) = Request::try_from_params(params)?;
+        Ok(match subscribe_param(&self.data, &caller_id, caller_api, &key).await {
+            Ok((key, value)) => subscribed_param(&key, value)?,
+            Err(response) => response,
+        }
+        .try_to_value()?)
+    }
+}
+
+/// Subscribes `caller_id` to the parameter `key`. Returns the resolved key and the value of the
+/// parameter as the caller may see it, `None` if it is not set, or the error response to
+/// `subscribeParam` if the caller can't subscribe.
+async fn subscribe_param(
+    data: &RosData,
+    caller_id: &str,
+    caller_api: String,
+    key: &str,
+) -> std::result::Result<(String, Option<Value>), (i32, String, Value)> {
+    let key = match data.resolve_name(caller_id, NameKind::Parameter, key) {
+        Ok(key) => key,
+        Err(err_msg) => return Err((-1, err_msg, Value::string("".to_owned()))),
+    };
+    if data.is_restricted(caller_id, &key) {
+        return Err((-1, format!("Parameter [{key}] is restricted"), Value::i4(0)));
+    }
+
+    register_node(data, caller_id, &caller_api).await;
+
+    // replaces the old entry if the subscribing node has restarted
+    let subscription = ParamSubscription::new(caller_id.to_owned(), key.clone(), caller_api.clone());
+    let limit = data
+        .config
+        .read()
+        .unwrap()
+        .max_param_subscriptions
+        .or(data.max_param_subscriptions);
+    let mut subscriptions = data.parameter_subscriptions.write().unwrap();
+    let added = !subscriptions.contains(caller_id, &key);
+    if !subscriptions.insert(subscription, limit) {
+        warn_throttled!(
+            data.log_throttle,
+            "param subscription limit",
+            "Rejecting subscription of node '{}' to param {}, there are {} subscriptions already",
+            caller_id,
+            key,
+            limit.unwrap_or_default()
+        );
+        let message = format!("too many parameter subscriptions, the limit is {}", limit.unwrap_or_default());
+        return Err((-1, message, Value::string("".to_owned())));
+    }
+    if added {
+        data.count_registration(caller_id, &caller_api);
+    }
+    drop(subscriptions);
+
+    let value = data.parameters.read().unwrap().get(param_path(&key));
+    let value = value.map(|value| data.visible_param(caller_id, &key, value));
+    Ok((key, value))
+}
+
+/// The response to `subscribeParam` for a subscription to `key`. Parameters that are not set are
+/// sent as an empty dictionary, like rosmaster does.
+fn subscribed_param(
+    key: &str,
+    value: Option<Value>,
+) -> std::result::Result<(i32, String, Value), dxr::DxrError> {
+    let value = value.map_or_else(|| HashMap::<String, Value>::new().try_to_value(), Ok)?;
+    Ok((1, format!("Subscribed to parameter [{key}]"), value))
+}
+
+/// Handler for subscribing to a parameter like `subscribeParam`, and getting its recent changes,
+/// see [`MasterBuilder::param_history`]. This is an extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `caller_api` - Node API URI of subscriber for paramUpdate callbacks (string)
+/// - `key` - Parameter name (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string, the parameter value and a list of structs:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `parameterValue` - the parameter value, as returned by `subscribeParam`
+/// - `history` - the recent changes of the parameter, of the namespaces containing it and of the
+///   parameters in it, oldest first (list of structs)
+struct SubscribeParamWithHistoryHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for SubscribeParamWithHistoryHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("SubscribeParamWithHistoryHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, caller_api, key) = Request::try_from_params(params)?;
+        let ((code, msg, value), history) =
+            match subscribe_param(&self.data, &caller_id, caller_api, &key).await {
+                Ok((key, value)) => {
+                    (subscribed_param(&key, value)?, self.data.param_history(&caller_id, &key))
+                }
+                Err(response) => (response, Vec::new()),
+            };
+        Ok((code, msg, value, history).try_to_value()?)
+    }
+}
+
+/// Handler for getting a parameter and subscribing to its updates in one call, which is what
+/// clients with a parameter cache like `rospy.get_param_cached` need. This is an extension to the
+/// ROS Master API.
+///
+/// The caller stays subscribed if the parameter is not set, so it is told once it gets set.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `caller_api` - Node API URI of subscriber for paramUpdate callbacks (string)
+/// - `key` - Parameter name (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string representing the response, and the parameter value:
+///
+/// - `code` - response code (integer), -1 if the parameter is not set
+/// - `statusMessage` - status message (string)
+/// - `parameterValue` - the parameter value (XML-RPC legal value)
+struct GetParamCachedHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetParamCachedHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetParamCachedHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, caller_api, key) = Request::try_from_params(params)?;
+        Ok(match subscribe_param(&self.data, &caller_id, caller_api, &key).await {
+            Ok((key, Some(value))) => (1, format!("Parameter [{key}]"), value),
+            Ok((key, None)) => (-1, format!("Parameter [{key}] is not set"), Value::i4(0)),
+            Err(response) => response,
+        }
+        .try_to_value()?)
+    }
+}
+
+/// Handler for unsubscribing from a parameter and its updates.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `caller_api` - Node API URI of subscriber (string)
+/// - `key` - Parameter name to unsubscribe from (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `numUnsubscribed` - number of unsubscriptions (either 0 or 1). If this is zero it means that the
+///   caller was not subscribed to the parameter. The call still succeeds as the intended final state is reached.
+struct UnSubscribeParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for UnSubscribeParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("UnSubscribeParamHandler {:?} ", params);
+        type Request = (String, String, String);
+        let (caller_id, caller_api, key) = Request::try_from_params(params)?;
+        let key = match self.data.resolve_name(&caller_id, NameKind::Parameter, &key) {
+            Ok(key) => key,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        let mut subscriptions = self.data.parameter_subscriptions.write().unwrap();
+        let removed = subscriptions.remove(&key, &caller_api);
+        for node in &removed {
+            self.data.uncount_registrations(node, 1);
+        }
+        drop(subscriptions);
+        let msg = format!("Unsubscribe to parameter [{key}]");
+        Ok((1, msg, if removed.is_empty() { 0 } else { 1 }).try_to_value()?)
+    }
+}
+
+/// Handler for checking if a parameter is stored on the server.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `key` - Parameter name (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a boolean representing the response:
+///
+/// - `code` - Response code (integer)
+/// - `statusMessage` - Status message (string)
+/// - `hasParam` - Boolean indicating whether the parameter is stored on the server (true) or not (false).
+struct HasParamHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for HasParamHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("HasParamHandler {:?} ", params);
+
+        type Request = (String, String);
+        let (caller_id, key) = Request::try_from_params(params)?;
+        let key = match self.data.resolve_name(&caller_id, NameKind::Parameter, &key) {
+            Ok(key) => key,
+            Err(err_msg) => return Ok((-1, err_msg, false).try_to_value()?),
+        };
+        let has = self.data.has_param(&key);
+        Ok((1, key, has).try_to_value()?)
+    }
+}
+
+/// Handler for getting a list of all parameter names stored on the server.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `namespace` - Optional. Only return parameters in this namespace, resolved relative to the
+///   caller's namespace. Use empty string to specify all names (string). This is an extension to
+///   the ROS Master API.
+///
+/// # Returns
+///
+/// A tuple of integers, a string, and a list of parameter names:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `parameterNameList` - list of all parameter names stored on the server, or in the given
+///   namespace (list of strings)
+struct GetParamNamesHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetParamNamesHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetParamNamesHandler {:?} ", params);
+        let a = <(String, String)>::try_from_params(params);
+        let b = <(String,)>::try_from_params(params);
+
+        let namespace = match (a, b) {
+            (Ok((caller_id, namespace)), _) => resolve(&caller_id, &namespace),
+            (Err(_), Ok(_)) => String::new(),
+            (Err(e), Err(_)) => return Err(e.into()),
+        };
+        let namespace = namespace.trim_end_matches('/');
+
+        let mut keys: Vec<String> = self.data.parameters.read().unwrap().get_keys();
+        if !namespace.is_empty() {
+            keys.retain(|key| match key.strip_prefix(namespace) {
+                Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                None => false,
+            });
+        }
+        Ok((1, "Parameter names", keys).try_to_value()?)
+    }
+}
+
+/// Handler for listing the nodes subscribed to parameters. This is an extension to the ROS Master
+/// API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `key` - Only list subscriptions that are notified when this parameter changes. Use empty
+///   string to list all subscriptions (string).
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a list of structs representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `subscriptions` - the subscribed node, parameter name and node API URI of each subscription
+///   (list of structs)
+struct GetParamSubscriptionsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetParamSubscriptionsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetParamSubscriptionsHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, key) = Request::try_from_params(params)?;
+        let key = resolve(&caller_id, &key);
+
+        let subscriptions = self.data.param_subscriptions(&key);
+        Ok((1, "", subscriptions).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving registration metadata of a topic. This is an extension to the ROS Master
+/// API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `topic` - Name of the topic (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a struct representing the response:
+///
+/// - `code` - response code (integer), -1 if nothing has ever been registered on the topic
+/// - `statusMessage` - status message (string)
+/// - `topicInfo` - the topic type, the first and last time anything was registered on the topic,
+///   and the registration times of its current publishers and subscribers (struct)
+struct GetTopicInfoHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetTopicInfoHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetTopicInfoHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, topic) = Request::try_from_params(params)?;
+
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, TopicInfo::default()).try_to_value()?),
+        };
+
+        let times = self.data.topic_times.read().unwrap().get(&topic).copied();
+        let Some(times) = times else {
+            let err_msg = format!("unknown topic [{topic}]");
+            return Ok((-1, err_msg, TopicInfo::default()).try_to_value()?);
+        };
+        let topic_type = self.data.topics.read().unwrap().get(&topic).cloned();
+        let info = TopicInfo {
+            topic_type: topic_type.unwrap_or_default(),
+            first_registered: times.first,
+            last_registered: times.last,
+            publishers: self
+                .data
+                .node_registrations(Registration::Publisher, &topic),
+            subscribers: self
+                .data
+                .node_registrations(Registration::Subscriber, &topic),
+        };
+        Ok((1, "", info).try_to_value()?)
+    }
+}
+
+/// Handler for attaching QoS hints to a topic. This is an extension to the ROS Master API.
+///
+/// The topic doesn't need to have publishers or subscribers yet, so operators can declare the
+/// hints before the system starts. Setting all hints to their unspecified values removes them.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `topic` - Name of the topic (string)
+/// - `hints` - `expected_rate` in Hz or 0 (double), `transport` (string) and `reliability`,
+///   `reliable`, `best_effort` or empty (string) (struct)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer), -1 if the hints are invalid
+/// - `statusMessage` - status message (string)
+/// - `ignore` - always 0 (integer)
+struct SetTopicHintsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for SetTopicHintsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("SetTopicHintsHandler {:?} ", params);
+        type Request = (String, String, TopicHints);
+        let (caller_id, topic, hints) = Request::try_from_params(params)?;
+
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, 0).try_to_value()?),
+        };
+
+        if !hints.expected_rate.is_finite() || hints.expected_rate < 0.0 {
+            let err_msg = format!("invalid expected rate {} for [{topic}]", hints.expected_rate);
+            return Ok((-1, err_msg, 0).try_to_value()?);
+        }
+        if !matches!(hints.reliability.as_str(), "" | "reliable" | "best_effort") {
+            let err_msg = format!("invalid reliability '{}' for [{topic}]", hints.reliability);
+            return Ok((-1, err_msg, 0).try_to_value()?);
+        }
+
+        let mut topic_hints = self.data.topic_hints.write().unwrap();
+        if hints == TopicHints::default() {
+            topic_hints.remove(&topic);
+        } else {
+            log::info!("{caller_id} set hints of {topic}: {hints:?}");
+            topic_hints.insert(topic, hints);
+        }
+        Ok((1, "", 0).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving the QoS hints of a topic. This is an extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `topic` - Name of the topic (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a struct representing the response:
+///
+/// - `code` - response code (integer), -1 if no hints are set for the topic
+/// - `statusMessage` - status message (string)
+/// - `hints` - the hints of the topic, see `setTopicHints` (struct)
+struct GetTopicHintsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetTopicHintsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetTopicHintsHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, topic) = Request::try_from_params(params)?;
+
+        let topic = match self.data.resolve_name(&caller_id, NameKind::Topic, &topic) {
+            Ok(topic) => topic,
+            Err(err_msg) => return Ok((-1, err_msg, TopicHints::default()).try_to_value()?),
+        };
+
+        match self.data.topic_hints.read().unwrap().get(&topic) {
+            Some(hints) => Ok((1, "", hints.clone()).try_to_value()?),
+            None => {
+                let err_msg = format!("no hints for [{topic}]");
+                Ok((-1, err_msg, TopicHints::default()).try_to_value()?)
+            }
+        }
+    }
+}
+
+/// Handler for retrieving registration metadata of a service. This is an extension to the ROS
+/// Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `service` - Name of the service (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a struct representing the response:
+///
+/// - `code` - response code (integer), -1 if the service has no providers
+/// - `statusMessage` - status message (string)
+/// - `serviceInfo` - the registration times of the current providers of the service (struct)
+struct GetServiceInfoHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetServiceInfoHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetServiceInfoHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, service) = Request::try_from_params(params)?;
+
+        let service = match self.data.resolve_name(&caller_id, NameKind::Service, &service) {
+            Ok(service) => service,
+            Err(err_msg) => return Ok((-1, err_msg, ServiceInfo::default()).try_to_value()?),
+        };
+
+        let providers = self
+            .data
+            .node_registrations(Registration::Service, &service);
+        if providers.is_empty() {
+            let err_msg = format!("unknown service [{service}]");
+            return Ok((-1, err_msg, ServiceInfo::default()).try_to_value()?);
+        }
+        Ok((1, "", ServiceInfo { providers }).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving everything known about a node. This is an extension to the ROS Master
+/// API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+/// - `node` - Name of the node (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a struct representing the response:
+///
+/// - `code` - response code (integer), -1 if the node is not registered
+/// - `statusMessage` - status message (string)
+/// - `nodeInfo` - the API URI, registrations and last contact of the node (struct). The node is
+///   considered healthy if it answers `getPid` on its API within a second.
+struct GetNodeInfoHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetNodeInfoHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetNodeInfoHandler {:?} ", params);
+        type Request = (String, String);
+        let (caller_id, node) = Request::try_from_params(params)?;
+
+        let Some(api_uri) = self.data.nodes.read().unwrap().get(&node).cloned() else {
+            let err_msg = format!("unknown node [{node}]");
+            return Ok((-1, err_msg, NodeInfo::default()).try_to_value()?);
+        };
+
+        let topics_of = |registrations: &RwLock<HashMap<String, HashSet<String>>>| {
+            let mut topics: Vec<String> = registrations
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(_, nodes)| nodes.contains(&node))
+                .map(|(topic, _)| topic.clone())
+                .collect();
+            topics.sort();
+            topics
+        };
+        let publications = topics_of(&self.data.publications);
+        let subscriptions = topics_of(&self.data.subscriptions);
+        let mut services: Vec<String> = self
+            .data
+            .service_list
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, providers)| providers.contains_key(&node))
+            .map(|(service, _)| service.clone())
+            .collect();
+        services.sort();
+        let last_seen = self
+            .data
+            .sessions
+            .read()
+            .unwrap()
+            .get(&node)
+            .map(|s| s.last_seen)
+            .unwrap_or_default();
+
+        let client_api = self.data.client_api(&api_uri);
+        let probe = client_api.get_pid(&caller_id);
+        let probe = async {
+            if self.data.check_callback(&api_uri).await.is_err() {
+                return Err(());
+            }
+            match tokio::time::timeout(NODE_PROBE_TIMEOUT, probe).await {
+                Ok(Ok(pid)) => Ok(pid),
+                _ => Err(()),
+            }
+        };
+        let healthy = self.data.timed(&api_uri, probe).await.is_ok();
+
+        let info = NodeInfo {
+            node,
+            api_uri,
+            publications,
+            subscriptions,
+            services,
+            last_seen,
+            healthy,
+        };
+        Ok((1, "", info).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving the sessions of all callers. This is an extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a list representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `sessions` - the API URI, first and last call and the number of requests and faults of each
+///   caller, sorted by caller ID (list of structs)
+struct GetSessionsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetSessionsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetSessionsHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        Ok((1, "", self.data.sessions()).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving the `shutdown` calls the master made to nodes, e.g. because another node
+/// registered with the same name. This is an extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a list representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `history` - the node, its API URI, the reason, the time and whether the node acknowledged,
+///   for the latest calls, oldest first (list of structs)
+struct GetShutdownHistoryHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetShutdownHistoryHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetShutdownHistoryHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        Ok((1, "", self.data.shutdown_history()).try_to_value()?)
+    }
+}
+
+/// Handler for retrieving the registrations and load per top-level namespace, so that the
+/// subsystems responsible for the load on the master can be found. This is an extension to the
+/// ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a list representing the response:
+///
+/// - `code` - response code (integer)
+/// - `statusMessage` - status message (string)
+/// - `stats` - the topics, publishers, subscribers, services, registrations, unregistrations and
+///   parameter writes, and the churn and parameter writes per minute of each namespace, sorted
+///   by namespace (list of structs)
+struct GetNamespaceStatsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetNamespaceStatsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetNamespaceStatsHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        Ok((1, "", self.data.namespace_stats()).try_to_value()?)
+    }
+}
+
+/// Handler for reloading the runtime configuration file of the master, see
+/// [`MasterBuilder::config_file`]. This is an extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers and a string representing the response:
+///
+/// - `code` - response code (integer), -1 if the master has no configuration file or it is
+///   invalid, in which case the previous configuration stays in place
+/// - `statusMessage` - status message (string)
+/// - `ignore` - ignore (integer)
+struct ReloadConfigHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for ReloadConfigHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("ReloadConfigHandler {:?} ", params);
+        type Request = String;
+        let caller_id = Request::try_from_params(params)?;
+        match self.data.reload_config() {
+            Ok(()) => Ok((1, "configuration reloaded", 0).try_to_value()?),
+            Err(e) => {
+                log::warn!("Failed to reload the configuration for {caller_id}: {e}");
+                Ok((-1, e.to_string(), 0).try_to_value()?)
+            }
+        }
+    }
+}
+
+/// Handler for retrieving the differences of the graph from the expected topology. This is an
+/// extension to the ROS Master API.
+///
+/// # Parameters
+///
+/// - `caller_id` - ROS caller ID (string)
+///
+/// # Returns
+///
+/// A tuple of integers, a string and a list representing the response:
+///
+/// - `code` - response code (integer), -1 if no topology is expected
+/// - `statusMessage` - status message (string)
+/// - `violations` - one line per missing (`-`), unexpected (`+`) or differently typed (`~`) node,
+///   topic or service, empty if the graph matches (list of strings)
+struct GetTopologyViolationsHandler {
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for GetTopologyViolationsHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("GetTopologyViolationsHandler {:?} ", params);
+        type Request = String;
+        let _caller_id = Request::try_from_params(params)?;
+        let Some(violations) = self.data.topology_violations() else {
+            return Ok((-1, "no expected topology configured", Vec::<String>::new()).try_to_value()?);
+        };
+        let lines: Vec<String> = violations.to_string().lines().map(str::to_owned).collect();
+        Ok((1, "", lines).try_to_value()?)
+    }
+}
+
+/// Wraps a handler to let the [`AuthProvider`] of the master accept or reject each call first.
+struct AuthHandler<H> {
+    auth: Option<Arc<dyn AuthProvider>>,
+    method: &'static str,
+    handler: H,
+}
+#[async_trait]
+impl<H: Handler> Handler for AuthHandler<H> {
+    async fn handle(&self, params: &[Value], headers: HeaderMap) -> HandlerResult {
+        if let Some(auth) = &self.auth {
+            let request = AuthRequest::new(self.method, params, &headers);
+            let decision = auth
+                .authenticate(&request)
+                .and_then(|identity| auth.authorize(&identity, &request));
+            if let Err(e) = decision {
+                log::warn!(
+                    "Rejected {} call of {}: {e:?}",
+                    self.method,
+                    request.caller_id().unwrap_or_default()
+                );
+                return Err(e.into());
+            }
+        }
+        self.handler.handle(params, headers).await
+    }
+}
+
+/// Wraps a handler to enforce the access rules and rate limit of the runtime configuration.
+struct PolicyHandler<H> {
+    data: Arc<RosData>,
+    method: &'static str,
+    handler: H,
+}
+#[async_trait]
+impl<H: Handler> Handler for PolicyHandler<H> {
+    async fn handle(&self, params: &[Value], headers: HeaderMap) -> HandlerResult {
+        let caller_id = params
+            .first()
+            .and_then(|v| String::try_from_value(v).ok())
+            .unwrap_or_default();
+        if let Err(fault) = self.data.check_policy(self.method, &caller_id) {
+            log::debug!("Rejected {} call of {caller_id}: {fault}", self.method);
+            return Err(fault);
+        }
+        self.handler.handle(params, headers).await
+    }
+}
+
+/// Wraps a handler to count the calls of each caller in its session. All master API methods take
+/// the caller ID as their first argument.
+struct SessionHandler<H> {
+    data: Arc<RosData>,
+    handler: H,
+}
+#[async_trait]
+impl<H: Handler> Handler for SessionHandler<H> {
+    async fn handle(&self, params: &[Value], headers: HeaderMap) -> HandlerResult {
+        let result = self.handler.handle(params, headers).await;
+        if let Some(caller_id) = params.first().and_then(|v| String::try_from_value(v).ok()) {
+            self.data.record_request(&caller_id, result.is_err());
+        }
+        result
+    }
+}
+
+/// Handler for debugging output. This handler logs the incoming request parameters as a debug
+/// message and always returns a success response with an empty status message.
+///
+/// # Parameters
+///
+/// - `data` - Shared reference to `RosData` struct (Arc<RosData>)
+///
+/// # Returns
+///
+/// A `HandlerResult` representing a tuple of integers and a string:
+///
+/// - `code` - response code (integer)
+/// - `ignore` ignored (string, always empty in this case)
+/// - `ignore` ignored (string, always empty in this case)
+struct DebugOutputHandler {
+    #[allow(dead_code)]
+    data: Arc<RosData>,
+}
+#[async_trait]
+impl Handler for DebugOutputHandler {
+    async fn handle(&self, params: &[Value], _headers: HeaderMap) -> HandlerResult {
+        log::debug!("DebugOutputHandler {:?} ", params);
+        Ok((1, "", "").try_to_value()?)
+    }
+}
+
+macro_rules! make_handlers {
+    ($self:ident, $($endpoint:expr=>$handlerFn:ident),*) => {{
+        let router = RouteBuilder::new()
+            $(.add_method($endpoint.as_str(), Box::new(SessionHandler {
+                data: $self.data.clone(),
+                handler: AuthHandler {
+                    auth: $self.auth.clone(),
+                    method: $endpoint.as_str(),
+                    handler: PolicyHandler {
+                        data: $self.data.clone(),
+                        method: $endpoint.as_str(),
+                        handler: $handlerFn {
+                            data: $self.data.clone(),
+                        },
+                    },
+                },
+            })))*
+            .build();
+        router
+    }};
+}
+
+/// Answers requests announcing a body larger than `limit` with a fault instead of reading them.
+async fn reject_oversized_request(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    match length {
+        Some(length) if length > limit => {
+            log::warn!("Rejecting request of {length} bytes, the limit is {limit} bytes");
+            let fault = dxr::Fault::new(
+                413,
+                format!("Request of {length} bytes exceeds the limit of {limit} bytes"),
+            );
+            match dxr::serialize_xml(&dxr::FaultResponse::from(fault)) {
+                Ok(body) => (StatusCode::OK, [(header::CONTENT_TYPE, "text/xml")], body).into_response(),
+                Err(_) => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            }
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// Checks the graph against the expected topology whenever it changes. The check can't run where
+/// the events are emitted, since the registry is locked there.
+async fn check_topology(data: Arc<RosData>) {
+    let mut events = data.events.subscribe();
+    loop {
+        data.check_topology();
+        loop {
+            match events.recv().await {
+                Ok(GraphEvent::ParamChanged { .. })
+                | Ok(GraphEvent::ParamDeleted { .. })
+                | Ok(GraphEvent::TopologyChanged { .. }) => continue,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => break,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+/// Forgets idle topics while the master is running, see [`TopicRetention::IdleFor`].
+async fn collect_idle_topics(data: Arc<RosData>, period: Duration) {
+    let mut interval = tokio::time::interval((period / 4).max(Duration::from_millis(100)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        data.collect_idle_topics(period);
+    }
+}
+
+/// Calls `getPid` on every node that isn't exempt and returns the nodes with the API they were
+/// probed at and whether they answered. Nodes that called `heartbeat` within the heartbeat period
+/// count as alive without being called.
+async fn probe_nodes(data: &RosData) -> Vec<(String, String, bool)> {
+    let nodes: Vec<(String, String)> = data
+        .nodes
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(node, _)| !data.liveness.is_exempt(node))
+        .map(|(node, api)| (node.clone(), api.clone()))
+        .collect();
+    let now = data.clock.instant();
+    let beating: HashSet<String> = data
+        .heartbeats
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, last)| now.saturating_duration_since(**last) <= data.liveness.heartbeat_period())
+        .map(|(node, _)| node.clone())
+        .collect();
+    let probes = nodes.into_iter().map(|(node, api)| {
+        let beating = beating.contains(&node);
+        async move {
+            let alive = beating || is_alive(data, &api).await;
+            (node, api, alive)
+        }
+    });
+    futures::future::join_all(probes).await
+}
+
+/// Calls `getPid` on every node periodically and unregisters the nodes that didn't answer often
+/// enough for long enough, see [`MasterBuilder::monitor_liveness`].
+async fn monitor_liveness(data: Arc<RosData>, every: Duration) {
+    let mut interval = tokio::time::interval(every.max(Duration::from_millis(100)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The failures in a row of each node and since when it fails, for the API it was probed at.
+    let mut failures: HashMap<String, (String, u32, Instant)> = HashMap::new();
+    loop {
+        interval.tick().await;
+        let mut still_failing = HashMap::new();
+        for (node, api, alive) in probe_nodes(&data).await {
+            if alive {
+                continue;
+            }
+            let now = data.clock.instant();
+            let (count, since) = match failures.remove(&node) {
+                Some((counted_api, count, since)) if counted_api == api => (count + 1, since),
+                _ => (1, now),
+            };
+            let failing_for = now.saturating_duration_since(since);
+            if count < data.liveness.failures || failing_for < data.liveness.grace {
+                still_failing.insert(node, (api, count, since));
+                continue;
+            }
+            log::warn!("Node '{node}' did not answer at {api} {count} times in a row for {failing_for:?}, unregistering it");
+            unregister_node(&data, &node, &api).await;
+        }
+        failures = still_failing;
+    }
+}
+
+/// Unregisters the nodes that don't answer a single probe, see [`Master::prune_dead_nodes`].
+async fn prune_dead_nodes(data: &Arc<RosData>) -> Vec<String> {
+    let mut pruned = Vec::new();
+    for (node, api, alive) in probe_nodes(data).await {
+        if !alive {
+            log::warn!("Node '{node}' did not answer at {api}, unregistering it");
+            unregister_node(data, &node, &api).await;
+            pruned.push(node);
+        }
+    }
+    pruned.sort();
+    pruned
+}
+
+/// Removes all registrations of `node`, which stopped answering at `api_uri`, and sends the
+/// subscribers of its topics the remaining publishers. Nothing is removed if the node registered
+/// again from another API meanwhile.
+async fn unregister_node(data: &Arc<RosData>, node: &str, api_uri: &str) {
+    if data.nodes.read().unwrap().get(node).is_none_or(|api| api != api_uri) {
+        return;
+    }
+    let remove_from = |registrations: &RwLock<HashMap<String, HashSet<String>>>| {
+        let mut registrations = registrations.write().unwrap();
+        let names: Vec<String> = registrations
+            .iter_mut()
+            .filter_map(|(name, nodes)| nodes.remove(node).then(|| name.clone()))
+            .collect();
+        registrations.retain(|_, nodes| !nodes.is_empty());
+        names
+    };
+    let published = remove_from(&data.publications);
+    let subscribed = remove_from(&data.subscriptions);
+    let services: Vec<String> = {
+        let mut service_list = data.service_list.write().unwrap();
+        let names = service_list
+            .iter_mut()
+            .filter_map(|(service, providers)| providers.remove(node).map(|_| service.clone()))
+            .collect();
+        service_list.retain(|_, providers| !providers.is_empty());
+        names
+    };
+    for topic in &published {
+        data.forget_registration(Registration::Publisher, topic, node);
+    }
+    for topic in &subscribed {
+        data.forget_registration(Registration::Subscriber, topic, node);
+    }
+    for service in &services {
+        data.forget_registration(Registration::Service, service, node);
+    }
+    {
+        let mut param_subscriptions = data.parameter_subscriptions.write().unwrap();
+        let params = param_subscriptions.remove_all(node);
+        data.uncount_registrations(node, params);
+    }
+    data.forget_node_if_unregistered(node);
+    data.emit(GraphEvent::NodeUnresponsive {
+        node: node.to_owned(),
+        api: api_uri.to_owned(),
+    });
+
+    for topic in published {
+        let subscriber_apis = data.subscriber_apis(&topic);
+        let publisher_apis = data.publisher_apis(&topic);
+        for api_uri in &subscriber_apis {
+            publisher_update(data, &topic, api_uri, &publisher_apis).await;
+        }
+        backfill_publisher_updates(data, &topic, subscriber_apis, publisher_apis);
+    }
+}
+
+/// Registers the topics of the nodes in `snapshot` again after the master restarted, see
+/// [`MasterBuilder::warm_start`]. Afterwards, the subscribers of the recovered topics are sent the
+/// publishers if nodes that registered themselves meanwhile share the topics with recovered ones.
+async fn warm_start(data: Arc<RosData>, snapshot: GraphSnapshot) {
+    let recoveries = snapshot
+        .node_apis
+        .iter()
+        .map(|(node, api_uri)| async { (node.as_str(), recover_node(&data, node, api_uri).await) });
+    let mut recovered = HashSet::new();
+    let mut topics = HashSet::new();
+    for (node, recovered_topics) in futures::future::join_all(recoveries).await {
+        if let Some(recovered_topics) = recovered_topics {
+            recovered.insert(node.to_owned());
+            topics.extend(recovered_topics);
+        }
+    }
+    log::info!("Recovered {} of {} nodes from the snapshot", recovered.len(), snapshot.node_apis.len());
+
+    for topic in topics {
+        let mut participants = HashSet::new();
+        for registrations in [&data.publications, &data.subscriptions] {
+            if let Some(nodes) = registrations.read().unwrap().get(&topic) {
+                participants.extend(nodes.iter().cloned());
+            }
+        }
+        // Nodes recovered together still know each other from before the restart.
+        if participants.is_subset(&recovered) {
+            continue;
+        }
+        let publisher_apis = data.publisher_apis(&topic);
+        for api_uri in data.subscriber_apis(&topic) {
+            publisher_update(&data, &topic, &api_uri, &publisher_apis).await;
+        }
+    }
+}
+
+/// Asks `node` at `api_uri` for its publications and subscriptions and registers them, unless the
+/// node registered itself since the master started. Returns the recovered topics, or `None` if
+/// the node wasn't recovered.
+async fn recover_node(data: &RosData, node: &str, api_uri: &str) -> Option<Vec<String>> {
+    if data.nodes.read().unwrap().contains_key(node) {
+        return None;
+    }
+    if let Err(e) = data.check_callback(api_uri).await {
+        log::warn!("Not recovering node '{node}': {e}");
+        return None;
+    }
+    let client_api = data.client_api(api_uri);
+    let calls = async {
+        let publications = client_api.get_publications("/master").await?;
+        let subscriptions = client_api.get_subscriptions("/master").await?;
+        Ok::<_, Error>((publications, subscriptions))
+    };
+    let (publications, subscriptions) = match data.timed(api_uri, calls).await {
+        Ok(((1, _, publications), (1, _, subscriptions))) => (publications, subscriptions),
+        Ok(((code, msg, _), _)) if code != 1 => {
+            log::warn!("Node '{node}' did not list its publications: {msg}");
+            return None;
+        }
+        Ok((_, (_, msg, _))) => {
+            log::warn!("Node '{node}' did not list its subscriptions: {msg}");
+            return None;
+        }
+        Err(e) => {
+            log::warn!("Could not recover node '{node}' at {api_uri}: {e}");
+            return None;
+        }
+    };
+    // The node may have registered itself while it was asked.
+    if data.nodes.read().unwrap().contains_key(node) {
+        return None;
+    }
+
+    let mut topics = Vec::new();
+    for (role, registered) in [
+        (Registration::Publisher, publications),
+        (Registration::Subscriber, subscriptions),
+    ] {
+        for (topic, topic_type) in registered {
+            let topic = match data.resolve_name(node, NameKind::Topic, &topic) {
+                Ok(topic) => topic,
+                Err(err_msg) => {
+                    log::warn!("Not recovering a topic of node '{node}': {err_msg}");
+                    continue;
+                }
+            };
+            if data.config.read().unwrap().is_topic_blocked(&topic) {
+                continue;
+            }
+            let registrations = match role {
+                Registration::Publisher => &data.publications,
+                _ => &data.subscriptions,
+            };
+            registrations
+                .write()
+                .unwrap()
+                .entry(topic.clone())
+                .or_default()
+                .insert(node.to_owned());
+            // Publishers set the type of their topic, subscribers only of topics without one.
+            let mut topic_types = data.topics.write().unwrap();
+            if role == Registration::Publisher {
+                topic_types.insert(topic.clone(), topic_type);
+            } else if topic_type != "*" {
+                topic_types.entry(topic.clone()).or_insert(topic_type);
+            }
+            drop(topic_types);
+            data.record_registration(role, &topic, node, api_uri);
+            topics.push(topic);
+        }
+    }
+    log::info!("Recovered node '{node}' with {} topics", topics.len());
+    Some(topics)
+}
+
+/// Writes the service registry whenever a service is registered or unregistered, see
+/// [`MasterBuilder::export_services`].
+async fn export_services(data: Arc<RosData>, path: PathBuf) {
+    let mut events = data.events.subscribe();
+    loop {
+        data.probe_service_types().await;
+        if let Err(e) = data.write_service_registry(&path) {
+            warn_throttled!(
+                data.log_throttle,
+                "service export",
+                "Failed to export the services to {}: {e}",
+                path.display()
+            );
+        }
+        loop {
+            match events.recv().await {
+                Ok(GraphEvent::ServiceRegistered { .. })
+                | Ok(GraphEvent::ServiceUnregistered { .. })
+                | Ok(GraphEvent::ServiceReplaced { .. })
+                | Err(broadcast::error::RecvError::Lagged(_)) => break,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+        // A burst of registrations, e.g. at bring-up, is written once.
+        while events.try_recv().is_ok() {}
+    }
+}
+
+/// Logs how many warnings the log throttle suppressed once their interval ended, also if no more
+/// warnings follow.
+async fn flush_log_throttle(data: Arc<RosData>) {
+    let period = data.log_throttle.interval().max(Duration::from_millis(100));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        data.log_throttle.flush();
+    }
+}
+
+/// Exports the graph and the parameters while the master is running, see
+/// [`MasterBuilder::export_snapshots`].
+async fn export_snapshots(data: Arc<RosData>, schedule: ExportSchedule) {
+    let mut interval = tokio::time::interval(schedule.every.max(Duration::from_millis(100)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Err(e) = export_snapshot(&data, &schedule) {
+            warn_throttled!(
+                data.log_throttle,
+                "export",
+                "Failed to export the graph to {}: {e}",
+                schedule.directory.display()
+            );
+        }
+    }
+}
+
+/// Writes the current graph and parameters to the export directory and removes the exports
+/// beyond the ones to keep.
+fn export_snapshot(data: &RosData, schedule: &ExportSchedule) -> Result<()> {
+    let dir = &schedule.directory;
+    let io = |path: &Path| {
+        let path = path.to_owned();
+        move |source| Error::Io { path, source }
+    };
+    std::fs::create_dir_all(dir).map_err(io(dir))?;
+    // Timestamps in the file names sort like the exports were made.
+    let time = data.clock.now().format("%Y%m%dT%H%M%S%.3fZ");
+    let state = data.system_state();
+    let topics = data.topics.read().unwrap().clone();
+    let mut snapshot = GraphSnapshot::new(&state, &topics);
+    snapshot.node_apis = data.nodes.read().unwrap().clone().into_iter().collect();
+    let snapshot = serde_json::to_string_pretty(&snapshot)
+        .map_err(|e| Error::InvalidConfig(format!("cannot serialize the graph: {e}")))?;
+    let files = [
+        (format!("graph-{time}.json"), snapshot + "\n"),
+        (format!("graph-{time}.dot"), crate::graph::to_dot(&state)),
+        (format!("params-{time}.yaml"), data.dump_params()?),
+    ];
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).map_err(io(&path))?;
+    }
+
+    let mut exports: Vec<(String, PathBuf)> = std::fs::read_dir(dir)
+        .map_err(io(dir))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let time = ["graph-", "params-"]
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix))?;
+            let time = [".json", ".dot", ".yaml"]
+                .iter()
+                .find_map(|extension| time.strip_suffix(extension))?;
+            Some((time.to_owned(), entry.path()))
+        })
+        .collect();
+    exports.sort();
+    let mut times: Vec<&str> = exports.iter().map(|(time, _)| time.as_str()).collect();
+    times.dedup();
+    if let Some(&oldest_kept) = times.len().checked_sub(schedule.keep).and_then(|n| times.get(n)) {
+        let oldest_kept = oldest_kept.to_owned();
+        for (time, path) in &exports {
+            if *time < oldest_kept {
+                std::fs::remove_file(path).map_err(io(path))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the runtime configuration file at `path`.
+fn read_config(path: &Path) -> Result<RuntimeConfig> {
+    let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    RuntimeConfig::from_yaml(&contents)
+}
+
+/// Merges the members of the dictionary `value` into the parameters in `namespace`. Other values
+/// replace the parameter `namespace`, which can't be the root.
+fn mount_params(params: &mut Parameters, namespace: &str, value: Value) -> Result<()> {
+    let namespace: Vec<&str> = param_path(namespace).collect();
+    match HashMap::<String, Value>::try_from_value(&value) {
+        Ok(members) => {
+            for (k, v) in members {
+                let key = namespace.iter().copied().chain(k.split('/'));
+                params.update_inner(key.filter(|s| !s.is_empty()), v);
+            }
+        }
+        Err(_) if namespace.is_empty() => {
+            return Err(Error::InvalidParams(
+                "only a dictionary can be loaded into /".to_owned(),
+            ))
+        }
+        Err(_) => params.update_inner(namespace.into_iter(), value),
+    }
+    Ok(())
+}
+
+/// The URI nodes reach a master bound to `addr` at, if none is configured, see
+/// [`MasterBuilder::advertised_uri`].
+fn default_advertised_uri(scheme: &str, addr: SocketAddr) -> Url {
+    let env = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let host = env("ROS_HOSTNAME")
+        .or_else(|| env("ROS_IP"))
+        .or_else(|| (!addr.ip().is_unspecified()).then(|| addr.ip().to_string()))
+        .or_else(|| hostname::get().ok()?.into_string().ok())
+        .unwrap_or_else(|| "localhost".to_owned());
+    let host = match host.parse::<Ipv6Addr>() {
+        Ok(ip) => format!("[{ip}]"),
+        Err(_) => host,
+    };
+    Url::parse(&format!("{scheme}://{host}:{}/", addr.port())).unwrap_or_else(|_| {
+        log::warn!("Cannot advertise the master at {host}, using its bind address instead");
+        Url::parse(&format!("{scheme}://{addr}/")).expect("socket addresses are valid hosts")
+    })
+}
+
+/// Asks whatever listens on `addr` for its PID, to tell another ROS master apart from other
+/// processes occupying the port.
+async fn running_master_pid(mut addr: std::net::SocketAddr) -> Option<i32> {
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr {
+            std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::LOCALHOST.into(),
+            std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let url = url::Url::parse(&format!("http://{addr}/")).ok()?;
+    let client = MasterClient::new(&url);
+    let probe = client.get_pid("/ros_core_rs");
+    let (code, _, pid) = tokio::time::timeout(NODE_PROBE_TIMEOUT, probe)
+        .await
+        .ok()?
+        .ok()?;
+    (code == 1).then_some(pid)
+}
+
+fn get_node_id() -> Option<[u8; 6]> {
+    let ip_link = std::process::Command::new("ip")
+        .arg("link")
+        .output()
+        .ok()?
+        .stdout;
+    let ip_link = String::from_utf8_lossy(&ip_link);
+    let mut next_is_mac = false;
+    let mut mac = None;
+    for element in ip_link.split_whitespace() {
+        if next_is_mac {
+            mac = Some(element);
+            break;
+        }
+        if element == "link/ether" {
+            next_is_mac = true;
+        }
+    }
+    let mac = mac?;
+    let mut all_ok = true;
+    let mac: Vec<u8> = mac
+        .split(':')
+        .filter_map(|hex| {
+            let res = u8::from_str_radix(hex, 16);
+            all_ok &= res.is_ok();
+            res.ok()
+        })
+        .collect();
+    if !all_ok {
+        return None;
+    }
+    let mac: [u8; 6] = mac.try_into().ok()?;
+    Some(mac)
+}
+
+/// Configures a [`Master`] before it is started.
+///
+/// # Examples
+///
+/// ```
+/// use ros_core_rs::core::MasterBuilder;
+/// use std::time::Duration;
+///
+/// let master = MasterBuilder::new("0.0.0.0:11311".parse().unwrap())
+///     .advertised_uri("http://robot.local:11311/".parse().unwrap())
+///     .param("/robot/name", dxr::Value::string("r2".to_owned()))
+///     .callback_timeout(Duration::from_secs(2))
+///     .build();
+/// ```
+pub struct MasterBuilder {
+    bind_addr: SocketAddr,
+    advertised_uri: Option<Url>,
+    parameters: Parameters,
+    topic_types: Topics,
+    reserved_services: HashMap<String, String>,
+    expected_topology: Option<GraphSnapshot>,
+    run_id: Option<String>,
+    callback_timeout: Duration,
+    order_publishers_by_latency: bool,
+    probe_service_providers: bool,
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    client_tls: Option<crate::tls::TlsConfig>,
+    #[cfg(feature = "param-encryption")]
+    param_key: Option<[u8; 32]>,
+    log_level: Option<log::LevelFilter>,
+    log_throttle: Duration,
+    max_request_size: usize,
+    capacity: (usize, usize),
+    prune_param_subscribers_after: Option<Duration>,
+    liveness: LivenessCheck,
+    max_param_subscriptions: Option<usize>,
+    param_history: usize,
+    topic_retention: TopicRetention,
+    service_ownership: ServiceOwnership,
+    shutdown_reason: Option<String>,
+    shutdown_hook: Option<ShutdownHook>,
+    clock: Arc<dyn TimeSource>,
+    ready_hook: Option<ReadyHook>,
+    exports: Option<ExportSchedule>,
+    service_export: Option<PathBuf>,
+    warm_start: Option<GraphSnapshot>,
+    config: RuntimeConfig,
+    config_file: Option<PathBuf>,
+    dual_stack: bool,
+    auth: Option<Arc<dyn AuthProvider>>,
+    name_policy: Option<Box<dyn NamePolicy>>,
+    #[cfg(feature = "ui")]
+    ui_dir: Option<PathBuf>,
+    #[cfg(feature = "cors")]
+    cors: Option<CorsConfig>,
+    #[cfg(feature = "unix-socket")]
+    unix_socket: Option<PathBuf>,
+    #[cfg(feature = "tls-server")]
+    tls: Option<(PathBuf, PathBuf)>,
+}
+
+impl MasterBuilder {
+    /// Creates a builder for a master listening on `bind_addr`.
+    pub fn new(bind_addr: SocketAddr) -> Self {
+        Self {
+            bind_addr,
+            advertised_uri: None,
+            parameters: Parameters::HashMap(HashMap::new()),
+            topic_types: Topics::new(),
+            reserved_services: HashMap::new(),
+            expected_topology: None,
+            run_id: None,
+            callback_timeout: DEFAULT_CALLBACK_TIMEOUT,
+            order_publishers_by_latency: false,
+            probe_service_providers: false,
+            #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+            client_tls: None,
+            #[cfg(feature = "param-encryption")]
+            param_key: None,
+            log_level: None,
+            log_throttle: DEFAULT_LOG_THROTTLE,
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            capacity: (0, 0),
+            prune_param_subscribers_after: None,
+            liveness: LivenessCheck::default(),
+            max_param_subscriptions: None,
+            param_history: 0,
+            topic_retention: TopicRetention::Keep,
+            service_ownership: ServiceOwnership::Replace,
+            auth: None,
+            name_policy: None,
+            shutdown_reason: None,
+            shutdown_hook: None,
+            clock: Arc::new(SystemClock),
+            ready_hook: None,
+            exports: None,
+            service_export: None,
+            warm_start: None,
+            config: RuntimeConfig::default(),
+            config_file: None,
+            dual_stack: false,
+            #[cfg(feature = "ui")]
+            ui_dir: None,
+            #[cfg(feature = "cors")]
+            cors: None,
+            #[cfg(feature = "unix-socket")]
+            unix_socket: None,
+            #[cfg(feature = "tls-server")]
+            tls: None,
+        }
+    }
+
+    /// Sets the URI under which nodes reach the master, as returned by `getUri`. This differs from
+    /// the bind address e.g. when listening on `0.0.0.0` or behind port forwarding.
+    ///
+    /// Like with rosmaster, the URI defaults to `http://HOST:PORT/` with the port of the bind
+    /// address, where the host is `ROS_HOSTNAME`, `ROS_IP`, the bind address if it isn't
+    /// unspecified, or else the host name of the machine.
+    pub fn advertised_uri(mut self, uri: Url) -> Self {
+        self.advertised_uri = Some(uri);
+        self
+    }
+
+    /// Sets the parameter `key` before the master starts. Dictionaries are stored as parameter
+    /// trees, like with `setParam`.
+    pub fn param(mut self, key: &str, value: Value) -> Self {
+        self.parameters.update_inner(param_path(key), value);
+        self
+    }
+
+    /// Merges the members of the dictionary `value` into the parameters in `namespace`, like
+    /// [`Master::load_params`]. Other values replace the parameter `namespace`.
+    pub fn params(mut self, namespace: &str, value: Value) -> Result<Self> {
+        mount_params(&mut self.parameters, namespace, value)?;
+        Ok(self)
+    }
+
+    /// Parses a rosparam YAML document and merges it into the parameters in `namespace`, see
+    /// [`MasterBuilder::params`].
+    pub fn params_yaml(self, namespace: &str, yaml: &str) -> Result<Self> {
+        let value = crate::rosparam::parse_yaml(yaml)?;
+        self.params(namespace, value)
+    }
+
+    /// Declares the type of `topic` before any publisher registers it. `getTopicTypes` reports
+    /// it right away, and subscribers asking for another type are warned about like for types
+    /// announced by publishers.
+    pub fn topic_type(mut self, topic: &str, topic_type: &str) -> Self {
+        let topic = format!("/{}", topic.trim_start_matches('/'));
+        self.topic_types.insert(topic, topic_type.to_owned());
+        self
+    }
+
+    /// Validates the graph against `expected` while the master is running. Missing and
+    /// unexpected nodes, topics and services as well as topics of another type are logged,
+    /// reported as [`GraphEvent::TopologyChanged`] and returned by `getTopologyViolations`.
+    pub fn expected_topology(mut self, expected: GraphSnapshot) -> Self {
+        self.expected_topology = Some(expected);
+        self
+    }
+
+    /// Sets what happens when a node registers a service that another node provides already. By
+    /// default, the new node replaces the previous provider like with rosmaster, so each service
+    /// has a single provider. `Reject` makes accidental duplicate service servers fail early.
+    pub fn service_ownership(mut self, ownership: ServiceOwnership) -> Self {
+        self.service_ownership = ownership;
+        self
+    }
+
+    /// Reserves the name `service` for `node`. `registerService` fails for all other nodes.
+    pub fn reserve_service(mut self, service: &str, node: &str) -> Self {
+        let service = format!("/{}", service.trim_start_matches('/'));
+        self.reserved_services.insert(service, node.to_owned());
+        self
+    }
+
+    /// Sets the `/run_id` parameter instead of generating a UUID.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Sets how long the master waits for nodes to answer `publisherUpdate`, `paramUpdate` and
+    /// `shutdown` calls. Defaults to [`DEFAULT_CALLBACK_TIMEOUT`]. The `callback_timeout` of the
+    /// runtime configuration takes precedence, see [`RuntimeConfig`].
+    pub fn callback_timeout(mut self, timeout: Duration) -> Self {
+        self.callback_timeout = timeout;
+        self
+    }
+
+    /// Lists the publishers of a topic in `registerSubscriber` responses and `publisherUpdate`
+    /// calls by how fast they answered the master's calls, so that subscribers on networks with
+    /// several routes connect to the best reachable publisher first. Publishers are probed with
+    /// `getPid` when they register, and later calls such as `paramUpdate` refine the
+    /// measurement. Publishers whose last call failed come last. By default, publishers are
+    /// listed in no particular order.
+    pub fn order_publishers_by_latency(mut self) -> Self {
+        self.order_publishers_by_latency = true;
+        self
+    }
+
+    /// Makes `lookupService` call `getPid` on the node of a service provider before returning
+    /// it, so that clients don't get the URI of a provider that crashed without unregistering.
+    /// Providers are tried in the order they registered, and those that don't answer within a
+    /// second are skipped. If none answers, the service has no provider. By default, any provider
+    /// is returned without a check.
+    pub fn probe_service_providers(mut self) -> Self {
+        self.probe_service_providers = true;
+        self
+    }
+
+    /// Verifies `https://` nodes and authenticates to them according to `tls` when calling their
+    /// API, e.g. with the CA of a private PKI.
+    #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+    pub fn client_tls(mut self, tls: crate::tls::TlsConfig) -> Self {
+        self.client_tls = Some(tls);
+        self
+    }
+
+    /// Encrypts sensitive parameters, see [`RuntimeConfig::sensitive_params`], with the 256 bit
+    /// `key` (ChaCha20-Poly1305) when they are dumped with [`Master::dump_params`], and decrypts
+    /// them in files loaded with [`Master::load_params`]. Keep the key apart from the dumps.
+    #[cfg(feature = "param-encryption")]
+    pub fn param_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.param_key = Some(key);
+        self
+    }
+
+    /// Caps the verbosity of all log output with [`log::set_max_level`] when the master is built.
+    pub fn log_level(mut self, level: log::LevelFilter) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Sets how often recurring warnings, such as failed calls to the same unreachable node, are
+    /// logged at most. Suppressed warnings are counted and reported once the interval ended. A zero
+    /// interval logs every warning. Defaults to [`DEFAULT_LOG_THROTTLE`].
+    pub fn log_throttle(mut self, interval: Duration) -> Self {
+        self.log_throttle = interval;
+        self
+    }
+
+    /// Sets the maximum size of an XML-RPC request in bytes. Larger requests are answered with a
+    /// fault without being read. Defaults to [`DEFAULT_MAX_REQUEST_SIZE`].
+    pub fn max_request_size(mut self, bytes: usize) -> Self {
+        self.max_request_size = bytes;
+        self
+    }
+
+    /// Preallocates the registry for the expected number of nodes and topics.
+    ///
+    /// The maps otherwise grow by doubling while the system starts up and may end up with up to
+    /// twice the capacity they need. Exceeding the hints is fine, they only avoid reallocations.
+    pub fn capacity(mut self, nodes: usize, topics: usize) -> Self {
+        self.capacity = (nodes, topics);
+        self
+    }
+
+    /// Listens on both IPv4 and IPv6, so that nodes of either stack reach the master. The bind
+    /// address is served together with its counterpart in the other IP version, `0.0.0.0` with
+    /// `::` and `127.0.0.1` with `::1`, on the same port.
+    pub fn dual_stack(mut self) -> Self {
+        self.dual_stack = true;
+        self
+    }
+
+    /// Serves the static files in `dir` under `/ui` on the same port as the API, e.g. a web
+    /// dashboard. `/ui/` serves `index.html` of the directory.
+    #[cfg(feature = "ui")]
+    pub fn ui_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.ui_dir = Some(dir.into());
+        self
+    }
+
+    /// Adds CORS headers to all responses, so that browser-based tools served from other origins
+    /// can call the master without a reverse proxy.
+    #[cfg(feature = "cors")]
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Also serves the API on the Unix domain socket at `path`, e.g. for nodes on the same host
+    /// that reach the master as `unix:///run/ros/master.sock`. A stale socket file left behind by
+    /// a crashed master is replaced, and the file is removed when the master stops.
+    #[cfg(feature = "unix-socket")]
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Serves the API over TLS instead of plain HTTP, with the PEM certificate chain in
+    /// `certificate` and the private key in `key`. Nodes then reach the master as
+    /// `https://host:port`. The files are read when the master starts serving.
+    ///
+    /// Most ROS client libraries only speak plain HTTP, so this suits masters used by tools and
+    /// nodes built on this crate.
+    #[cfg(feature = "tls-server")]
+    pub fn tls(mut self, certificate: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.tls = Some((certificate.into(), key.into()));
+        self
+    }
+
+    /// Drops parameter subscriptions whose `paramUpdate` calls have failed continuously for
+    /// `period`, e.g. because the node died without unsubscribing. By default, subscriptions are
+    /// kept until the node unsubscribes or is replaced.
+    pub fn prune_param_subscribers_after(mut self, period: Duration) -> Self {
+        self.prune_param_subscribers_after = Some(period);
+        self
+    }
+
+    /// Calls `getPid` on every registered node every `every` and unregisters the nodes that didn't
+    /// answer `failures` times in a row, e.g. because they were killed before they could
+    /// unregister. Their publications, subscriptions, services and parameter subscriptions are
+    /// removed, and the subscribers of their topics are sent the remaining publishers. By default,
+    /// the registrations of dead nodes are kept until the nodes are replaced, like rosmaster does.
+    ///
+    /// Nodes that call the `heartbeat` extension at least every `every` are not called.
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap())
+    ///     .monitor_liveness(Duration::from_secs(10), 3)
+    ///     .liveness_grace_period(Duration::from_secs(60))
+    ///     .exempt_from_liveness("/gateway")
+    ///     .build();
+    /// ```
+    pub fn monitor_liveness(mut self, every: Duration, failures: u32) -> Self {
+        self.liveness.every = Some(every);
+        self.liveness.failures = failures.max(1);
+        self
+    }
+
+    /// Keeps nodes that stopped answering registered until they have failed for `grace`, see
+    /// [`MasterBuilder::monitor_liveness`], so that nodes behind flaky links survive short
+    /// outages. No grace period by default.
+    pub fn liveness_grace_period(mut self, grace: Duration) -> Self {
+        self.liveness.grace = grace;
+        self
+    }
+
+    /// Never unregisters the nodes in `namespace` because they don't answer, neither with
+    /// [`MasterBuilder::monitor_liveness`] nor with [`Master::prune_dead_nodes`], e.g. for nodes
+    /// that can't be called by the master.
+    pub fn exempt_from_liveness(mut self, namespace: &str) -> Self {
+        let namespace = format!("/{}", namespace.trim_matches('/'));
+        self.liveness.exempt.push(namespace);
+        self
+    }
+
+    /// Sets when topics without publishers and subscribers are forgotten, so that
+    /// `getTopicTypes` and the memory of the master don't grow with every short-lived topic.
+    /// Types declared with [`MasterBuilder::topic_type`] are kept until the topic was used. By
+    /// default, topics are never forgotten, like rosmaster does.
+    pub fn topic_retention(mut self, retention: TopicRetention) -> Self {
+        self.topic_retention = retention;
+        self
+    }
+
+    /// Forgets topics that have had neither publishers nor subscribers for `period`, the same as
+    /// `topic_retention(TopicRetention::IdleFor(period))`.
+    pub fn forget_idle_topics_after(self, period: Duration) -> Self {
+        self.topic_retention(TopicRetention::IdleFor(period))
+    }
+
+    /// Writes the graph and the parameters to `directory` every `every` while the master runs, as
+    /// a flight recorder of the system over a mission. Each export consists of
+    /// `graph-TIME.json`, a [`GraphSnapshot`] that `ros-core-rs graph diff` compares,
+    /// `graph-TIME.dot`, the nodes and topics for Graphviz, and `params-TIME.yaml`, a parameter
+    /// dump like [`Master::dump_params`]. Only the latest `keep` exports are kept, or all of them if
+    /// `keep` is 0.
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap())
+    ///     .export_snapshots("/var/log/ros/graph", Duration::from_secs(60), 24 * 60)
+    ///     .build();
+    /// ```
+    pub fn export_snapshots(
+        mut self,
+        directory: impl Into<PathBuf>,
+        every: Duration,
+        keep: usize,
+    ) -> Self {
+        self.exports = Some(ExportSchedule {
+            directory: directory.into(),
+            every,
+            keep,
+        });
+        self
+    }
+
+    /// Writes the providers of every service to the JSON file at `path` whenever a service is
+    /// registered or unregistered, so that tooling can check offline that a recorded deployment
+    /// had the services it requires, see [`ServiceRegistry`]. The master asks new providers for
+    /// the type of their service, which is left out for providers that don't answer.
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    ///
+    /// let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap())
+    ///     .export_services("/var/log/ros/services.json")
+    ///     .build();
+    /// ```
+    pub fn export_services(mut self, path: impl Into<PathBuf>) -> Self {
+        self.service_export = Some(path.into());
+        self
+    }
+
+    /// Recovers the registrations of running nodes after a restart of the master, so that they
+    /// don't have to be restarted as well. Once the master serves, it asks every node of
+    /// `snapshot` that hasn't registered itself yet for its publications and subscriptions at the
+    /// API URI recorded in [`GraphSnapshot::node_apis`], like the exports of
+    /// [`MasterBuilder::export_snapshots`] have them. Nodes that don't answer are left out.
+    /// Services can't be recovered, since nodes don't list them, and have to be registered again.
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    /// use ros_core_rs::graph::GraphSnapshot;
+    ///
+    /// let snapshot = GraphSnapshot::from_yaml("node_apis: {/talker: 'http://robot:4000/'}").unwrap();
+    /// let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap())
+    ///     .warm_start(snapshot)
+    ///     .build();
+    /// ```
+    pub fn warm_start(mut self, snapshot: GraphSnapshot) -> Self {
+        self.warm_start = Some(snapshot);
+        self
+    }
+
+    /// Limits the number of parameter subscriptions, so that misbehaving nodes can't grow the
+    /// master's memory without bounds. `subscribeParam` calls beyond the limit fail, while nodes
+    /// renewing an existing subscription are still served. Unlimited by default. The
+    /// `max_param_subscriptions` of the runtime configuration takes precedence, see
+    /// [`RuntimeConfig`].
+    pub fn max_param_subscriptions(mut self, limit: usize) -> Self {
+        self.max_param_subscriptions = Some(limit);
+        self
+    }
+
+    /// Keeps the last `len` changes made with `setParam` and `deleteParam`. Nodes that were down
+    /// while parameters changed get the changes affecting them with `subscribeParamWithHistory`.
+    /// No changes are kept by default.
+    pub fn param_history(mut self, len: usize) -> Self {
+        self.param_history = len;
+        self
+    }
+
+    /// Sets the access rules, blocked topics, rate limit and log level, which can be replaced
+    /// while the master is running with [`Master::set_config`].
+    pub fn config(mut self, config: RuntimeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Loads the runtime configuration from the YAML file at `path`, see
+    /// [`MasterBuilder::config`]. The file is read again by `reloadConfig` calls and
+    /// [`Master::reload_config`], e.g. when the binary gets a SIGHUP.
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        self.config = read_config(&path)?;
+        self.config_file = Some(path);
+        Ok(self)
+    }
+
+    /// Checks every call of the master API with `provider` before handling it. Rejected calls are
+    /// answered with a fault.
+    pub fn auth(mut self, provider: impl AuthProvider + 'static) -> Self {
+        self.auth = Some(Arc::new(provider));
+        self
+    }
+
+    /// Lets `policy` rewrite or reject every topic, service and parameter name of calls to the
+    /// master, after it checked and resolved them. Rejected calls fail with the message of the
+    /// policy. The namespaces that filter `getTopics`, `getParamNames` and
+    /// `getParamSubscriptions` are not passed to it.
+    /// See [`NamePolicy`] for an example.
+    pub fn name_policy(mut self, policy: impl NamePolicy + 'static) -> Self {
+        self.name_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Shuts down all registered nodes with `reason` when the master is stopped through
+    /// [`Master::serve_with_shutdown`], so that a whole stack can be torn down from the core.
+    pub fn shutdown_nodes_on_exit(mut self, reason: impl Into<String>) -> Self {
+        self.shutdown_reason = Some(reason.into());
+        self
+    }
+
+    /// Takes timestamps, timeouts and rates from `clock` instead of the system clock, e.g. from a
+    /// PTP-synchronized clock, or from a [`ManualClock`] in tests.
+    pub fn time_source(mut self, clock: Arc<dyn TimeSource>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Calls `hook` with the address the master listens on once it accepts connections, e.g. to
+    /// tell a service manager that nodes can be started. With dual-stack listening, this is the
+    /// address of the first listener.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    ///
+    /// let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap())
+    ///     .on_ready(|addr| eprintln!("listening on {addr}"))
+    ///     .build();
+    /// ```
+    pub fn on_ready(mut self, hook: impl Fn(SocketAddr) + Send + Sync + 'static) -> Self {
+        self.ready_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` with the node, its API URI and the reason before the master asks a node to
+    /// shut down, e.g. because another node registered with the same name. The call is made
+    /// regardless of the hook, which should return quickly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ros_core_rs::core::MasterBuilder;
+    ///
+    /// let master = MasterBuilder::new("0.0.0.0:11311".parse().unwrap())
+    ///     .on_shutdown_node(|node, api_uri, reason| {
+    ///         eprintln!("shutting down {node} at {api_uri}: {reason}");
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn on_shutdown_node(
+        mut self,
+        hook: impl Fn(&str, &str, &str) + Send + Sync + 'static,
+    ) -> Self {
+        self.shutdown_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Creates the configured [`Master`]. Nothing is bound yet, the address is bound and the
+    /// master serves requests once [`Master::serve`] or [`Master::spawn`] is called.
+    pub fn build(self) -> Master {
+        if let Some(level) = self.log_level {
+            log::set_max_level(level);
+        }
+        if let Ok(Some(level)) = self.config.log_level() {
+            log::set_max_level(level);
+        }
+        let run_id = self.run_id.unwrap_or_else(|| {
+            uuid::Uuid::new_v1(
+                uuid::Timestamp::now(ContextV1::new_random()),
+                &get_node_id().unwrap_or_default(),
+            )
+            .to_string()
+        });
+        let mut parameters = self.parameters;
+        parameters.update_inner(["run_id"].into_iter(), Value::string(run_id));
+        #[cfg(feature = "tls-server")]
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        #[cfg(not(feature = "tls-server"))]
+        let scheme = "http";
+        let advertised_uri = self
+            .advertised_uri
+            .unwrap_or_else(|| default_advertised_uri(scheme, self.bind_addr));
+        let log_throttle = LogThrottle::new(self.log_throttle, self.clock.clone());
+        let (nodes, topics) = self.capacity;
+        let mut topic_types = self.topic_types;
+        topic_types.reserve(topics);
+
+        Master {
+            data: Arc::new(RosData {
+                service_list: RwLock::new(Services::new()),
+                nodes: RwLock::new(Nodes::with_capacity(nodes)),
+                topics: RwLock::new(topic_types),
+                subscriptions: RwLock::new(Subscriptions::with_capacity(topics)),
+                publications: RwLock::new(Publishers::with_capacity(topics)),
+                parameters: RwLock::new(parameters),
+                parameter_keys: RwLock::new(None),
+                parameter_subscriptions: RwLock::new(ParamSubscriptions::default()),
+                topic_times: RwLock::new(HashMap::with_capacity(topics)),
+                topic_hints: RwLock::new(HashMap::new()),
+                namespace_activity: Mutex::new(NamespaceActivity::default()),
+                reserved_services: self.reserved_services,
+                expected_topology: self.expected_topology,
+                topology_violations: RwLock::new(GraphDiff::default()),
+                registration_times: RwLock::new(HashMap::new()),
+                registration_counts: Mutex::new(HashMap::new()),
+                sessions: RwLock::new(HashMap::with_capacity(nodes)),
+                events: broadcast::channel(EVENT_CAPACITY).0,
+                shutdown_history: RwLock::new(VecDeque::new()),
+                param_history: Mutex::new(VecDeque::with_capacity(self.param_history)),
+                param_history_len: self.param_history,
+                service_types: Mutex::new(HashMap::new()),
+                shutdown_hook: self.shutdown_hook,
+                clock: self.clock,
+                config: RwLock::new(self.config),
+                config_file: self.config_file,
+                rate_buckets: Mutex::new(HashMap::new()),
+                log_throttle,
+                prune_param_subscribers_after: self.prune_param_subscribers_after,
+                max_param_subscriptions: self.max_param_subscriptions,
+                topic_retention: self.topic_retention,
+                service_ownership: self.service_ownership,
+                name_policy: self.name_policy,
+                idle_topics: Mutex::new(HashMap::new()),
+                callback_timeout: self.callback_timeout,
+                order_publishers_by_latency: self.order_publishers_by_latency,
+                probe_service_providers: self.probe_service_providers,
+                liveness: self.liveness,
+                heartbeats: Mutex::new(HashMap::new()),
+                node_latency: RwLock::new(HashMap::new()),
+                #[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+                client_tls: self.client_tls,
+                #[cfg(feature = "param-encryption")]
+                param_key: self.param_key.as_ref().map(super::param_crypto::ParamKey::new),
+                advertised_uri,
+                uri: self.bind_addr,
+            }),
+            max_request_size: self.max_request_size,
+            shutdown_reason: self.shutdown_reason,
+            ready_hook: self.ready_hook,
+            exports: self.exports,
+            service_export: self.service_export,
+            warm_start: self.warm_start,
+            dual_stack: self.dual_stack,
+            auth: self.auth,
+            #[cfg(feature = "ui")]
+            ui_dir: self.ui_dir,
+            #[cfg(feature = "cors")]
+            cors: self.cors,
+            #[cfg(feature = "unix-socket")]
+            unix_socket: self.unix_socket,
+            #[cfg(feature = "tls-server")]
+            tls: self.tls,
+        }
+    }
+}
+
+impl Master {
+    /// Creates a master listening on `url` with the default configuration. Use [`MasterBuilder`]
+    /// to configure it.
+    pub fn new(url: &SocketAddr) -> Master {
+        MasterBuilder::new(*url).build()
+    }
+
+    /// Loads a rosparam YAML file and mounts its contents under `namespace`.
+    ///
+    /// If the document is a dictionary, each of its top-level keys is set below `namespace`, so
+    /// several files can be mounted into the same namespace as long as their keys do not collide.
+    /// Any other document is stored at `namespace` itself, which therefore must not be `/`.
+    /// Parameters encrypted by [`Master::dump_params`] are decrypted with the parameter
+    /// encryption key.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ros_core_rs::core::Master;
+    ///
+    /// let core = Master::new(&"0.0.0.0:11311".parse().unwrap());
+    /// core.load_params("/planner", "planner.yaml").unwrap();
+    /// core.load_params("/", "robot.yaml").unwrap();
+    /// ```
+    pub fn load_params(&self, namespace: &str, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let value = crate::rosparam::parse_yaml(&contents).map_err(|e| {
+            Error::InvalidParams(format!("failed to load {}: {e}", path.display()))
+        })?;
+        #[cfg(feature = "param-encryption")]
+        let value = match &self.data.param_key {
+            Some(key) => key.decrypt(namespace, value).map_err(|e| {
+                Error::InvalidParams(format!("failed to load {}: {e}", path.display()))
+            })?,
+            None => value,
+        };
+
+        let mut params = self.data.parameters.write().unwrap();
+        mount_params(&mut params, namespace, value).map_err(|_| {
+            Error::InvalidParams(format!(
+                "{} must contain a dictionary to be loaded into /",
+                path.display()
+            ))
+        })?;
+        self.data.invalidate_param_keys();
+        Ok(())
+    }
+
+    /// Serializes all parameters to a rosparam YAML document, which [`Master::load_params`] and
+    /// `rosparam load` read back. Base64 and date values have no rosparam representation and
+    /// make this fail.
+    ///
+    /// Sensitive parameters, see [`RuntimeConfig::sensitive_params`], are encrypted with the key
+    /// set with `MasterBuilder::param_encryption_key` and left out without one.
+    pub fn dump_params(&self) -> Result<String> {
+        self.data.dump_params()
+    }
+
+    /// Returns the nodes that are notified when the parameter `key` changes, or all parameter
+    /// subscriptions if `key` is empty.
+    pub fn param_subscriptions(&self, key: &str) -> Vec<ParamSubscriptionInfo> {
+        self.data.param_subscriptions(key)
+    }
+
+    /// Returns the type of every topic that has been registered, by topic name.
+    pub fn topics(&self) -> Topics {
+        self.data.topics()
+    }
+
+    /// Returns the XML-RPC API URI of every registered node, by node name.
+    pub fn nodes(&self) -> Nodes {
+        self.data.nodes()
+    }
+
+    /// Returns the providers of every service, as service API URI by node name.
+    pub fn services(&self) -> Services {
+        self.data.services()
+    }
+
+    /// Returns the same state as `getSystemState`.
+    pub fn system_state(&self) -> SystemState {
+        self.data.system_state()
+    }
+
+    /// Returns the sessions of all callers, like `getSessions`.
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.data.sessions()
+    }
+
+    /// Returns the latest shutdown calls to nodes, like `getShutdownHistory`.
+    pub fn shutdown_history(&self) -> Vec<ShutdownRecord> {
+        self.data.shutdown_history()
+    }
+
+    /// Returns the registrations and load per top-level namespace, like `getNamespaceStats`.
+    pub fn namespace_stats(&self) -> Vec<NamespaceStats> {
+        self.data.namespace_stats()
+    }
+
+    /// Replaces the runtime configuration of the running master, see [`MasterBuilder::config`].
+    pub fn set_config(&self, config: RuntimeConfig) {
+        self.data.apply_config(config);
+    }
+
+    /// Reads the file given to [`MasterBuilder::config_file`] again and applies it, like
+    /// `reloadConfig`. Registrations and parameters are kept. If the file can't be read or is
+    /// invalid, the previous configuration stays in place.
+    pub fn reload_config(&self) -> Result<()> {
+        self.data.reload_config()
+    }
+
+    /// Takes a snapshot of the graph, e.g. to compare it with the expected topology.
+    pub fn graph_snapshot(&self) -> GraphSnapshot {
+        self.data.graph_snapshot()
+    }
+
+    /// Returns how the graph differs from [`MasterBuilder::expected_topology`], or `None` if no
+    /// topology is expected.
+    pub fn topology_violations(&self) -> Option<GraphDiff> {
+        self.data.topology_violations()
+    }
+
+    /// Returns a receiver of all changes to the graph and the parameters from now on.
+    ///
+    /// Parameters loaded with [`Master::load_params`] are not reported. A receiver that falls more
+    /// than 1024 events behind gets [`broadcast::error::RecvError::Lagged`] and misses the oldest
+    /// events.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ros_core_rs::core::{GraphEvent, Master};
+    ///
+    /// # async fn run() -> ros_core_rs::Result<()> {
+    /// let master = Master::new(&"0.0.0.0:11311".parse().unwrap());
+    /// let mut events = master.subscribe_events();
+    /// let handle = master.spawn().await?;
+    /// while let Ok(event) = events.recv().await {
+    ///     if let GraphEvent::PublisherRegistered { topic, node } = event {
+    ///         println!("{node} publishes {topic}");
+    ///     }
+    /// }
+    /// # handle.stop().await
+    /// # }
+    /// ```
+    pub fn subscribe_events(&self) -> broadcast::Receiver<GraphEvent> {
+        self.data.events.subscribe()
+    }
+
+    /// Calls `shutdown` with `reason` on the API of every registered node and waits for the nodes
+    /// to answer, at most for the callback timeout. Returns the number of nodes that acknowledged.
+    pub async fn shutdown_nodes(&self, reason: &str) -> usize {
+        let nodes = self.data.nodes.read().unwrap().clone();
+        log::info!("Shutting down {} nodes: {reason}", nodes.len());
+        let calls = nodes.into_iter().map(|(node, api_uri)| async move {
+            let res = self.data.shutdown_node(&node, &api_uri, reason).await;
+            if let Err(e) = &res {
+                log::warn!("Error shutting down node '{node}': {e}");
+            }
+            res.is_ok()
+        });
+        futures::future::join_all(calls)
+            .await
+            .into_iter()
+            .filter(|acknowledged| *acknowledged)
+            .count()
+    }
+
+    /// Calls `getPid` on every registered node once and unregisters the nodes that don't answer,
+    /// like [`MasterBuilder::monitor_liveness`] does, but without waiting for repeated failures or
+    /// the grace period. Nodes exempt with [`MasterBuilder::exempt_from_liveness`] are kept.
+    /// Returns the unregistered nodes.
+    pub async fn prune_dead_nodes(&self) -> Vec<String> {
+        prune_dead_nodes(&self.data).await
+    }
+
+    fn create_router(&self) -> axum::Router {
+        // `system.multicall` needs no handler: the XML-RPC server unpacks it and calls the
+        // handlers below, including their session, auth and policy wrappers.
+        make_handlers!(
+            self,
+            MasterEndpoints::RegisterService => RegisterServiceHandler,
+            MasterEndpoints::UnRegisterService => UnRegisterServiceHandler,
+            MasterEndpoints::RegisterSubscriber => RegisterSubscriberHandler,
+            MasterEndpoints::UnregisterSubscriber => UnRegisterSubscriberHandler,
+            MasterEndpoints::RegisterPublisher => RegisterPublisherHandler,
+            MasterEndpoints::UnregisterPublisher => UnRegisterPublisherHandler,
+            MasterEndpoints::LookupNode => LookupNodeHandler,
+            MasterEndpoints::GetPublishedTopics => GetPublishedTopicsHandler,
+            MasterEndpoints::GetTopicTypes => GetTopicTypesHandler,
+            MasterEndpoints::GetSystemState => GetSystemStateHandler,
+            MasterEndpoints::GetUri => GetUriHandler,
+            MasterEndpoints::LookupService => LookupServiceHandler,
+            MasterEndpoints::DeleteParam => DeleteParamHandler,
+            MasterEndpoints::SetParam => SetParamHandler,
+            MasterEndpoints::GetParam => GetParamHandler,
+            MasterEndpoints::SearchParam => SearchParamHandler,
+            MasterEndpoints::SubscribeParam => SubscribeParamHandler,
+            MasterEndpoints::UnsubscribeParam => UnSubscribeParamHandler,
+            MasterEndpoints::HasParam => HasParamHandler,
+            MasterEndpoints::GetParamNames => GetParamNamesHandler,
+            MasterEndpoints::GetPid => GetPidHandler,
+            MasterEndpoints::GetTopicInfo => GetTopicInfoHandler,
+            MasterEndpoints::GetServiceInfo => GetServiceInfoHandler,
+            MasterEndpoints::GetTopics => GetTopicsHandler,
+            MasterEndpoints::GetParamSubscriptions => GetParamSubscriptionsHandler,
+            MasterEndpoints::GetNodeInfo => GetNodeInfoHandler,
+            MasterEndpoints::GetSessions => GetSessionsHandler,
+            MasterEndpoints::GetShutdownHistory => GetShutdownHistoryHandler,
+            MasterEndpoints::ReloadConfig => ReloadConfigHandler,
+            MasterEndpoints::GetTopologyViolations => GetTopologyViolationsHandler,
+            MasterEndpoints::SetTopicHints => SetTopicHintsHandler,
+            MasterEndpoints::GetTopicHints => GetTopicHintsHandler,
+            MasterEndpoints::GetNamespaceStats => GetNamespaceStatsHandler,
+            MasterEndpoints::SubscribeParamWithHistory => SubscribeParamWithHistoryHandler,
+            MasterEndpoints::GetParamCached => GetParamCachedHandler,
+            MasterEndpoints::Heartbeat => HeartbeatHandler,
+            MasterEndpoints::Default => DebugOutputHandler
+        )
+    }
+
+    /// Starts the ROS core server and listens for incoming requests.
+    ///
+    /// The server will listen on the URI specified during the construction of `RosCoreServer`.
+    /// The server router will handle requests to both `/` and `/RPC2`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating if the server started successfully or if there was an error. Failing to
+    /// bind to the address is reported as [`Error::Bind`], or as [`Error::AlreadyRunning`] if
+    /// another ROS master is serving it already.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ros_core_rs::core::Master;
+    /// use url::Url;
+    ///
+    /// let socket_address = ros_core_rs::url_to_socket_addr(&Url::parse("http://0.0.0.0:11311").unwrap());
+    /// let core = Master::new(&socket_address.unwrap());
+    /// core.serve();
+    /// ```
+    pub async fn serve(&self) -> Result<()> {
+        self.serve_with_shutdown(CancellationToken::new()).await
+    }
+
+    /// Like [`Master::serve`], but shuts the server down gracefully and returns once `shutdown`
+    /// is cancelled.
+    ///
+    /// The master stops accepting connections, and requests in flight are completed, including
+    /// the `publisherUpdate` and `paramUpdate` calls they make. A node that doesn't answer holds
+    /// up the shutdown for at most the callback timeout. Afterwards, the registered nodes are shut
+    /// down as well if [`MasterBuilder::shutdown_nodes_on_exit`] is set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ros_core_rs::core::{CancellationToken, Master};
+    ///
+    /// # async fn run() -> ros_core_rs::Result<()> {
+    /// let master = Master::new(&"0.0.0.0:11311".parse().unwrap());
+    /// let shutdown = CancellationToken::new();
+    /// tokio::spawn({
+    ///     let shutdown = shutdown.clone();
+    ///     async move {
+    ///         tokio::signal::ctrl_c().await.ok();
+    ///         shutdown.cancel();
+    ///     }
+    /// });
+    /// master.serve_with_shutdown(shutdown).await
+    /// # }
+    /// ```
+    pub async fn serve_with_shutdown(&self, shutdown: CancellationToken) -> Result<()> {
+        let router = self.router()?;
+        let listeners = self.bind().await?;
+        self.serve_router(router, listeners, shutdown).await
+    }
+
+    /// Binds the configured address and serves the master on a new tokio task.
+    ///
+    /// Unlike [`Master::serve`], the master is running when this returns, and errors binding the
+    /// address are reported right away. Once the future resolves, the listeners are bound and the
+    /// routes are mounted, so nodes can register right away at [`MasterHandle::uri`] without
+    /// polling the master first. The returned handle stops the master and waits for it. Dropping
+    /// the handle leaves the master running in the background.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ros_core_rs::core::Master;
+    ///
+    /// # async fn run() -> ros_core_rs::Result<()> {
+    /// let master = Master::new(&"127.0.0.1:0".parse().unwrap());
+    /// let handle = master.spawn().await?;
+    /// println!("ROS_MASTER_URI={}", handle.uri());
+    /// // ...
+    /// handle.stop().await
+    /// # }
+    /// ```
+    pub async fn spawn(self) -> Result<MasterHandle> {
+        let router = self.router()?;
+        let listeners = self.bind().await?;
+        let local_addr = listeners.tcp[0].local_addr().map_err(|source| Error::Bind {
+            addr: self.data.uri,
+            source,
+        })?;
+        let data = self.data.clone();
+        #[cfg(feature = "tls-server")]
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        #[cfg(not(feature = "tls-server"))]
+        let scheme = "http";
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { self.serve_router(router, listeners, shutdown).await }
+        });
+        Ok(MasterHandle {
+            data,
+            local_addr,
+            scheme,
+            shutdown,
+            task,
+        })
+    }
+
+    /// Builds the router for the XML-RPC API and the optional extras around it.
+    fn router(&self) -> Result<axum::Router> {
+        // Some ROS implementation use /RPC2 like the python subscribers. Some ROS implementation
+        // use / like Foxglove. We serve them all.
+        #[allow(unused_mut)]
+        let mut router: axum::Router = axum::Router::new()
+            .nest("/", self.create_router())
+            .nest("/RPC2", self.create_router());
+        #[cfg(feature = "ui")]
+        if let Some(dir) = &self.ui_dir {
+            if !dir.is_dir() {
+                return Err(Error::Io {
+                    path: dir.clone(),
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "UI directory does not exist",
+                    ),
+                });
+            }
+            log::info!("Serving {} under /ui", dir.display());
+            router = router.nest_service("/ui", tower_http::services::ServeDir::new(dir));
+        }
+        let router = router
+            .layer(middleware::from_fn_with_state(
+                self.max_request_size,
+                reject_oversized_request,
+            ))
+            // Requests without a Content-Length are still read up to the limit only.
+            .layer(DefaultBodyLimit::max(self.max_request_size));
+        #[cfg(feature = "cors")]
+        let router = match &self.cors {
+            Some(cors) => router.layer(cors.layer()?),
+            None => router,
+        };
+        Ok(router)
+    }
+
+    async fn bind(&self) -> Result<Listeners> {
+        // Invalid certificates are reported before taking the port.
+        #[cfg(feature = "tls-server")]
+        let tls = match &self.tls {
+            Some((certificate, key)) => Some(tls_acceptor(certificate, key)?),
+            None => None,
+        };
+        let addr = self.data.uri;
+        let bound = if self.dual_stack {
+            bind_tcp(addr)
+        } else {
+            TcpListener::bind(addr).await
+        };
+        let listener = match bound {
+            Ok(listener) => listener,
+            // The port acts as a lock: whoever binds it first is the master, any other instance
+            // steps back instead of starting a second graph.
+            Err(source) if source.kind() == std::io::ErrorKind::AddrInUse => {
+                return Err(match running_master_pid(addr).await {
+                    Some(pid) => Error::AlreadyRunning { addr, pid },
+                    None => Error::Bind { addr, source },
+                });
+            }
+            Err(source) => return Err(Error::Bind { addr, source }),
+        };
+        log::info!("roscore-rs is listening on {}", listener.local_addr().unwrap_or(addr));
+        let port = listener.local_addr().map_or(addr.port(), |a| a.port());
+        let mut tcp = vec![listener];
+        if self.dual_stack {
+            match dual_stack_counterpart(addr.ip()) {
+                Some(ip) => {
+                    let addr = SocketAddr::new(ip, port);
+                    let listener = bind_tcp(addr).map_err(|source| Error::Bind { addr, source })?;
+                    log::info!("roscore-rs is listening on {addr}");
+                    tcp.push(listener);
+                }
+                None => log::warn!(
+                    "Only listening on {addr}, dual-stack needs an unspecified or loopback address"
+                ),
+            }
+        }
+        Ok(Listeners {
+            tcp,
+            #[cfg(feature = "unix-socket")]
+            unix: match &self.unix_socket {
+                Some(path) => Some(bind_unix(path).await?),
+                None => None,
+            },
+            #[cfg(feature = "tls-server")]
+            tls,
+        })
+    }
+
+    async fn serve_router(
+        &self,
+        router: axum::Router,
+        listeners: Listeners,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let checker = self
+            .data
+            .expected_topology
+            .is_some()
+            .then(|| tokio::spawn(check_topology(self.data.clone())));
+        let collector = match self.data.topic_retention {
+            TopicRetention::IdleFor(period) => {
+                Some(tokio::spawn(collect_idle_topics(self.data.clone(), period)))
+            }
+            _ => None,
+        };
+        let exporter = self
+            .exports
+            .clone()
+            .map(|schedule| tokio::spawn(export_snapshots(self.data.clone(), schedule)));
+        let service_exporter = self
+            .service_export
+            .clone()
+            .map(|path| tokio::spawn(export_services(self.data.clone(), path)));
+        let liveness_monitor = self
+            .data
+            .liveness
+            .every
+            .map(|every| tokio::spawn(monitor_liveness(self.data.clone(), every)));
+        let recovery = self
+            .warm_start
+            .clone()
+            .map(|snapshot| tokio::spawn(warm_start(self.data.clone(), snapshot)));
+        let throttle_flusher = tokio::spawn(flush_log_throttle(self.data.clone()));
+        // The Unix socket stops with the TCP listeners, also when serving one of them fails.
+        let shutdown = shutdown.child_token();
+        #[cfg(feature = "unix-socket")]
+        let unix = listeners.unix.map(|(listener, path)| {
+            tokio::spawn(serve_unix(router.clone(), listener, path, shutdown.clone()))
+        });
+        let watcher = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown.cancelled().await;
+                log::info!("Shutting down");
+            }
+        });
+        let first = listeners.tcp.first().map(TcpListener::local_addr);
+        let servers = listeners.tcp.into_iter().map(|listener| {
+            let router = router.clone();
+            let shutdown = shutdown.clone();
+            #[cfg(feature = "tls-server")]
+            let tls = listeners.tls.clone();
+            async move {
+                #[cfg(feature = "tls-server")]
+                let result = match tls {
+                    Some(acceptor) => {
+                        serve_tls(router, listener, acceptor, shutdown.clone()).await;
+                        Ok(())
+                    }
+                    None => serve_http(router, listener, shutdown.clone()).await,
+                };
+                #[cfg(not(feature = "tls-server"))]
+                let result = serve_http(router, listener, shutdown.clone()).await;
+                // The other listeners stop as well if serving one of them fails.
+                shutdown.cancel();
+                result
+            }
+        });
+        // The listeners queue connections already, they are accepted once the servers run.
+        if let (Some(hook), Some(Ok(addr))) = (&self.ready_hook, first) {
+            hook(addr);
+        }
+        let result: Result<()> = futures::future::join_all(servers).await.into_iter().collect();
+        watcher.abort();
+        shutdown.cancel();
+        #[cfg(feature = "unix-socket")]
+        if let Some(unix) = unix {
+            unix.await.ok();
+        }
+        if let Some(checker) = checker {
+            checker.abort();
+        }
+        if let Some(collector) = collector {
+            collector.abort();
+        }
+        if let Some(exporter) = exporter {
+            exporter.abort();
+        }
+        if let Some(service_exporter) = service_exporter {
+            service_exporter.abort();
+        }
+        if let Some(liveness_monitor) = liveness_monitor {
+            liveness_monitor.abort();
+        }
+        if let Some(recovery) = recovery {
+            recovery.abort();
+        }
+        throttle_flusher.abort();
+        result?;
+        if let Some(reason) = &self.shutdown_reason {
+            self.shutdown_nodes(reason).await;
+        }
+        Ok(())
+    }
+}
+
+/// The sockets a master serves its API on.
+struct Listeners {
+    /// The first listener is bound to the configured address, the second one to its counterpart
+    /// in the other IP version if dual-stack is enabled.
+    tcp: Vec<TcpListener>,
+    #[cfg(feature = "unix-socket")]
+    unix: Option<(tokio::net::UnixListener, PathBuf)>,
+    /// Set if the TCP listener serves TLS.
+    #[cfg(feature = "tls-server")]
+    tls: Option<tokio_rustls::TlsAcceptor>,
+}
+
+/// Binds `addr` without also accepting IPv4 connections on IPv6 sockets, so that the IPv4
+/// counterpart of the address can be bound as well.
+fn bind_tcp(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    // Like `TcpListener::bind`, so that a restarted master gets its port back right away.
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// The address of the other IP version that nodes reach the same way as `ip`, for
+/// [`MasterBuilder::dual_stack`].
+fn dual_stack_counterpart(ip: IpAddr) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(ip) if ip.is_unspecified() => Some(Ipv6Addr::UNSPECIFIED.into()),
+        IpAddr::V4(ip) if ip.is_loopback() => Some(Ipv6Addr::LOCALHOST.into()),
+        IpAddr::V6(ip) if ip.is_unspecified() => Some(Ipv4Addr::UNSPECIFIED.into()),
+        IpAddr::V6(ip) if ip.is_loopback() => Some(Ipv4Addr::LOCALHOST.into()),
+        _ => None,
+    }
+}
+
+/// Binds the Unix socket at `path`. A socket file that nobody listens on anymore is left over
+/// from a master that didn't stop cleanly and is replaced.
+#[cfg(feature = "unix-socket")]
+async fn bind_unix(path: &Path) -> Result<(tokio::net::UnixListener, PathBuf)> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let io_error = |source| Error::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let is_socket = std::fs::symlink_metadata(path).map(|m| m.file_type().is_socket());
+    if is_socket.unwrap_or(false) {
+        if tokio::net::UnixStream::connect(path).await.is_ok() {
+            return Err(io_error(std::io::ErrorKind::AddrInUse.into()));
+        }
+        std::fs::remove_file(path).map_err(io_error)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path).map_err(io_error)?;
+    log::info!("roscore-rs is listening on {}", path.display());
+    Ok((listener, path.to_owned()))
+}
+
+/// Serves `router` on `listener` until `shutdown` is cancelled, then waits for the requests in
+/// flight and removes the socket file.
+#[cfg(feature = "unix-socket")]
+async fn serve_unix(
+    router: axum::Router,
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+    shutdown: CancellationToken,
+) {
+    let listener = &listener;
+    serve_connections(
+        router,
+        &path.display().to_string(),
+        move || async move { listener.accept().await.map(|(stream, _)| stream) },
+        |stream| async { Ok(stream) },
+        shutdown,
+    )
+    .await;
+    if let Err(e) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove {}: {e}", path.display());
+    }
+}
+
+/// Loads the PEM certificate chain and private key the master presents to its clients.
+#[cfg(feature = "tls-server")]
+fn tls_acceptor(certificate: &Path, key: &Path) -> Result<tokio_rustls::TlsAcceptor> {
+    use tokio_rustls::rustls::pki_types::pem::{self, PemObject};
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio_rustls::rustls::{crypto, ServerConfig};
+
+    fn pem_error(path: &Path) -> impl Fn(pem::Error) -> Error + '_ {
+        move |e| match e {
+            pem::Error::Io(source) => Error::Io {
+                path: path.to_owned(),
+                source,
+            },
+            e => Error::InvalidConfig(format!("{}: {e}", path.display())),
+        }
+    }
+
+    let certificates = CertificateDer::pem_file_iter(certificate)
+        .and_then(|certificates| certificates.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(pem_error(certificate))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(pem_error(key))?;
+    let config = ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|config| {
+            config
+                .with_no_client_auth()
+                .with_single_cert(certificates, key)
+        })
+        .map_err(|e| Error::InvalidConfig(format!("invalid TLS certificate or key: {e}")))?;
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serves `router` over TLS on `listener` until `shutdown` is cancelled, then waits for the
+/// requests in flight.
+#[cfg(feature = "tls-server")]
+async fn serve_tls(
+    router: axum::Router,
+    listener: TcpListener,
+    acceptor: tokio_rustls::TlsAcceptor,
+    shutdown: CancellationToken,
+) {
+    let name = match listener.local_addr() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => "the TLS listener".to_owned(),
+    };
+    let listener = &listener;
+    serve_connections(
+        router,
+        &name,
+        move || async move { listener.accept().await.map(|(stream, _)| stream) },
+        move |stream| acceptor.accept(stream),
+        shutdown,
+    )
+    .await;
+}
+
+/// Serves HTTP/1 connections with `router` until `shutdown` is cancelled, then waits for the
+/// requests in flight. `handshake` runs on each accepted connection first, e.g. to set up TLS.
+#[cfg(any(feature = "unix-socket", feature = "tls-server"))]
+async fn serve_connections<C, S, A, H>(
+    router: axum::Router,
+    name: &str,
+    accept: impl Fn() -> A,
+    handshake: impl Fn(C) -> H + Clone + Send + 'static,
+    shutdown: CancellationToken,
+) where
+    A: std::future::Future<Output = std::io::Result<C>>,
+    H: std::future::Future<Output = std::io::Result<S>> + Send,
+    C: Send + 'static,
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    use hyper_util::rt::TokioIo;
+    use hyper_util::server::graceful::GracefulShutdown;
+    use hyper_util::service::TowerToHyperService;
+
+    let connections = GracefulShutdown::new();
+    loop {
+        let stream = tokio::select! {
+            accepted = accept() => match accepted {
+                Ok(stream) => stream,
+                Err(e) => {
+                    // E.g. out of file descriptors, don't spin until some are closed.
+                    log::warn!("Failed to accept a connection on {name}: {e}");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        };
+        let handshake = handshake.clone();
+        let service = TowerToHyperService::new(router.clone());
+        let watcher = connections.watcher();
+        let name = name.to_owned();
+        tokio::spawn(async move {
+            let stream = match handshake(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::debug!("Handshake on {name} failed: {e}");
+                    return;
+                }
+            };
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection).await {
+                log::debug!("Connection on {name} failed: {e}");
+            }
+        });
+    }
+    connections.shutdown().await;
+}
+
+/// Serves `router` with the XML-RPC server of dxr until `shutdown` is cancelled.
+async fn serve_http(
+    router: axum::Router,
+    listener: TcpListener,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let mut server = Server::from_route(router);
+    let trigger = server.shutdown_trigger();
+    let watcher = tokio::spawn(async move {
+        shutdown.cancelled().await;
+        trigger.notify_one();
+    });
+    let result = server.serve_listener(listener).await;
+    watcher.abort();
+    Ok(result?)
+}
+
+/// A master running on a tokio task, returned by [`Master::spawn`].
+pub struct MasterHandle {
+    data: Arc<RosData>,
+    local_addr: SocketAddr,
+    scheme: &'static str,
+    shutdown: CancellationToken,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl MasterHandle {
+    /// The address the master is listening on. If the master was configured with port 0, this is
+    /// the port picked by the operating system.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The URI of the master for `ROS_MASTER_URI`, based on [`MasterHandle::local_addr`]. It uses
+    /// `https` if the master serves TLS.
+    pub fn uri(&self) -> Url {
+        Url::parse(&format!("{}://{}/", self.scheme, self.local_addr))
+            .expect("socket addresses are valid hosts")
+    }
+
+    /// Returns the type of every registered topic, see [`Master::topics`].
+    pub fn topics(&self) -> Topics {
+        self.data.topics()
+    }
+
+    /// Returns the API of every registered node, see [`Master::nodes`].
+    pub fn nodes(&self) -> Nodes {
+        self.data.nodes()
+    }
+
+    /// Returns the providers of every service, see [`Master::services`].
+    pub fn services(&self) -> Services {
+        self.data.services()
+    }
+
+    /// Returns the same state as `getSystemState`, see [`Master::system_state`].
+    pub fn system_state(&self) -> SystemState {
+        self.data.system_state()
+    }
+
+    /// Returns the sessions of all callers, see [`Master::sessions`].
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.data.sessions()
+    }
+
+    /// Returns the latest shutdown calls to nodes, see [`Master::shutdown_history`].
+    pub fn shutdown_history(&self) -> Vec<ShutdownRecord> {
+        self.data.shutdown_history()
+    }
+
+    /// Returns the registrations and load per namespace, see [`Master::namespace_stats`].
+    pub fn namespace_stats(&self) -> Vec<NamespaceStats> {
+        self.data.namespace_stats()
+    }
+
+    /// Serializes all parameters to a rosparam YAML document, see [`Master::dump_params`].
+    pub fn dump_params(&self) -> Result<String> {
+        self.data.dump_params()
+    }
+
+    /// Returns the providers of every service, with the types known from
+    /// [`MasterBuilder::export_services`].
+    pub fn service_registry(&self) -> ServiceRegistry {
+        self.data.service_registry()
+    }
+
+    /// Unregisters the nodes that don't answer `getPid`, see [`Master::prune_dead_nodes`].
+    pub async fn prune_dead_nodes(&self) -> Vec<String> {
+        prune_dead_nodes(&self.data).await
+    }
+
+    /// Writes [`MasterHandle::service_registry`] to the JSON file at `path` right away.
+    pub fn export_services(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.data.write_service_registry(path.as_ref())
+    }
+
+    /// Replaces the runtime configuration, see [`Master::set_config`].
+    pub fn set_config(&self, config: RuntimeConfig) {
+        self.data.apply_config(config);
+    }
+
+    /// Reads the configuration file again and applies it, see [`Master::reload_config`].
+    pub fn reload_config(&self) -> Result<()> {
+        self.data.reload_config()
+    }
+
+    /// Takes a snapshot of the graph, see [`Master::graph_snapshot`].
+    pub fn graph_snapshot(&self) -> GraphSnapshot {
+        self.data.graph_snapshot()
+    }
+
+    /// Returns how the graph differs from the expected topology, see
+    /// [`Master::topology_violations`].
+    pub fn topology_violations(&self) -> Option<GraphDiff> {
+        self.data.topology_violations()
+    }
+
+    /// Returns a receiver of all changes to the graph and the parameters from now on, see
+    /// [`Master::subscribe_events`].
+    pub fn subscribe_events(&self) -> broadcast::Receiver<GraphEvent> {
+        self.data.events.subscribe()
+    }
+
+    /// Whether the master has stopped, by [`MasterHandle::stop`] or because serving failed.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Shuts the master down gracefully, like cancelling the token of
+    /// [`Master::serve_with_shutdown`], and waits until it has stopped.
+    pub async fn stop(self) -> Result<()> {
+        self.shutdown.cancel();
+        self.join().await
+    }
+
+    /// Waits until the master stops and returns the result of serving.
+    pub async fn join(self) -> Result<()> {
+        match self.task.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // The task is never aborted, only the runtime shutting down cancels it.
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_liveness_exempt() {
+    let check = LivenessCheck {
+        exempt: vec!["/gateway".to_owned()],
+        ..LivenessCheck::default()
+    };
+    assert!(check.is_exempt("/gateway"));
+    assert!(check.is_exempt("/gateway/mcu_1"));
+    assert!(!check.is_exempt("/gateway_2/mcu"));
+    assert!(!check.is_exempt("/talker"));
+}
+
+#[test]
+fn test_param_subscriber_registration() {
+    let master = MasterBuilder::new("127.0.0.1:0".parse().unwrap()).build();
+    let data = &master.data;
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let api = "http://127.0.0.1:1/";
+    let subscribe = |key| runtime.block_on(subscribe_param(data, "/node", api.to_owned(), key));
+    let unsubscribe = |key: &str| {
+        let handler = UnSubscribeParamHandler { data: data.clone() };
+        let params = ["/node", api, key].map(|s| Value::string(s.to_owned()));
+        runtime.block_on(handler.handle(&params, HeaderMap::new())).unwrap()
+    };
+    let registered = || data.nodes.read().unwrap().contains_key("/node");
+
+    // a node that only subscribed to parameters stays until its last subscription is gone
+    subscribe("/a").unwrap();
+    subscribe("/b").unwrap();
+    subscribe("/b").unwrap();
+    unsubscribe("/a");
+    assert!(registered());
+    unsubscribe("/b");
+    assert!(!registered());
+
+    // unregistering its last topic keeps the parameter subscriptions of a node
+    subscribe("/a").unwrap();
+    data.record_registration(Registration::Publisher, "/chatter", "/node", api);
+    data.forget_registration(Registration::Publisher, "/chatter", "/node");
+    assert!(registered());
+    assert_eq!(data.param_subscriptions("/a").len(), 1);
+    unsubscribe("/a");
+    assert!(!registered());
+}
+
+#[test]
+fn test_search_param() {
+    // the parameters and namespaces as listed by `ParamValue::get_keys`
+    let params = [
+        "/a", "/a/b", "/ns1", "/ns1/p", "/ns1/ns2", "/ns1/ns2/q", "/ns1/node", "/ns1/node/private",
+        "/robot", "/robot/arm",
+    ];
+    let has_param = |name: &str| name == "/" || params.contains(&name);
+    let search = |caller_id, key| search_param(caller_id, key, has_param);
+
+    // upwards from the namespace of the caller, starting with its private namespace
+    assert_eq!(search("/ns1/ns2/node", "p").as_deref(), Some("/ns1/p"));
+    assert_eq!(search("/ns1/ns2/node", "q").as_deref(), Some("/ns1/ns2/q"));
+    assert_eq!(search("/ns1/node", "private").as_deref(), Some("/ns1/node/private"));
+    assert_eq!(search("/ns1/other", "private"), None);
+    assert_eq!(search("/node", "a").as_deref(), Some("/a"));
+    assert_eq!(search("/", "a").as_deref(), Some("/a"));
+    assert_eq!(search("/ns1/node", "missing"), None);
+
+    // only the first element of the key is searched for
+    assert_eq!(search("/ns1/node", "a/missing").as_deref(), Some("/a/missing"));
+    assert_eq!(search("/ns1/node", "ns2/q").as_deref(), Some("/ns1/ns2/q"));
+    assert_eq!(search("/ns1/node", "robot/arm/").as_deref(), Some("/robot/arm/"));
+    assert_eq!(search("/ns1/node", "ns1/p").as_deref(), Some("/ns1/p"));
+
+    // global keys are not searched for
+    assert_eq!(search("/ns1/node", "/a/b/").as_deref(), Some("/a/b/"));
+    assert_eq!(search("/ns1/node", "/").as_deref(), Some("/"));
+    assert_eq!(search("/ns1/node", "/p"), None);
+}
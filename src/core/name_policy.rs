@@ -0,0 +1,66 @@
+//! Naming conventions enforced by the master, see [`MasterBuilder::name_policy`].
+//!
+//! [`MasterBuilder::name_policy`]: super::MasterBuilder::name_policy
+use std::fmt;
+
+/// What a name passed to a [`NamePolicy`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    Topic,
+    Service,
+    Parameter,
+}
+
+impl NameKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameKind::Topic => "topic",
+            NameKind::Service => "service",
+            NameKind::Parameter => "parameter",
+        }
+    }
+}
+
+impl fmt::Display for NameKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Rewrites or rejects the topic, service and parameter names of calls to the master, e.g. to
+/// enforce the naming conventions of an organization.
+///
+/// The policy sees every name after the master checked it against the ROS naming rules and
+/// resolved it, so it is always global. It has to rewrite a name the same way for every call,
+/// otherwise nodes can't unregister or look up what they registered. It runs for every call that
+/// takes a name, so it should not block for long.
+///
+/// # Example
+///
+/// ```
+/// use ros_core_rs::core::{MasterBuilder, NameKind, NamePolicy};
+///
+/// /// Topics live below `/robot`, and nobody may use a `tmp` namespace.
+/// struct RobotNames;
+///
+/// impl NamePolicy for RobotNames {
+///     fn check(&self, _caller_id: &str, kind: NameKind, name: &str) -> Result<String, String> {
+///         if name.split('/').any(|segment| segment == "tmp") {
+///             return Err(format!("{kind} name [{name}] uses the tmp namespace"));
+///         }
+///         match kind {
+///             NameKind::Topic if !name.starts_with("/robot/") => Ok(format!("/robot{name}")),
+///             _ => Ok(name.to_owned()),
+///         }
+///     }
+/// }
+///
+/// let master = MasterBuilder::new("0.0.0.0:11311".parse().unwrap())
+///     .name_policy(RobotNames)
+///     .build();
+/// ```
+pub trait NamePolicy: Send + Sync {
+    /// Returns the name to use for `name`, a resolved name of a `kind` used by `caller_id`, or
+    /// the status message to reject the call with.
+    fn check(&self, caller_id: &str, kind: NameKind, name: &str) -> Result<String, String>;
+}
@@ -0,0 +1,124 @@
+//! Checking and resolving the names of topics, services and parameters, see
+//! <http://wiki.ros.org/Names>.
+//!
+//! Every handler resolves the names it is called with here, so that e.g. `setParam` and `getParam`
+//! agree on the key of `~x`, `x/` or `/ns/x/`.
+use super::NameKind;
+
+/// Whether `name` follows the ROS naming rules: it starts with a letter, `/` or `~` and continues
+/// with alphanumerics, underscores and slashes, but no `//`.
+pub(crate) fn is_legal_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_ascii_alphabetic() || first == '/' || first == '~')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '/')
+        && !name.contains("//")
+}
+
+/// Checks the names of a call before they are resolved: `caller_id` has to be a legal node name
+/// and `name` a legal name of a `kind`. Returns the status message to reject the call with
+/// otherwise.
+pub(crate) fn check_names(caller_id: &str, kind: NameKind, name: &str) -> Option<String> {
+    if !is_legal_name(caller_id) || caller_id.starts_with('~') {
+        return Some(format!("caller_id [{caller_id}] is not a legal node name"));
+    }
+    if !is_legal_name(name) {
+        return Some(format!("{kind} name [{name}] is not a legal ROS name"));
+    }
+    // The parameter `/` is the whole parameter tree, but there is no topic or service `/`.
+    if name == "/" && kind != NameKind::Parameter {
+        return Some(format!("{kind} name [{name}] cannot be the global namespace"));
+    }
+    None
+}
+
+/// Resolves `name`, used by the node `caller_id`, to a canonical global name: relative names are
+/// resolved in the namespace of the node and private names (`~x`) in the node itself. Caller IDs
+/// without leading slash count as global, and trailing slashes are dropped. The empty name
+/// resolves to `/`.
+pub(crate) fn resolve(caller_id: &str, name: &str) -> String {
+    let caller_id = caller_id.trim_end_matches('/');
+    let resolved = match name.chars().next() {
+        None => String::new(),
+        Some('/') => name.to_owned(),
+        Some('~') => format!("{caller_id}/{}", &name[1..]),
+        Some(_) => match caller_id.rsplit_once('/') {
+            Some((namespace, _node_name)) => format!("{namespace}/{name}"),
+            None => name.to_owned(),
+        },
+    };
+    let mut canonical = String::with_capacity(resolved.len() + 1);
+    for element in param_path(&resolved) {
+        canonical.push('/');
+        canonical.push_str(element);
+    }
+    if canonical.is_empty() {
+        canonical.push('/');
+    }
+    canonical
+}
+
+/// The path of the parameter `name` in the parameter tree, e.g. `ns` and `x` for `/ns/x/`, and
+/// nothing for the root `/`.
+pub(crate) fn param_path(name: &str) -> impl Iterator<Item = &str> {
+    name.split('/').filter(|element| !element.is_empty())
+}
+
+#[test]
+fn test_check_names() {
+    for name in ["/chatter", "chatter", "~private", "/ns/sub_1/", "a1"] {
+        assert!(is_legal_name(name), "{name}");
+    }
+    for name in ["", "//chatter", "/ns//sub", "1topic", "_x", "/ns/~private", "/ch atter", "/ä"] {
+        assert!(!is_legal_name(name), "{name}");
+    }
+    assert_eq!(check_names("/node", NameKind::Topic, "/chatter"), None);
+    assert_eq!(check_names("/node", NameKind::Parameter, "/"), None);
+    assert!(check_names("/node", NameKind::Topic, "/").is_some());
+    assert!(check_names("~node", NameKind::Topic, "/chatter").is_some());
+    assert!(check_names("", NameKind::Parameter, "/x").is_some());
+}
+
+#[test]
+fn test_resolve() {
+    // nodes in the root namespace
+    for (caller_id, name, resolved) in [
+        ("/node", "x", "/x"),
+        ("/node", "x/y", "/x/y"),
+        ("/node", "~x", "/node/x"),
+        ("/node", "/x/", "/x"),
+        ("node", "x", "/x"),
+        ("node", "~x", "/node/x"),
+        ("/", "x", "/x"),
+        ("/", "~x", "/x"),
+        ("/", "~", "/"),
+    ] {
+        assert_eq!(resolve(caller_id, name), resolved, "{name} used by {caller_id}");
+    }
+    // nested and private names
+    for (caller_id, name, resolved) in [
+        ("/a/b/c/node", "x", "/a/b/c/x"),
+        ("/a/b/c/node", "x/y/", "/a/b/c/x/y"),
+        ("/a/b/c/node", "~x/y", "/a/b/c/node/x/y"),
+        ("a/b/node", "x", "/a/b/x"),
+        ("a/b/node", "~x", "/a/b/node/x"),
+        ("/ns/node", "/x", "/x"),
+        ("/ns/node", "x", "/ns/x"),
+        ("/ns/node", "x/y/", "/ns/x/y"),
+        ("/ns/node", "~x", "/ns/node/x"),
+        ("/ns/node", "~x/", "/ns/node/x"),
+        ("/ns/node", "~", "/ns/node"),
+        ("/ns/node", "/ns/x/", "/ns/x"),
+        ("/ns/node", "/", "/"),
+        ("/ns/node", "", "/"),
+        ("ns/node", "x", "/ns/x"),
+        ("/ns/node/", "~x", "/ns/node/x"),
+        ("/ns/node/", "x", "/ns/x"),
+    ] {
+        assert_eq!(resolve(caller_id, name), resolved, "{name} used by {caller_id}");
+    }
+    assert_eq!(param_path("/ns/x/").collect::<Vec<_>>(), ["ns", "x"]);
+    assert_eq!(param_path("/").count(), 0);
+}
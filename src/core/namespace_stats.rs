@@ -0,0 +1,178 @@
+//! Registration churn and parameter writes per top-level namespace, as reported by
+//! `getNamespaceStats`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{NamespaceStats, SystemState};
+
+/// Rates are averaged over about this long.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// The top-level namespace of the global `name`, e.g. `/arm` for `/arm/joint_states`. Names
+/// without a namespace, such as `/rosout`, belong to `/`.
+pub(crate) fn top_level_namespace(name: &str) -> &str {
+    match name.strip_prefix('/').and_then(|rest| rest.split_once('/')) {
+        Some((namespace, _)) => &name[..namespace.len() + 1],
+        None => "/",
+    }
+}
+
+/// A count of events that decays exponentially, so that it approximates the number of events
+/// within the last [`RATE_WINDOW`] without remembering each of them.
+#[derive(Debug)]
+struct DecayingCount {
+    value: f64,
+    updated: Instant,
+}
+
+impl DecayingCount {
+    fn new(now: Instant) -> Self {
+        Self {
+            value: 0.0,
+            updated: now,
+        }
+    }
+
+    fn at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.value * (-elapsed.as_secs_f64() / RATE_WINDOW.as_secs_f64()).exp()
+    }
+
+    fn add(&mut self, now: Instant) {
+        self.value = self.at(now) + 1.0;
+        self.updated = now;
+    }
+}
+
+#[derive(Debug)]
+struct Activity {
+    registrations: usize,
+    unregistrations: usize,
+    param_writes: usize,
+    churn: DecayingCount,
+    recent_param_writes: DecayingCount,
+}
+
+impl Activity {
+    fn new(now: Instant) -> Self {
+        Self {
+            registrations: 0,
+            unregistrations: 0,
+            param_writes: 0,
+            churn: DecayingCount::new(now),
+            recent_param_writes: DecayingCount::new(now),
+        }
+    }
+}
+
+/// What happened in a namespace, see [`NamespaceActivity::record`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Change {
+    Registered,
+    Unregistered,
+    ParamWritten,
+}
+
+/// The changes made in each top-level namespace since the master started.
+#[derive(Debug, Default)]
+pub(crate) struct NamespaceActivity {
+    by_namespace: HashMap<String, Activity>,
+}
+
+impl NamespaceActivity {
+    /// Counts `change` to the global `name` at `now` for its top-level namespace.
+    pub(crate) fn record(&mut self, name: &str, change: Change, now: Instant) {
+        let activity = self
+            .by_namespace
+            .entry(top_level_namespace(name).to_owned())
+            .or_insert_with(|| Activity::new(now));
+        match change {
+            Change::Registered => {
+                activity.registrations += 1;
+                activity.churn.add(now);
+            }
+            Change::Unregistered => {
+                activity.unregistrations += 1;
+                activity.churn.add(now);
+            }
+            Change::ParamWritten => {
+                activity.param_writes += 1;
+                activity.recent_param_writes.add(now);
+            }
+        }
+    }
+
+    /// Combines the recorded activity with the current registrations in `state`, with the rates
+    /// as of `now`, sorted by namespace.
+    pub(crate) fn stats(&self, state: &SystemState, now: Instant) -> Vec<NamespaceStats> {
+        fn entry<'a>(
+            stats: &'a mut HashMap<String, NamespaceStats>,
+            namespace: &str,
+        ) -> &'a mut NamespaceStats {
+            stats
+                .entry(namespace.to_owned())
+                .or_insert_with(|| NamespaceStats {
+                    namespace: namespace.to_owned(),
+                    ..Default::default()
+                })
+        }
+        let mut stats = HashMap::new();
+        let mut topics = std::collections::HashSet::new();
+        for (topic, nodes) in &state.publishers {
+            topics.insert(topic);
+            entry(&mut stats, top_level_namespace(topic)).publishers += nodes.len() as i32;
+        }
+        for (topic, nodes) in &state.subscribers {
+            topics.insert(topic);
+            entry(&mut stats, top_level_namespace(topic)).subscribers += nodes.len() as i32;
+        }
+        for topic in topics {
+            entry(&mut stats, top_level_namespace(topic)).topics += 1;
+        }
+        for (service, nodes) in &state.services {
+            entry(&mut stats, top_level_namespace(service)).services += nodes.len() as i32;
+        }
+        for (namespace, activity) in &self.by_namespace {
+            let stats = entry(&mut stats, namespace);
+            stats.registrations = activity.registrations as i32;
+            stats.unregistrations = activity.unregistrations as i32;
+            stats.param_writes = activity.param_writes as i32;
+            stats.churn_per_minute = activity.churn.at(now);
+            stats.param_writes_per_minute = activity.recent_param_writes.at(now);
+        }
+        let mut stats: Vec<_> = stats.into_values().collect();
+        stats.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        stats
+    }
+}
+
+#[test]
+fn test_namespace_stats() {
+    assert_eq!(top_level_namespace("/arm/joint_states"), "/arm");
+    assert_eq!(top_level_namespace("/arm/left/gripper"), "/arm");
+    assert_eq!(top_level_namespace("/rosout"), "/");
+    assert_eq!(top_level_namespace("/"), "/");
+
+    let now = Instant::now();
+    let mut activity = NamespaceActivity::default();
+    activity.record("/arm/joint_states", Change::Registered, now);
+    activity.record("/arm/joint_states", Change::Unregistered, now);
+    activity.record("/nav/goal", Change::ParamWritten, now);
+    let state = SystemState {
+        publishers: vec![("/arm/joint_states".into(), vec!["/a".into(), "/b".into()])],
+        subscribers: vec![("/arm/cmd".into(), vec!["/a".into()])],
+        services: vec![("/rosout/get_loggers".into(), vec!["/rosout".into()])],
+    };
+    let stats = activity.stats(&state, now + RATE_WINDOW);
+    let namespaces: Vec<_> = stats.iter().map(|s| s.namespace.as_str()).collect();
+    assert_eq!(namespaces, ["/arm", "/nav", "/rosout"]);
+    assert_eq!(
+        (stats[0].topics, stats[0].publishers, stats[0].subscribers),
+        (2, 2, 1)
+    );
+    assert_eq!((stats[0].registrations, stats[0].unregistrations), (1, 1));
+    // Two changes a minute ago count like 2 / e changes within the last minute.
+    assert!((stats[0].churn_per_minute - 2.0 / std::f64::consts::E).abs() < 1e-9);
+    assert_eq!(stats[1].param_writes, 1);
+    assert_eq!(stats[2].services, 1);
+}
@@ -0,0 +1,87 @@
+//! Encryption of sensitive parameters in parameter dumps, see
+//! [`MasterBuilder::param_encryption_key`].
+//!
+//! [`MasterBuilder::param_encryption_key`]: super::MasterBuilder::param_encryption_key
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dxr::{TryFromValue, TryToValue, Value};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::{Error, Result};
+
+const PREFIX: &str = "ENC[chacha20poly1305:";
+
+/// The key sensitive parameters are encrypted with.
+pub(crate) struct ParamKey(LessSafeKey);
+
+impl ParamKey {
+    pub(crate) fn new(key: &[u8; 32]) -> Self {
+        // ChaCha20-Poly1305 takes any 32 byte key.
+        Self(LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).unwrap()))
+    }
+
+    /// Encrypts the parameter `value` into a string that [`ParamKey::decrypt`] turns back into
+    /// the value.
+    pub(crate) fn encrypt(&self, value: &Value) -> Result<Value> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::InvalidParams("no randomness for encryption".to_owned()))?;
+        let mut data = crate::rosparam::to_yaml(value)?.into_bytes();
+        self.0
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| Error::InvalidParams("failed to encrypt a parameter".to_owned()))?;
+        let encoded = BASE64.encode([&nonce[..], &data].concat());
+        Ok(Value::string(format!("{PREFIX}{encoded}]")))
+    }
+
+    /// Decrypts the encrypted strings in the parameter `value`, the parameter `key`.
+    pub(crate) fn decrypt(&self, key: &str, value: Value) -> Result<Value> {
+        if let Ok(members) = HashMap::<String, Value>::try_from_value(&value) {
+            let mut decrypted = HashMap::with_capacity(members.len());
+            for (name, member) in members {
+                let member_key = format!("{}/{name}", key.trim_end_matches('/'));
+                decrypted.insert(name, self.decrypt(&member_key, member)?);
+            }
+            return Ok(decrypted.try_to_value()?);
+        }
+        let Some(encoded) = encrypted(&value) else {
+            return Ok(value);
+        };
+        let invalid = || Error::InvalidParams(format!("cannot decrypt parameter {key}"));
+        let data = BASE64.decode(encoded).map_err(|_| invalid())?;
+        if data.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let (nonce, data) = data.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| invalid())?;
+        let mut data = data.to_vec();
+        let plain = self
+            .0
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| invalid())?;
+        let yaml = std::str::from_utf8(plain).map_err(|_| invalid())?;
+        crate::rosparam::parse_yaml(yaml)
+    }
+}
+
+/// The encoded ciphertext if `value` is an encrypted parameter.
+pub(crate) fn encrypted(value: &Value) -> Option<String> {
+    let s = String::try_from_value(value).ok()?;
+    Some(s.strip_prefix(PREFIX)?.strip_suffix(']')?.to_owned())
+}
+
+#[test]
+fn test_round_trip() {
+    let key = ParamKey::new(&[7; 32]);
+    let secret = crate::rosparam::parse_yaml("{user: admin, pin: 1234}").unwrap();
+    let encrypted = key.encrypt(&secret).unwrap();
+    assert!(self::encrypted(&encrypted).is_some());
+    let tree = HashMap::from([("login".to_owned(), encrypted)]).try_to_value().unwrap();
+    let expected = HashMap::from([("login".to_owned(), secret)]).try_to_value().unwrap();
+    assert_eq!(key.decrypt("/", tree.clone()).unwrap(), expected);
+    assert!(ParamKey::new(&[8; 32]).decrypt("/", tree).is_err());
+}
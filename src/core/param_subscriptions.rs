@@ -0,0 +1,201 @@
+//! The parameter subscriptions of a master, indexed by parameter name.
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::time::Instant;
+
+#[derive(Debug)]
+pub(crate) struct ParamSubscription {
+    pub(crate) node_id: String,
+    pub(crate) param: String,
+    pub(crate) api_uri: String,
+    pub(crate) updates_succeeded: usize,
+    pub(crate) updates_failed: usize,
+    pub(crate) failing_since: Option<Instant>, // first failure of paramUpdate since the last success
+}
+
+impl ParamSubscription {
+    pub(crate) fn new(node_id: String, param: String, api_uri: String) -> Self {
+        Self {
+            node_id,
+            param,
+            api_uri,
+            updates_succeeded: 0,
+            updates_failed: 0,
+            failing_since: None,
+        }
+    }
+}
+
+/// Parameter subscriptions ordered by parameter and node. The parameters in a namespace `ns` are
+/// neighbours in this order, all starting with `ns/`, so the subscriptions affected by a change
+/// are found without visiting all the others.
+#[derive(Debug, Default)]
+pub(crate) struct ParamSubscriptions {
+    by_param: BTreeMap<String, BTreeMap<String, ParamSubscription>>,
+    len: usize,
+}
+
+impl ParamSubscriptions {
+    /// Adds `subscription`, or replaces the one of the same node to the same parameter, e.g.
+    /// after the node restarted. New subscriptions are rejected if there are `limit` already.
+    pub(crate) fn insert(&mut self, subscription: ParamSubscription, limit: Option<usize>) -> bool {
+        let nodes = match self.by_param.get_mut(&subscription.param) {
+            Some(nodes) if nodes.contains_key(&subscription.node_id) => {
+                nodes.insert(subscription.node_id.clone(), subscription);
+                return true;
+            }
+            _ if limit.is_some_and(|limit| self.len >= limit) => return false,
+            Some(nodes) => nodes,
+            None => self.by_param.entry(subscription.param.clone()).or_default(),
+        };
+        nodes.insert(subscription.node_id.clone(), subscription);
+        self.len += 1;
+        true
+    }
+
+    /// Whether `node_id` is subscribed to `param`.
+    pub(crate) fn contains(&self, node_id: &str, param: &str) -> bool {
+        self.by_param
+            .get(param)
+            .is_some_and(|nodes| nodes.contains_key(node_id))
+    }
+
+    /// Removes the subscriptions to `param` of the node API `api_uri`. Returns the nodes whose
+    /// subscriptions were removed.
+    pub(crate) fn remove(&mut self, param: &str, api_uri: &str) -> Vec<String> {
+        let Some(nodes) = self.by_param.get_mut(param) else {
+            return Vec::new();
+        };
+        let removed: Vec<String> = nodes
+            .iter()
+            .filter(|(_, subscription)| subscription.api_uri == api_uri)
+            .map(|(node_id, _)| node_id.clone())
+            .collect();
+        for node_id in &removed {
+            nodes.remove(node_id);
+        }
+        if nodes.is_empty() {
+            self.by_param.remove(param);
+        }
+        self.len -= removed.len();
+        removed
+    }
+
+    /// Removes the subscription of `node_id` to `param`. Returns whether there was one.
+    pub(crate) fn remove_node(&mut self, node_id: &str, param: &str) -> bool {
+        let Some(nodes) = self.by_param.get_mut(param) else {
+            return false;
+        };
+        let removed = nodes.remove(node_id).is_some();
+        if removed {
+            self.len -= 1;
+        }
+        if nodes.is_empty() {
+            self.by_param.remove(param);
+        }
+        removed
+    }
+
+    /// Removes all subscriptions of `node_id`. Returns how many there were.
+    pub(crate) fn remove_all(&mut self, node_id: &str) -> usize {
+        let before = self.len;
+        self.by_param.retain(|_, nodes| {
+            if nodes.remove(node_id).is_some() {
+                self.len -= 1;
+            }
+            !nodes.is_empty()
+        });
+        before - self.len
+    }
+
+    pub(crate) fn get_mut(&mut self, node_id: &str, param: &str) -> Option<&mut ParamSubscription> {
+        self.by_param.get_mut(param)?.get_mut(node_id)
+    }
+
+    /// Returns the subscriptions to `key`, to the namespaces containing it and to the parameters
+    /// in it, i.e. those that are notified when `key` changes, like rosmaster does. Names are
+    /// compared by their path segments, so `/foo_bar` doesn't affect `/foo`. The result is ordered
+    /// by parameter and node.
+    pub(crate) fn affected_by<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a ParamSubscription> + 'a {
+        let key = key.trim_end_matches('/');
+        // For `/ns/x`, these are ``, `/`, `/ns`, `/ns/` and `/ns/x`.
+        let ancestors = key
+            .match_indices('/')
+            .flat_map(|(i, _)| [&key[..i], &key[..=i]])
+            .chain([key])
+            .filter_map(|param| self.by_param.get(param));
+        let namespace = format!("{key}/");
+        let descendants = self
+            .by_param
+            .range::<str, _>((Bound::Included(namespace.as_str()), Bound::Unbounded))
+            .take_while(move |(param, _)| param.starts_with(&namespace))
+            .map(|(_, nodes)| nodes);
+        ancestors.chain(descendants).flat_map(|nodes| nodes.values())
+    }
+}
+
+#[test]
+fn test_param_subscriptions() {
+    let mut subscriptions = ParamSubscriptions::default();
+    for (node, param) in [("/a", "/ns"), ("/b", "/ns/x"), ("/c", "/ns/x/y"), ("/d", "/other")] {
+        let subscription = ParamSubscription::new(node.into(), param.into(), format!("http://{node}"));
+        assert!(subscriptions.insert(subscription, Some(4)));
+    }
+    let affected = |subscriptions: &ParamSubscriptions, key| -> Vec<String> {
+        subscriptions.affected_by(key).map(|s| s.node_id.clone()).collect()
+    };
+    assert_eq!(affected(&subscriptions, "/ns/x"), ["/a", "/b", "/c"]);
+    assert_eq!(affected(&subscriptions, "/ns/x/"), ["/a", "/b", "/c"]);
+    assert_eq!(affected(&subscriptions, "/ns/x/y/z"), ["/a", "/b", "/c"]);
+    assert_eq!(affected(&subscriptions, "/ns/xy"), ["/a"]);
+    assert!(affected(&subscriptions, "/n").is_empty());
+    assert!(affected(&subscriptions, "/ns_bar").is_empty());
+    assert_eq!(affected(&subscriptions, "/other/z"), ["/d"]);
+    assert_eq!(affected(&subscriptions, "").len(), 4);
+    assert_eq!(affected(&subscriptions, "/").len(), 4);
+
+    // Replacing a subscription doesn't count against the limit, adding one does.
+    let replaced = ParamSubscription::new("/a".into(), "/ns".into(), "http://new".into());
+    assert!(subscriptions.insert(replaced, Some(4)));
+    let added = ParamSubscription::new("/e".into(), "/ns".into(), "http://e".into());
+    assert!(!subscriptions.insert(added, Some(4)));
+    assert_eq!(subscriptions.len, 4);
+
+    assert!(subscriptions.contains("/a", "/ns"));
+    assert!(subscriptions.remove("/ns", "http:///a").is_empty());
+    assert_eq!(subscriptions.remove("/ns", "http://new"), ["/a"]);
+    assert!(!subscriptions.contains("/a", "/ns"));
+    assert!(subscriptions.remove_node("/b", "/ns/x"));
+    assert!(!subscriptions.remove_node("/b", "/ns/x"));
+    assert_eq!(affected(&subscriptions, "/ns"), ["/c"]);
+    assert_eq!(subscriptions.len, 2);
+    assert_eq!(subscriptions.remove_all("/c"), 1);
+    assert_eq!(subscriptions.remove_all("/c"), 0);
+    assert_eq!(affected(&subscriptions, "/"), ["/d"]);
+}
+
+#[test]
+fn test_param_subscriptions_segments() {
+    let mut subscriptions = ParamSubscriptions::default();
+    // `!` sorts before `/` and `_` after it, neither may end the namespace of `/foo` early.
+    for (node, param) in [("/a", "/"), ("/b", "/foo"), ("/c", "/foo!"), ("/d", "/foo/")] {
+        let subscription = ParamSubscription::new(node.into(), param.into(), format!("http://{node}"));
+        assert!(subscriptions.insert(subscription, None));
+    }
+    for (node, param) in [("/e", "/foo/x"), ("/f", "/foo_bar")] {
+        let subscription = ParamSubscription::new(node.into(), param.into(), format!("http://{node}"));
+        assert!(subscriptions.insert(subscription, None));
+    }
+    let affected = |key| -> Vec<String> {
+        subscriptions.affected_by(key).map(|s| s.node_id.clone()).collect()
+    };
+    assert_eq!(affected("/foo"), ["/a", "/b", "/d", "/e"]);
+    assert_eq!(affected("/foo/x"), ["/a", "/b", "/d", "/e"]);
+    assert_eq!(affected("/foo/y"), ["/a", "/b", "/d"]);
+    assert_eq!(affected("/foo_bar"), ["/a", "/f"]);
+    assert_eq!(affected("/fo"), ["/a"]);
+    assert_eq!(affected("/").len(), 6);
+}
@@ -0,0 +1,79 @@
+//! Several independent masters in one process, e.g. for integration tests that each need their
+//! own ROS graph.
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use super::{Master, MasterHandle};
+use crate::Result;
+
+/// Runs masters side by side, each with its own registry and parameters, and stops them
+/// individually or all at once.
+///
+/// # Example
+///
+/// ```no_run
+/// use ros_core_rs::core::MasterPool;
+///
+/// # async fn run() -> ros_core_rs::Result<()> {
+/// let mut pool = MasterPool::new();
+/// let a = pool.spawn_ephemeral().await?.uri();
+/// let b = pool.spawn_ephemeral().await?.local_addr();
+/// // ... point one set of nodes at `a` and another at `b`
+/// pool.stop(b).await;
+/// pool.stop_all().await
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MasterPool {
+    masters: BTreeMap<SocketAddr, MasterHandle>,
+}
+
+impl MasterPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts serving `master` and adds it to the pool, see [`Master::spawn`].
+    pub async fn spawn(&mut self, master: Master) -> Result<&MasterHandle> {
+        let handle = master.spawn().await?;
+        Ok(self.masters.entry(handle.local_addr()).or_insert(handle))
+    }
+
+    /// Starts a master with the default configuration on a free port of the loopback interface.
+    pub async fn spawn_ephemeral(&mut self) -> Result<&MasterHandle> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        self.spawn(Master::new(&addr)).await
+    }
+
+    /// Returns the master listening on `addr`.
+    pub fn get(&self, addr: SocketAddr) -> Option<&MasterHandle> {
+        self.masters.get(&addr)
+    }
+
+    /// Returns all masters of the pool, ordered by address.
+    pub fn iter(&self) -> impl Iterator<Item = &MasterHandle> {
+        self.masters.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.masters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.masters.is_empty()
+    }
+
+    /// Stops the master listening on `addr` and removes it from the pool. Returns `None` if there
+    /// is no such master, or the result of serving.
+    pub async fn stop(&mut self, addr: SocketAddr) -> Option<Result<()>> {
+        let handle = self.masters.remove(&addr)?;
+        Some(handle.stop().await)
+    }
+
+    /// Stops all masters and returns the first error any of them ended with.
+    pub async fn stop_all(&mut self) -> Result<()> {
+        let handles = std::mem::take(&mut self.masters);
+        let results = futures::future::join_all(handles.into_values().map(MasterHandle::stop));
+        results.await.into_iter().collect()
+    }
+}
@@ -0,0 +1,78 @@
+//! The clock a master takes its timestamps and timeouts from, see
+//! [`MasterBuilder::time_source`].
+//!
+//! [`MasterBuilder::time_source`]: super::MasterBuilder::time_source
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// Where a master takes the current time from.
+///
+/// Wall-clock times stamp what the master reports, e.g. the registration times of `getTopicInfo`,
+/// the sessions of `getSessions` and the shutdown history. Monotonic instants measure timeouts,
+/// rates and latencies, and must never go backwards. Implementations can be backed by GPS or PTP
+/// clocks, or controlled by tests, see [`ManualClock`].
+pub trait TimeSource: Send + Sync {
+    /// The current time in UTC.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current monotonic time. Defaults to [`Instant::now`].
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The clock of the operating system, which masters use by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when it is advanced, for tests of time-dependent behaviour.
+///
+/// ```
+/// use ros_core_rs::core::{ManualClock, TimeSource};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new("2024-01-01T00:00:00Z".parse().unwrap());
+/// let start = clock.instant();
+/// clock.advance(Duration::from_secs(90));
+/// assert_eq!(clock.instant() - start, Duration::from_secs(90));
+/// assert_eq!(clock.now().to_rfc3339(), "2024-01-01T00:01:30+00:00");
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    start: (DateTime<Utc>, Instant),
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a clock standing at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            start: (now, Instant::now()),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl TimeSource for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = *self.elapsed.lock().unwrap();
+        self.start.0 + chrono::Duration::from_std(elapsed).unwrap_or(chrono::Duration::MAX)
+    }
+
+    fn instant(&self) -> Instant {
+        self.start.1 + *self.elapsed.lock().unwrap()
+    }
+}
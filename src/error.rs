@@ -0,0 +1,98 @@
+//! Error type returned by the public API of this crate.
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[cfg(feature = "client")]
+use dxr_client::ClientError;
+
+/// Errors returned by [`Master`](crate::core::Master), [`MasterClient`](crate::core::MasterClient)
+/// and [`ClientApi`](crate::client_api::ClientApi).
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The master could not listen on its address, e.g. because the port is already in use.
+    #[error("failed to bind to {addr}: {source}")]
+    Bind {
+        addr: SocketAddr,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Another ROS master is already serving the address.
+    #[error("another ROS master (pid {pid}) is already running on {addr}")]
+    AlreadyRunning { addr: SocketAddr, pid: i32 },
+    /// The HTTP server failed while serving requests.
+    #[cfg(feature = "server")]
+    #[error("server error: {0}")]
+    Server(#[from] dxr_server::ServerError),
+    /// The remote side answered with an XML-RPC fault.
+    #[error("{uri} returned a fault: {fault}")]
+    Fault { uri: String, fault: dxr::Fault },
+    /// A request could not be encoded or a response could not be decoded.
+    #[error("failed to decode XML-RPC data: {0}")]
+    Decode(#[from] dxr::DxrError),
+    /// A master or node could not be reached.
+    #[error("{uri} is unreachable: {source}")]
+    UnreachableNode {
+        uri: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The master may not call a node API outside the networks of its configuration, see
+    /// [`RuntimeConfig::may_call`](crate::core::RuntimeConfig::may_call).
+    #[error("{uri} is in a network the master may not call")]
+    CallbackRefused { uri: String },
+    /// A URI could not be parsed or does not describe a usable address.
+    #[error("invalid URI '{0}'")]
+    InvalidUri(String),
+    /// A file could not be read or written.
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// A file is not a usable ROS bag, e.g. because it is truncated or has no index.
+    #[error("{} is not a usable bag: {reason}", path.display())]
+    InvalidBag { path: PathBuf, reason: String },
+    /// The configuration of the master is invalid.
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    /// A parameter document could not be parsed or contains values that can't be stored.
+    #[error("invalid parameters: {0}")]
+    InvalidParams(String),
+    /// A service request or response doesn't match the definition of the service type, or the
+    /// definition wasn't found.
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+    /// A ROS service refused the connection or answered a call with an error.
+    #[error("service {service} failed: {message}")]
+    ServiceFailed { service: String, message: String },
+}
+
+/// A `Result` alias where the `Err` case is [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(feature = "client")]
+impl Error {
+    /// Attributes an error of the XML-RPC client to the endpoint it was talking to.
+    pub(crate) fn from_client(uri: &str, error: ClientError) -> Self {
+        match error {
+            ClientError::Fault { fault } => Error::Fault {
+                uri: uri.to_owned(),
+                fault,
+            },
+            ClientError::RPC { error } => Error::Decode(error),
+            ClientError::Net { error } => Error::UnreachableNode {
+                uri: uri.to_owned(),
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+/// For parameter documents, other YAML documents map their errors explicitly.
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::InvalidParams(error.to_string())
+    }
+}
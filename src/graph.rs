@@ -0,0 +1,248 @@
+//! Snapshots of the ROS graph and their comparison, e.g. to validate a bring-up against the
+//! topology it is expected to have.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{SystemState, Topics};
+
+/// The nodes, topics and services of a running system.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    #[serde(default)]
+    pub nodes: BTreeSet<String>,
+    /// The type of every topic, empty if no publisher or subscriber announced it yet.
+    #[serde(default)]
+    pub topics: BTreeMap<String, String>,
+    #[serde(default)]
+    pub services: BTreeSet<String>,
+    /// The XML-RPC API URI of every node, as far as known. Exported snapshots have it so that a
+    /// restarted master can ask the nodes for their topics, see `MasterBuilder::warm_start`. They
+    /// are not compared by [`GraphSnapshot::diff`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_apis: BTreeMap<String, String>,
+}
+
+impl GraphSnapshot {
+    /// Parses a snapshot or an expected topology from YAML or JSON.
+    ///
+    /// ```
+    /// let expected = ros_core_rs::graph::GraphSnapshot::from_yaml(
+    ///     "nodes: [/talker, /listener]\ntopics: {/chatter: std_msgs/String}",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn from_yaml(contents: &str) -> crate::Result<Self> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| crate::Error::InvalidConfig(format!("not a graph snapshot: {e}")))
+    }
+
+    /// Builds a snapshot from the result of `getSystemState` and `getTopicTypes`.
+    pub fn new(state: &SystemState, topic_types: &Topics) -> Self {
+        let mut snapshot = GraphSnapshot::default();
+        for (topic, nodes) in state.publishers.iter().chain(&state.subscribers) {
+            let topic_type = topic_types.get(topic).cloned().unwrap_or_default();
+            snapshot.topics.insert(topic.clone(), topic_type);
+            snapshot.nodes.extend(nodes.iter().cloned());
+        }
+        for (service, nodes) in &state.services {
+            snapshot.services.insert(service.clone());
+            snapshot.nodes.extend(nodes.iter().cloned());
+        }
+        snapshot
+    }
+
+    /// Returns what changed from `self` to `other`. Topics without a known type in either
+    /// snapshot are not reported as type changes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ros_core_rs::graph::GraphSnapshot;
+    ///
+    /// let expected = GraphSnapshot {
+    ///     nodes: ["/talker".to_owned()].into(),
+    ///     topics: [("/chatter".to_owned(), "std_msgs/String".to_owned())].into(),
+    ///     ..Default::default()
+    /// };
+    /// let diff = expected.diff(&GraphSnapshot::default());
+    /// assert_eq!(diff.removed_nodes, ["/talker"]);
+    /// ```
+    pub fn diff(&self, other: &GraphSnapshot) -> GraphDiff {
+        fn added<'a>(
+            from: impl Iterator<Item = &'a String>,
+            to: impl Iterator<Item = &'a String>,
+        ) -> Vec<String> {
+            let from: BTreeSet<_> = from.collect();
+            to.filter(|name| !from.contains(name)).cloned().collect()
+        }
+        GraphDiff {
+            added_nodes: added(self.nodes.iter(), other.nodes.iter()),
+            removed_nodes: added(other.nodes.iter(), self.nodes.iter()),
+            added_topics: added(self.topics.keys(), other.topics.keys()),
+            removed_topics: added(other.topics.keys(), self.topics.keys()),
+            type_changes: self
+                .topics
+                .iter()
+                .filter_map(|(topic, old)| match other.topics.get(topic) {
+                    Some(new) if new != old && !new.is_empty() && !old.is_empty() => {
+                        Some((topic.clone(), old.clone(), new.clone()))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            added_services: added(self.services.iter(), other.services.iter()),
+            removed_services: added(other.services.iter(), self.services.iter()),
+        }
+    }
+}
+
+/// The differences between two [`GraphSnapshot`]s. All lists are sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_topics: Vec<String>,
+    pub removed_topics: Vec<String>,
+    /// Topics whose type changed, as `(topic, old type, new type)`.
+    pub type_changes: Vec<(String, String, String)>,
+    pub added_services: Vec<String>,
+    pub removed_services: Vec<String>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == GraphDiff::default()
+    }
+}
+
+/// One line per difference, prefixed with `+`, `-` or `~` like a diff.
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.added_nodes {
+            writeln!(f, "+ node {node}")?;
+        }
+        for node in &self.removed_nodes {
+            writeln!(f, "- node {node}")?;
+        }
+        for topic in &self.added_topics {
+            writeln!(f, "+ topic {topic}")?;
+        }
+        for topic in &self.removed_topics {
+            writeln!(f, "- topic {topic}")?;
+        }
+        for (topic, old, new) in &self.type_changes {
+            writeln!(f, "~ topic {topic}: {old} -> {new}")?;
+        }
+        for service in &self.added_services {
+            writeln!(f, "+ service {service}")?;
+        }
+        for service in &self.removed_services {
+            writeln!(f, "- service {service}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The providers of every service, as exported by
+/// [`MasterBuilder::export_services`](crate::core::MasterBuilder::export_services) for tooling
+/// that checks recorded deployments offline.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceRegistry {
+    /// The providers of each service, sorted by node.
+    #[serde(default)]
+    pub services: BTreeMap<String, Vec<ServiceProvider>>,
+}
+
+/// A node providing a service, see [`ServiceRegistry`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceProvider {
+    pub node: String,
+    /// The XML-RPC API URI of the node, empty if the node is not registered.
+    #[serde(default)]
+    pub node_api: String,
+    /// The `rosrpc://` URI the service is called at.
+    pub service_api: String,
+    /// The service type, e.g. `std_srvs/Trigger`, if the provider answered a probe for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_type: Option<String>,
+}
+
+impl ServiceRegistry {
+    /// Parses an exported registry from JSON or YAML.
+    pub fn from_yaml(contents: &str) -> crate::Result<Self> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| crate::Error::InvalidConfig(format!("not a service registry: {e}")))
+    }
+
+    /// Returns the services of `required` that have no provider, in the order they are given.
+    ///
+    /// ```
+    /// let registry = ros_core_rs::graph::ServiceRegistry::from_yaml(
+    ///     r#"{"services": {"/arm/home": [{"node": "/arm", "service_api": "rosrpc://robot:4000"}]}}"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(registry.missing(["/arm/home", "/gripper/open"]), ["/gripper/open"]);
+    /// ```
+    pub fn missing<'a>(&self, required: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        required
+            .into_iter()
+            .filter(|service| self.services.get(*service).is_none_or(Vec::is_empty))
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+/// Renders the nodes and topics of `state` as a Graphviz DOT graph like rqt_graph draws it, with
+/// edges from publishers to topics and from topics to subscribers.
+///
+/// ```
+/// use ros_core_rs::core::SystemState;
+///
+/// let state = SystemState {
+///     publishers: vec![("/chatter".into(), vec!["/talker".into()])],
+///     subscribers: vec![("/chatter".into(), vec!["/listener".into()])],
+///     services: vec![],
+/// };
+/// let dot = ros_core_rs::graph::to_dot(&state);
+/// assert!(dot.contains("\"/talker\" -> \"topic:/chatter\";"));
+/// assert!(dot.contains("\"topic:/chatter\" -> \"/listener\";"));
+/// ```
+pub fn to_dot(state: &SystemState) -> String {
+    let mut nodes = BTreeSet::new();
+    let mut topics = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+    for (topic, publishers) in &state.publishers {
+        topics.insert(topic);
+        for node in publishers {
+            nodes.insert(node);
+            edges.insert((quote(node), quote(&format!("topic:{topic}"))));
+        }
+    }
+    for (topic, subscribers) in &state.subscribers {
+        topics.insert(topic);
+        for node in subscribers {
+            nodes.insert(node);
+            edges.insert((quote(&format!("topic:{topic}")), quote(node)));
+        }
+    }
+    let mut dot = String::from("digraph ros {\n");
+    for node in nodes {
+        dot.push_str(&format!("  {} [shape=ellipse];\n", quote(node)));
+    }
+    for topic in topics {
+        let id = quote(&format!("topic:{topic}"));
+        dot.push_str(&format!("  {id} [shape=box, label={}];\n", quote(topic)));
+    }
+    for (from, to) in edges {
+        dot.push_str(&format!("  {from} -> {to};\n"));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Quotes `id` as a DOT string.
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -7,7 +7,7 @@
 //! # Examples
 //! ```
 //! use url::Url;
-//! async fn demo() -> anyhow::Result<()>{
+//! async fn demo() -> ros_core_rs::Result<()>{
 //!   const ROS_MASTER_URI: &str = "http://0.0.0.0:11311";
 //!   let uri = Url::parse(ROS_MASTER_URI).unwrap();
 //!   let socket_address = ros_core_rs::url_to_socket_addr(&uri)?;
@@ -16,21 +16,60 @@
 //! }
 //! ```
 //!
+pub mod bag;
+#[cfg(feature = "client")]
 pub mod client_api;
 pub mod core;
+mod error;
+pub mod graph;
+#[cfg(feature = "server")]
+mod log_throttle;
+pub mod rosparam;
+pub mod rosrpc;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+pub mod tls;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use url::Url;
 
+pub use error::{Error, Result};
+
 mod param_tree;
 
-pub fn url_to_socket_addr(url: &Url) -> anyhow::Result<SocketAddr> {
+/// Extracts the socket address to listen on from a master URI such as `http://0.0.0.0:11311`.
+/// Unix socket URIs have no socket address, see [`url_to_unix_path`].
+pub fn url_to_socket_addr(url: &Url) -> Result<SocketAddr> {
+    if url_to_unix_path(url).is_some() {
+        return Err(Error::InvalidUri(url.to_string()));
+    }
     let ip_addr = match url.host() {
-        Some(url::Host::Domain(domain)) if domain == "localhost" => IpAddr::V4(Ipv4Addr::LOCALHOST),
-        Some(url::Host::Domain(domain)) => domain.parse()?,
+        Some(url::Host::Domain("localhost")) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Some(url::Host::Domain(domain)) => domain
+            .parse()
+            .map_err(|_| Error::InvalidUri(url.to_string()))?,
         Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
         Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
-        None => anyhow::bail!("Invalid URL: no host specified"),
+        None => return Err(Error::InvalidUri(url.to_string())),
     };
-    let port = url.port().expect("Invalid URL: no port specified");
+    let port = url.port().ok_or_else(|| Error::InvalidUri(url.to_string()))?;
     Ok(SocketAddr::new(ip_addr, port))
 }
+
+/// Extracts the socket path from a master URI such as `unix:///run/ros/master.sock`. Returns
+/// `None` for URIs of other schemes.
+///
+/// ```
+/// use url::Url;
+///
+/// let uri = Url::parse("unix:///run/ros/master.sock").unwrap();
+/// assert_eq!(
+///     ros_core_rs::url_to_unix_path(&uri),
+///     Some("/run/ros/master.sock".into())
+/// );
+/// ```
+pub fn url_to_unix_path(url: &Url) -> Option<PathBuf> {
+    if url.scheme() != "unix" {
+        return None;
+    }
+    url.to_file_path().ok()
+}
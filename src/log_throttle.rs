@@ -0,0 +1,116 @@
+//! Rate limiting for warnings that can recur at a high rate, e.g. every failed call to a node that
+//! died without unregistering.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::TimeSource;
+
+/// Lets through one message per key and interval and counts the ones it holds back.
+pub(crate) struct LogThrottle {
+    clock: Arc<dyn TimeSource>,
+    state: Mutex<ThrottleState>,
+}
+
+struct ThrottleState {
+    interval: Duration,
+    keys: HashMap<String, Throttled>,
+}
+
+struct Throttled {
+    logged_at: Instant,
+    suppressed: usize,
+}
+
+impl LogThrottle {
+    pub(crate) fn new(interval: Duration, clock: Arc<dyn TimeSource>) -> Self {
+        Self {
+            clock,
+            state: Mutex::new(ThrottleState {
+                interval,
+                keys: HashMap::new(),
+            }),
+        }
+    }
+
+    /// The throttling interval, e.g. how often to call [`LogThrottle::flush`].
+    pub(crate) fn interval(&self) -> Duration {
+        self.state.lock().unwrap().interval
+    }
+
+    /// Whether a message with `key` should be logged now. Messages are suppressed, and counted,
+    /// until the interval of the last logged one ended.
+    pub(crate) fn allow(&self, key: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.instant();
+        state.expire(now);
+        if let Some(throttled) = state.keys.get_mut(key) {
+            throttled.suppressed += 1;
+            return false;
+        }
+        state.keys.insert(
+            key.to_owned(),
+            Throttled {
+                logged_at: now,
+                suppressed: 0,
+            },
+        );
+        true
+    }
+
+    /// Forgets the keys whose interval ended and logs how many of their messages were suppressed.
+    /// Returns the keys with suppressed messages and their counts.
+    pub(crate) fn flush(&self) -> Vec<(String, usize)> {
+        let now = self.clock.instant();
+        self.state.lock().unwrap().expire(now)
+    }
+}
+
+impl ThrottleState {
+    fn expire(&mut self, now: Instant) -> Vec<(String, usize)> {
+        let interval = self.interval;
+        let mut flushed = Vec::new();
+        self.keys.retain(|key, throttled| {
+            if now.saturating_duration_since(throttled.logged_at) < interval {
+                return true;
+            }
+            if throttled.suppressed > 0 {
+                log::warn!(
+                    "{} similar warnings suppressed within {interval:?} ({key})",
+                    throttled.suppressed
+                );
+                flushed.push((key.clone(), throttled.suppressed));
+            }
+            false
+        });
+        flushed
+    }
+}
+
+/// Logs a warning unless one with the same key was logged within the throttling interval. The
+/// number of suppressed warnings is logged once the interval ended.
+macro_rules! warn_throttled {
+    ($throttle:expr, $key:expr, $($arg:tt)+) => {
+        if $throttle.allow(&$key) {
+            log::warn!($($arg)+)
+        }
+    };
+}
+pub(crate) use warn_throttled;
+
+#[test]
+fn test_log_throttle() {
+    let clock = Arc::new(crate::core::ManualClock::new("2024-01-01T00:00:00Z".parse().unwrap()));
+    let throttle = LogThrottle::new(Duration::from_secs(60), clock.clone());
+    assert!(throttle.allow("a"));
+    assert!(!throttle.allow("a"));
+    assert!(!throttle.allow("a"));
+    assert!(throttle.allow("b"));
+    assert_eq!(throttle.flush(), []);
+
+    // The suppressed warnings are reported once the interval ended, also if none follow.
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(throttle.flush(), [("a".to_owned(), 2)]);
+    assert!(throttle.state.lock().unwrap().keys.is_empty());
+    assert!(throttle.allow("a"));
+}
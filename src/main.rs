@@ -1,18 +1,1385 @@
+use std::io::Write;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ros_core_rs::core::CancellationToken;
+use ros_core_rs::graph::GraphSnapshot;
 use url::Url;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::init();
-    let uri = match std::env::var("ROS_MASTER_URI") {
-        Ok(v) => Url::parse(v.as_str())?,
-        Err(std::env::VarError::NotPresent) => Url::parse("http://0.0.0.0:11311").unwrap(),
-        Err(v) => anyhow::bail!(
-            "Unkown error when parsing ROS_MASTER_URI: {}",
-            v.to_string()
-        ),
-    };
-
-    let socket_address = ros_core_rs::url_to_socket_addr(&uri)?;
-    let master = ros_core_rs::core::Master::new(&socket_address);
-    master.serve().await
+/// A pure Rust implementation of the ROS master.
+///
+/// Without a command, serves the master at ROS_MASTER_URI (http://0.0.0.0:11311 if unset).
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Captures and compares the graph of the master at ROS_MASTER_URI.
+    #[command(subcommand)]
+    Graph(GraphCommand),
+    /// Registers the topics recorded in bags on the master at ROS_MASTER_URI.
+    #[command(subcommand)]
+    Bag(BagCommand),
+    /// Shows the topics of the master at ROS_MASTER_URI, like rostopic.
+    #[command(subcommand)]
+    Topic(TopicCommand),
+    /// Shows and controls the nodes of the master at ROS_MASTER_URI, like rosnode.
+    #[command(subcommand)]
+    Node(NodeCommand),
+    /// Shows and calls the services of the master at ROS_MASTER_URI, like rosservice.
+    #[command(subcommand)]
+    Service(ServiceCommand),
+    /// Manages the parameters of the master at ROS_MASTER_URI.
+    #[command(subcommand)]
+    Param(ParamCommand),
+    /// Checks that a master answers getPid and getUri, and exits with 1 if it doesn't. Meant for
+    /// container healthchecks.
+    Healthcheck {
+        /// The master to check instead of ROS_MASTER_URI.
+        #[arg(long)]
+        uri: Option<Url>,
+        /// How long the master may take to answer.
+        #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+        timeout: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum GraphCommand {
+    /// Prints the nodes, topics and services as JSON, or saves them to FILE.
+    Snapshot { file: Option<PathBuf> },
+    /// Prints the differences of two graphs, each a JSON file or `live`. Exits with 1 if they
+    /// differ.
+    Diff { a: String, b: String },
+}
+
+#[derive(Subcommand)]
+enum BagCommand {
+    /// Registers the topics of the bags as published by the virtual node NODE, so subscribers
+    /// can be wired up before playback starts.
+    Seed(SeedArgs),
+    /// Unregisters the virtual publishers added by `seed`.
+    Unseed(SeedArgs),
+}
+
+#[derive(Subcommand)]
+enum TopicCommand {
+    /// Prints the topics with publishers or subscribers.
+    List,
+    /// Prints the type, publishers and subscribers of a topic.
+    Info { topic: String },
+    /// Prints the type of a topic. Exits with 1 if no publisher or subscriber announced it.
+    Type { topic: String },
+}
+
+#[derive(Subcommand)]
+enum NodeCommand {
+    /// Prints the nodes with publishers, subscribers or services.
+    List,
+    /// Prints the API, registrations and process ID of a node.
+    Info { node: String },
+    /// Calls getPid on a node and prints how long it took to answer. Exits with 1 if it doesn't.
+    Ping {
+        node: String,
+        /// How many times to call the node, a second apart.
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+    },
+    /// Asks nodes to shut down.
+    Kill {
+        #[arg(required = true)]
+        nodes: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceCommand {
+    /// Prints the services.
+    List,
+    /// Prints the node, URI, type and arguments of a service.
+    Info { service: String },
+    /// Calls a service and prints the response as YAML. The request is either a YAML mapping of
+    /// its fields, e.g. `'{a: 1, b: 2}'`, or one YAML value per field in order. Service
+    /// definitions are looked up on ROS_PACKAGE_PATH.
+    Call { service: String, args: Vec<String> },
+}
+
+#[derive(Subcommand)]
+enum ParamCommand {
+    /// Prints a parameter, or a namespace of parameters, as YAML.
+    Get { key: String },
+    /// Sets a parameter to a YAML value, e.g. `42`, `[1, 2]` or `{gain: 0.5}`.
+    Set { key: String, value: String },
+    /// Deletes a parameter or a namespace of parameters.
+    Delete { key: String },
+    /// Prints the names of the parameters, optionally only those in a namespace.
+    List { namespace: Option<String> },
+    /// Sets the parameters of a rosparam YAML file, optionally in a namespace.
+    Load {
+        file: PathBuf,
+        namespace: Option<String>,
+    },
+    /// Writes the parameters, optionally only those in a namespace, as a rosparam YAML file, or
+    /// to stdout without a file.
+    Dump {
+        file: Option<PathBuf>,
+        namespace: Option<String>,
+    },
+    /// Copies the parameters of another running master, e.g. a stock roscore, except its run_id.
+    Import {
+        /// The master to copy from.
+        #[arg(long, value_name = "URI")]
+        from_master: Url,
+        /// Also registers the publishers, subscribers and services of the other master.
+        #[arg(long)]
+        registrations: bool,
+    },
+}
+
+#[derive(Args)]
+struct SeedArgs {
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// The virtual node publishing the topics.
+    #[arg(long, default_value = "/bag_seed")]
+    node: String,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// The address to listen on, instead of the host of ROS_MASTER_URI.
+    #[arg(long, env = "ROS_CORE_BIND")]
+    bind: Option<IpAddr>,
+    /// The port to listen on, instead of the port of ROS_MASTER_URI.
+    #[arg(long, env = "ROS_CORE_PORT")]
+    port: Option<u16>,
+    /// The host name or address nodes reach the master at, returned by getUri. Defaults to
+    /// ROS_HOSTNAME, ROS_IP, the bind address or the host name of the machine.
+    #[arg(long, env = "ROS_CORE_ADVERTISE_HOST")]
+    advertise_host: Option<String>,
+    /// Loads a rosparam YAML file before serving, under NAMESPACE if given.
+    #[arg(
+        long = "params-file",
+        visible_alias = "params",
+        value_name = "[NAMESPACE=]FILE",
+        env = "ROS_CORE_PARAMS_FILE",
+        value_parser = parse_params_arg
+    )]
+    params_files: Vec<(String, PathBuf)>,
+    /// Writes the parameters to FILE as rosparam YAML when the master stops and on SIGUSR1.
+    #[arg(long, value_name = "FILE", env = "ROS_CORE_DUMP_PARAMS")]
+    dump_params: Option<PathBuf>,
+    /// Encrypts sensitive parameters in dumps with the base64-encoded 32 byte key in FILE.
+    #[arg(long, value_name = "FILE", env = "ROS_CORE_PARAM_KEY_FILE")]
+    param_key_file: Option<PathBuf>,
+    /// Where to send the logs, filtered by RUST_LOG.
+    #[arg(long, value_enum, default_value_t = LogTarget::Stderr)]
+    log_target: LogTarget,
+    /// How to format the logs on stderr.
+    #[arg(long, value_enum, env = "ROS_CORE_LOG_FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// Rejects requests larger than BYTES.
+    #[arg(long, value_name = "BYTES")]
+    max_request_size: Option<usize>,
+    /// Logs recurring warnings, such as failed calls to the same node, once per SECONDS at most.
+    #[arg(long, value_name = "SECONDS")]
+    log_throttle: Option<u64>,
+    /// Drops parameter subscriptions whose updates have failed for SECONDS.
+    #[arg(long, value_name = "SECONDS")]
+    prune_param_subscribers: Option<u64>,
+    /// Calls getPid on every node each SECONDS and unregisters nodes that stop answering.
+    #[arg(long, value_name = "SECONDS")]
+    monitor_liveness: Option<u64>,
+    /// How many times in a row a node may not answer before it is unregistered.
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "monitor_liveness")]
+    liveness_failures: u32,
+    /// Only unregisters nodes that have not answered for at least SECONDS.
+    #[arg(long, value_name = "SECONDS", requires = "monitor_liveness")]
+    liveness_grace: Option<u64>,
+    /// Never unregisters the nodes in NAMESPACE because they don't answer.
+    #[arg(long, value_name = "NAMESPACE", requires = "monitor_liveness")]
+    liveness_exempt: Vec<String>,
+    /// Forgets topics that have had no publishers and subscribers for SECONDS.
+    #[arg(long, value_name = "SECONDS")]
+    forget_idle_topics: Option<u64>,
+    /// What happens when a node registers a service another node provides.
+    #[arg(long, value_enum, default_value_t = ServiceOwnershipArg::Replace)]
+    service_ownership: ServiceOwnershipArg,
+    /// Forgets topics as soon as their last publisher and subscriber unregister.
+    #[arg(long, conflicts_with = "forget_idle_topics")]
+    forget_unused_topics: bool,
+    /// Rejects parameter subscriptions beyond N.
+    #[arg(long, value_name = "N")]
+    max_param_subscriptions: Option<usize>,
+    /// Keeps the last N parameter changes for subscribeParamWithHistory.
+    #[arg(long, value_name = "N")]
+    param_history: Option<usize>,
+    /// Writes graph snapshots (JSON and DOT) and parameter dumps to DIR periodically.
+    #[arg(long, value_name = "DIR")]
+    export_dir: Option<PathBuf>,
+    /// How often to export to --export-dir.
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    export_every: u64,
+    /// How many exports to keep in --export-dir, 0 keeps all of them.
+    #[arg(long, value_name = "N", default_value_t = 1440)]
+    export_keep: usize,
+    /// Writes the providers of every service to FILE (JSON) whenever they change.
+    #[arg(long, value_name = "FILE")]
+    export_services: Option<PathBuf>,
+    /// Checks the graph against the expected topology in FILE.
+    #[arg(long, value_name = "FILE")]
+    expect_topology: Option<PathBuf>,
+    /// Recovers the running nodes of the graph snapshot FILE after a restart, or of the latest
+    /// snapshot if FILE is a directory written by --export-dir.
+    #[arg(long, value_name = "FILE")]
+    warm_start: Option<PathBuf>,
+    /// Loads access rules, blocked topics and limits from FILE, reloaded on SIGHUP.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// The number of tokio worker threads, one per CPU by default.
+    #[arg(long, value_name = "N")]
+    worker_threads: Option<NonZeroUsize>,
+    /// Preallocates the registry for this many nodes and topics.
+    #[arg(long, value_name = "NODES,TOPICS", value_parser = parse_capacity_arg)]
+    capacity: Option<(usize, usize)>,
+    /// Asks all registered nodes to shut down when the master stops.
+    #[arg(long)]
+    shutdown_nodes_on_exit: bool,
+    /// Lists the publishers of a topic by how fast they answer the master, fastest first.
+    #[arg(long)]
+    order_publishers_by_latency: bool,
+    /// Skips service providers that don't answer getPid in lookupService.
+    #[arg(long)]
+    probe_service_providers: bool,
+    /// Also listens on the counterpart of the bind address in the other IP version.
+    #[arg(long)]
+    dual_stack: bool,
+    /// Also serves the API on a Unix domain socket at PATH.
+    #[arg(long, value_name = "PATH")]
+    unix_socket: Option<PathBuf>,
+    /// Serves the API over TLS with the PEM certificate chain in FILE.
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// The PEM private key of --tls-cert.
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    /// Serves the static files in DIR under /ui.
+    #[arg(long, value_name = "DIR")]
+    ui: Option<PathBuf>,
+    /// Sends CORS headers for ORIGIN, or for all origins with *.
+    #[arg(long, value_name = "ORIGIN")]
+    cors_origin: Vec<String>,
+    /// The methods allowed for CORS requests, e.g. GET,POST.
+    #[arg(long, value_name = "METHODS")]
+    cors_methods: Option<String>,
+}
+
+/// Where the binary sends its logs.
+#[derive(Clone, Copy, ValueEnum)]
+enum LogTarget {
+    Stderr,
+    Journald,
+    Syslog,
+}
+
+/// How the binary formats its logs on stderr.
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// What happens when a node registers a service another node provides, see
+/// `MasterBuilder::service_ownership`.
+#[derive(Clone, Copy, ValueEnum)]
+enum ServiceOwnershipArg {
+    /// Both nodes provide the service.
+    AllowMultiple,
+    /// The registration fails.
+    Reject,
+    /// The new node replaces the previous provider, like rosmaster.
+    Replace,
+    /// The new node replaces the previous provider, which is shut down.
+    ReplaceAndShutdown,
+}
+
+/// Splits a `--params-file` argument into the namespace to mount the file under and its path.
+/// Files given without a namespace are loaded into the root namespace.
+fn parse_params_arg(spec: &str) -> Result<(String, PathBuf), String> {
+    Ok(match spec.split_once('=') {
+        Some((namespace, path)) => (namespace.to_owned(), PathBuf::from(path)),
+        None => ("/".to_owned(), PathBuf::from(spec)),
+    })
+}
+
+/// Installs the logger for `target`. All targets are filtered by `RUST_LOG`, the system logs
+/// have their own format.
+#[cfg_attr(not(feature = "journald"), allow(unused_variables))]
+fn init_logging(target: LogTarget, format: LogFormat, uri: &Url) -> anyhow::Result<()> {
+    match target {
+        LogTarget::Stderr => {
+            let mut builder = env_logger::Builder::from_default_env();
+            if let LogFormat::Json = format {
+                builder.format(|buf, record| {
+                    let line = serde_json::json!({
+                        "time": chrono::Utc::now().to_rfc3339(),
+                        "level": record.level().as_str(),
+                        "target": record.target(),
+                        "message": record.args().to_string(),
+                    });
+                    writeln!(buf, "{line}")
+                });
+            }
+            builder.init();
+        }
+        #[cfg(feature = "journald")]
+        LogTarget::Journald => {
+            // The journal gets the source location and module of each message as fields already.
+            let journal = systemd_journal_logger::JournalLog::new()
+                .map_err(|e| anyhow::anyhow!("Failed to connect to journald: {e}"))?
+                .with_syslog_identifier("ros-core-rs".to_owned())
+                .with_extra_fields(vec![("ROS_MASTER_URI", uri.as_str())]);
+            install_filtered(journal)?;
+        }
+        #[cfg(feature = "syslog")]
+        LogTarget::Syslog => {
+            let formatter = syslog::Formatter3164 {
+                facility: syslog::Facility::LOG_DAEMON,
+                hostname: None,
+                process: "ros-core-rs".to_owned(),
+                pid: std::process::id(),
+            };
+            let logger = syslog::unix(formatter)
+                .map_err(|e| anyhow::anyhow!("Failed to connect to syslog: {e}"))?;
+            install_filtered(syslog::BasicLogger::new(logger))?;
+        }
+        #[cfg(not(feature = "journald"))]
+        LogTarget::Journald => anyhow::bail!("ros-core-rs was built without the journald feature"),
+        #[cfg(not(feature = "syslog"))]
+        LogTarget::Syslog => anyhow::bail!("ros-core-rs was built without the syslog feature"),
+    }
+    Ok(())
+}
+
+/// Installs `logger` behind the `RUST_LOG` filter, which only `env_logger` applies by itself.
+#[cfg(any(feature = "journald", feature = "syslog"))]
+fn install_filtered(logger: impl log::Log + 'static) -> anyhow::Result<()> {
+    let filter = env_logger::filter::Builder::from_env("RUST_LOG").build();
+    log::set_max_level(filter.filter());
+    log::set_boxed_logger(Box::new(FilteredLogger { filter, logger }))?;
+    Ok(())
+}
+
+#[cfg(any(feature = "journald", feature = "syslog"))]
+struct FilteredLogger<L> {
+    filter: env_logger::filter::Filter,
+    logger: L,
+}
+
+#[cfg(any(feature = "journald", feature = "syslog"))]
+impl<L: log::Log> log::Log for FilteredLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.filter.enabled(metadata) && self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.filter.matches(record) {
+            self.logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
+/// Reads a graph snapshot from a JSON file, or takes one from the master at `uri` for `live`.
+fn load_snapshot(
+    source: &str,
+    uri: &Url,
+    runtime: &tokio::runtime::Runtime,
+) -> anyhow::Result<GraphSnapshot> {
+    if source == "live" {
+        let client = ros_core_rs::core::MasterClient::new(uri);
+        return Ok(runtime.block_on(client.graph_snapshot(CALLER_ID))?);
+    }
+    let file =
+        std::fs::File::open(source).map_err(|e| anyhow::anyhow!("Failed to open {source}: {e}"))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("Failed to read graph snapshot {source}: {e}"))
+}
+
+/// Returns `path`, or the latest `graph-TIME.json` export in it if `path` is a directory.
+fn latest_snapshot(path: PathBuf) -> anyhow::Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path);
+    }
+    // The timestamps in the names of the exports sort like the exports were made.
+    std::fs::read_dir(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|file| {
+            file.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("graph-") && name.ends_with(".json"))
+        })
+        .max()
+        .ok_or_else(|| anyhow::anyhow!("No graph snapshot in {}", path.display()))
+}
+
+/// Runs `ros-core-rs graph ...` against the master at `uri`. Differences found by `diff` make the
+/// process exit with 1, so bring-up scripts can check the graph.
+fn graph_command(command: GraphCommand, uri: &Url) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    match command {
+        GraphCommand::Snapshot { file: None } => {
+            let snapshot = load_snapshot("live", uri, &runtime)?;
+            println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        }
+        GraphCommand::Snapshot { file: Some(path) } => {
+            let snapshot = load_snapshot("live", uri, &runtime)?;
+            std::fs::write(&path, serde_json::to_string_pretty(&snapshot)? + "\n")
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))?;
+        }
+        GraphCommand::Diff { a, b } => {
+            let diff = load_snapshot(&a, uri, &runtime)?.diff(&load_snapshot(&b, uri, &runtime)?);
+            print!("{diff}");
+            if !diff.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The caller ID of the subcommands that call a master.
+const CALLER_ID: &str = "/ros_core_rs";
+
+/// Runs `ros-core-rs topic ...` against the master at `uri`.
+fn topic_command(command: TopicCommand, uri: &Url) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = ros_core_rs::core::MasterClient::new(uri);
+    runtime.block_on(async {
+        let (code, message, (publishers, subscribers, _)) =
+            client.get_system_state(CALLER_ID).await?;
+        check_code("getSystemState", code, &message)?;
+        let (code, message, types) = client.get_topic_types(CALLER_ID).await?;
+        check_code("getTopicTypes", code, &message)?;
+        let topic_type = |topic: &str| {
+            types
+                .iter()
+                .find(|(name, _)| name == topic)
+                .map(|(_, topic_type)| topic_type.clone())
+        };
+        match command {
+            TopicCommand::List => {
+                let topics: std::collections::BTreeSet<_> = publishers
+                    .iter()
+                    .chain(&subscribers)
+                    .map(|(topic, _)| topic)
+                    .collect();
+                for topic in topics {
+                    println!("{topic}");
+                }
+            }
+            TopicCommand::Info { topic } => {
+                let topic = global(&topic);
+                let nodes = |registrations: &[(String, Vec<String>)]| {
+                    registrations
+                        .iter()
+                        .find(|(name, _)| *name == topic)
+                        .map(|(_, nodes)| nodes.clone())
+                        .unwrap_or_default()
+                };
+                let (publishers, subscribers) = (nodes(&publishers), nodes(&subscribers));
+                if publishers.is_empty() && subscribers.is_empty() {
+                    eprintln!("Unknown topic {topic}");
+                    std::process::exit(1);
+                }
+                println!(
+                    "Type: {}",
+                    topic_type(&topic).as_deref().unwrap_or("unknown")
+                );
+                for (title, nodes) in [("Publishers", publishers), ("Subscribers", subscribers)] {
+                    println!("\n{title}:");
+                    if nodes.is_empty() {
+                        println!(" None");
+                    }
+                    for node in nodes {
+                        match client.lookup_node(CALLER_ID, &node).await? {
+                            (1, _, api) => println!(" * {node} ({api})"),
+                            _ => println!(" * {node}"),
+                        }
+                    }
+                }
+            }
+            TopicCommand::Type { topic } => {
+                let topic = global(&topic);
+                match topic_type(&topic) {
+                    Some(topic_type) => println!("{topic_type}"),
+                    None => {
+                        eprintln!("No publisher or subscriber announced the type of {topic}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// How long nodes may take to answer the calls of `ros-core-rs node`.
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs `ros-core-rs node ...` against the master at `uri`.
+fn node_command(command: NodeCommand, uri: &Url) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = ros_core_rs::core::MasterClient::new(uri);
+    let lookup = |node: String| {
+        let client = &client;
+        async move {
+            let node = global(&node);
+            let (code, message, api) = client.lookup_node(CALLER_ID, &node).await?;
+            check_code("lookupNode", code, &message)?;
+            let api = ros_core_rs::client_api::ClientApi::with_timeout(&api, NODE_TIMEOUT);
+            anyhow::Ok((node, api))
+        }
+    };
+    runtime.block_on(async {
+        match command {
+            NodeCommand::List => {
+                let (code, message, (publishers, subscribers, services)) =
+                    client.get_system_state(CALLER_ID).await?;
+                check_code("getSystemState", code, &message)?;
+                let nodes: std::collections::BTreeSet<_> = publishers
+                    .iter()
+                    .chain(&subscribers)
+                    .chain(&services)
+                    .flat_map(|(_, nodes)| nodes)
+                    .collect();
+                for node in nodes {
+                    println!("{node}");
+                }
+            }
+            NodeCommand::Info { node } => {
+                let node = global(&node);
+                let (code, message, (publishers, subscribers, services)) =
+                    client.get_system_state(CALLER_ID).await?;
+                check_code("getSystemState", code, &message)?;
+                let (code, message, types) = client.get_topic_types(CALLER_ID).await?;
+                check_code("getTopicTypes", code, &message)?;
+                println!("Node [{node}]");
+                let registered = |registrations: &[(String, Vec<String>)]| {
+                    registrations
+                        .iter()
+                        .filter(|(_, nodes)| nodes.contains(&node))
+                        .map(|(name, _)| name.clone())
+                        .collect::<Vec<_>>()
+                };
+                for (title, names) in [
+                    ("Publications", registered(&publishers)),
+                    ("Subscriptions", registered(&subscribers)),
+                    ("Services", registered(&services)),
+                ] {
+                    println!("{title}:");
+                    if names.is_empty() {
+                        println!(" None");
+                    }
+                    for name in names {
+                        match types.iter().find(|(topic, _)| *topic == name) {
+                            Some((_, topic_type)) if title != "Services" => {
+                                println!(" * {name} [{topic_type}]")
+                            }
+                            _ => println!(" * {name}"),
+                        }
+                    }
+                    println!();
+                }
+                let (_, api) = lookup(node).await?;
+                println!("URI: {}", api.uri());
+                println!("Pid: {}", node_pid(&api).await?);
+            }
+            NodeCommand::Ping { node, count } => {
+                let (node, api) = lookup(node).await?;
+                for i in 0..count {
+                    if i > 0 {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                    let start = std::time::Instant::now();
+                    if let Err(e) = node_pid(&api).await {
+                        eprintln!("{node} is unreachable: {e}");
+                        std::process::exit(1);
+                    }
+                    let time = start.elapsed().as_secs_f64() * 1000.0;
+                    println!("reply from {} time={time:.3}ms", api.uri());
+                }
+            }
+            NodeCommand::Kill { nodes } => {
+                for node in nodes {
+                    let (node, api) = lookup(node).await?;
+                    api.shutdown(CALLER_ID, &format!("user request from {CALLER_ID}"))
+                        .await?;
+                    println!("killed {node}");
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Asks a node for its process ID.
+async fn node_pid(api: &ros_core_rs::client_api::ClientApi) -> anyhow::Result<i32> {
+    use dxr::TryFromValue;
+
+    let response = api.get_pid(CALLER_ID).await?;
+    let (code, message, pid) = <(i32, String, i32)>::try_from_value(&response)?;
+    check_code("getPid", code, &message)?;
+    Ok(pid)
+}
+
+/// Runs `ros-core-rs service ...` against the master at `uri`.
+fn service_command(command: ServiceCommand, uri: &Url) -> anyhow::Result<()> {
+    use ros_core_rs::rosrpc::{MessageDefinitions, ServiceClient};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = ros_core_rs::core::MasterClient::new(uri);
+    let lookup = |service: &str| {
+        let service = global(service);
+        let (code, message, api) = runtime.block_on(client.lookup_service(CALLER_ID, &service))?;
+        check_code("lookupService", code, &message)?;
+        anyhow::Ok(ServiceClient::with_timeout(&api, &service, NODE_TIMEOUT)?)
+    };
+    let definitions = MessageDefinitions::from_env();
+    match command {
+        ServiceCommand::List => {
+            let (code, message, (_, _, services)) =
+                runtime.block_on(client.get_system_state(CALLER_ID))?;
+            check_code("getSystemState", code, &message)?;
+            let mut services: Vec<_> = services.into_iter().map(|(name, _)| name).collect();
+            services.sort();
+            for service in services {
+                println!("{service}");
+            }
+        }
+        ServiceCommand::Info { service } => {
+            let service_client = lookup(&service)?;
+            let (code, message, (_, _, services)) =
+                runtime.block_on(client.get_system_state(CALLER_ID))?;
+            check_code("getSystemState", code, &message)?;
+            let service = global(&service);
+            if let Some((_, nodes)) = services.iter().find(|(name, _)| *name == service) {
+                println!("Node: {}", nodes.join(", "));
+            }
+            println!("URI: {}", service_client.uri());
+            let header = service_client.probe(CALLER_ID)?;
+            let service_type = header.get("type").map_or("unknown", String::as_str);
+            println!("Type: {service_type}");
+            if let Ok(definition) = definitions.service(service_type) {
+                let args: Vec<_> = definition
+                    .request
+                    .fields
+                    .iter()
+                    .map(|f| f.name.as_str())
+                    .collect();
+                println!("Args: {}", args.join(" "));
+            }
+        }
+        ServiceCommand::Call { service, args } => {
+            let service_client = lookup(&service)?;
+            let header = service_client.probe(CALLER_ID)?;
+            let args = args
+                .iter()
+                .map(|arg| serde_yaml::from_str(arg))
+                .collect::<Result<Vec<serde_yaml::Value>, _>>()?;
+            let request = match args.as_slice() {
+                [] => serde_yaml::Value::Null,
+                [request @ serde_yaml::Value::Mapping(_)] => request.clone(),
+                args => {
+                    // One value per field, like `rosservice call /add_two_ints 1 2`.
+                    let service_type = header.get("type").map_or("", String::as_str);
+                    let fields = definitions.service(service_type)?.request.fields;
+                    if args.len() != fields.len() {
+                        anyhow::bail!(
+                            "{service_type} takes {} arguments, but {} were given",
+                            fields.len(),
+                            args.len()
+                        );
+                    }
+                    let request = fields
+                        .into_iter()
+                        .map(|f| f.name.into())
+                        .zip(args.iter().cloned());
+                    serde_yaml::Value::Mapping(request.collect())
+                }
+            };
+            let response =
+                service_client.call_probed(CALLER_ID, &definitions, &header, &request)?;
+            print!("{}", serde_yaml::to_string(&response)?);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `ros-core-rs param ...` against the master at `uri`.
+fn param_command(command: ParamCommand, uri: &Url) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let target = ros_core_rs::core::MasterClient::new(uri);
+    match command {
+        ParamCommand::Get { key } => runtime.block_on(async {
+            let (code, message, value) = target.get_param(CALLER_ID, &global(&key)).await?;
+            check_code("getParam", code, &message)?;
+            print!("{}", ros_core_rs::rosparam::to_yaml(&value)?);
+            Ok(())
+        }),
+        ParamCommand::Set { key, value } => {
+            let value = ros_core_rs::rosparam::parse_yaml(&value)?;
+            runtime.block_on(async {
+                let (code, message, _) = target.set_param(CALLER_ID, &global(&key), &value).await?;
+                check_code("setParam", code, &message)
+            })
+        }
+        ParamCommand::Delete { key } => runtime.block_on(async {
+            let (code, message, _) = target.delete_param(CALLER_ID, &global(&key)).await?;
+            check_code("deleteParam", code, &message)
+        }),
+        ParamCommand::List { namespace } => runtime.block_on(async {
+            let (code, message, mut names) = target.get_param_names(CALLER_ID).await?;
+            check_code("getParamNames", code, &message)?;
+            let namespace = global(namespace.as_deref().unwrap_or("/"));
+            let prefix = format!("{}/", namespace.trim_end_matches('/'));
+            names.retain(|name| *name == namespace || name.starts_with(&prefix));
+            names.sort();
+            for name in names {
+                println!("{name}");
+            }
+            Ok(())
+        }),
+        ParamCommand::Load { file, namespace } => {
+            let contents = std::fs::read_to_string(&file)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", file.display()))?;
+            let value = ros_core_rs::rosparam::parse_yaml(&contents)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", file.display()))?;
+            let namespace = global(namespace.as_deref().unwrap_or("/"));
+            runtime.block_on(load_params(&target, &namespace, value))
+        }
+        ParamCommand::Dump { file, namespace } => {
+            let namespace = global(namespace.as_deref().unwrap_or("/"));
+            let yaml = runtime.block_on(async {
+                let (code, message, value) = target.get_param(CALLER_ID, &namespace).await?;
+                check_code("getParam", code, &message)?;
+                anyhow::Ok(ros_core_rs::rosparam::to_yaml(&value)?)
+            })?;
+            match file {
+                Some(file) => std::fs::write(&file, yaml)
+                    .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", file.display())),
+                None => {
+                    print!("{yaml}");
+                    Ok(())
+                }
+            }
+        }
+        ParamCommand::Import {
+            from_master,
+            registrations,
+        } => {
+            let source = ros_core_rs::core::MasterClient::new(&from_master);
+            runtime.block_on(async {
+                import_params(&source, &target).await?;
+                if registrations {
+                    import_registrations(&source, &target).await?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+/// Makes a name given on the command line global, as names are resolved relative to the caller
+/// and this tool has no namespace of its own.
+fn global(name: &str) -> String {
+    format!("/{}", name.trim_start_matches('/'))
+}
+
+/// Sets `value` as the parameters in `namespace` like `rosparam load`, i.e. the members of a
+/// dictionary are set one by one and leave the other parameters in the namespace alone.
+async fn load_params(
+    target: &ros_core_rs::core::MasterClient,
+    namespace: &str,
+    value: dxr::Value,
+) -> anyhow::Result<()> {
+    use dxr::TryFromValue;
+
+    let Ok(members) = std::collections::HashMap::<String, dxr::Value>::try_from_value(&value)
+    else {
+        if namespace == "/" {
+            anyhow::bail!("only a dictionary can be loaded into the root namespace");
+        }
+        let (code, message, _) = target.set_param(CALLER_ID, namespace, &value).await?;
+        return check_code("setParam", code, &message);
+    };
+    let mut members: Vec<_> = members.into_iter().collect();
+    members.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, value) in members {
+        let key = format!("{}/{name}", namespace.trim_end_matches('/'));
+        let (code, message, _) = target.set_param(CALLER_ID, &key, &value).await?;
+        check_code("setParam", code, &message)?;
+    }
+    Ok(())
+}
+
+/// Fails with the status message of a master if `code` isn't success.
+fn check_code(call: &str, code: i32, message: &str) -> anyhow::Result<()> {
+    if code != 1 {
+        anyhow::bail!("{call} failed: {message}");
+    }
+    Ok(())
+}
+
+/// Copies the parameters from `source` to `target`, namespace by namespace, except the run_id
+/// that identifies the run of `target`.
+async fn import_params(
+    source: &ros_core_rs::core::MasterClient,
+    target: &ros_core_rs::core::MasterClient,
+) -> anyhow::Result<()> {
+    use dxr::TryFromValue;
+
+    let (code, message, tree) = source.get_param(CALLER_ID, "/").await?;
+    check_code("getParam", code, &message)?;
+    let mut tree: Vec<_> = std::collections::HashMap::<String, dxr::Value>::try_from_value(&tree)?
+        .into_iter()
+        .filter(|(name, _)| name != "run_id")
+        .collect();
+    tree.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, value) in tree {
+        let key = format!("/{name}");
+        let (code, message, _) = target.set_param(CALLER_ID, &key, &value).await?;
+        check_code("setParam", code, &message)?;
+        println!("{key}");
+    }
+    Ok(())
+}
+
+/// Registers the publishers, subscribers and services of `source` with `target`, under the
+/// names and APIs of their nodes.
+async fn import_registrations(
+    source: &ros_core_rs::core::MasterClient,
+    target: &ros_core_rs::core::MasterClient,
+) -> anyhow::Result<()> {
+    let (code, message, (publishers, subscribers, services)) =
+        source.get_system_state(CALLER_ID).await?;
+    check_code("getSystemState", code, &message)?;
+    let (code, message, types) = source.get_topic_types(CALLER_ID).await?;
+    check_code("getTopicTypes", code, &message)?;
+    let types: std::collections::HashMap<_, _> = types.into_iter().collect();
+    let mut apis = std::collections::HashMap::new();
+    for node in publishers
+        .iter()
+        .chain(&subscribers)
+        .chain(&services)
+        .flat_map(|(_, nodes)| nodes)
+    {
+        if apis.contains_key(node) {
+            continue;
+        }
+        match source.lookup_node(CALLER_ID, node).await? {
+            (1, _, api) => {
+                apis.insert(node.clone(), api);
+            }
+            (_, message, _) => eprintln!("Skipping {node}: {message}"),
+        }
+    }
+
+    for (topic, nodes) in &publishers {
+        let topic_type = types.get(topic).map_or("*", String::as_str);
+        for (node, api) in nodes.iter().filter_map(|n| Some((n, apis.get(n)?))) {
+            let (code, message, _) = target
+                .register_publisher(node, topic, topic_type, api)
+                .await?;
+            check_code("registerPublisher", code, &message)?;
+            println!("{topic} published by {node}");
+        }
+    }
+    for (topic, nodes) in &subscribers {
+        let topic_type = types.get(topic).map_or("*", String::as_str);
+        for (node, api) in nodes.iter().filter_map(|n| Some((n, apis.get(n)?))) {
+            let (code, message, _) = target
+                .register_subscriber(node, topic, topic_type, api)
+                .await?;
+            check_code("registerSubscriber", code, &message)?;
+            println!("{topic} subscribed by {node}");
+        }
+    }
+    for (service, nodes) in &services {
+        let (code, message, service_api) = source.lookup_service(CALLER_ID, service).await?;
+        if code != 1 {
+            eprintln!("Skipping {service}: {message}");
+            continue;
+        }
+        for (node, api) in nodes.iter().filter_map(|n| Some((n, apis.get(n)?))) {
+            let (code, message, _) = target
+                .register_service(node, service, &service_api, api)
+                .await?;
+            check_code("registerService", code, &message)?;
+            println!("{service} provided by {node}");
+        }
+    }
+    Ok(())
+}
+
+/// Runs `ros-core-rs healthcheck` against the master at `uri`, exiting with 1 if it is unhealthy.
+fn healthcheck(uri: &Url, timeout: Duration) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = ros_core_rs::core::MasterClient::new(uri);
+    let check = async {
+        let (code, message, pid) = client.get_pid("/ros_core_rs_healthcheck").await?;
+        if code != 1 {
+            anyhow::bail!("getPid failed: {message}");
+        }
+        let (code, message, _) = client.get_uri("/ros_core_rs_healthcheck").await?;
+        if code != 1 {
+            anyhow::bail!("getUri failed: {message}");
+        }
+        Ok(pid)
+    };
+    match runtime.block_on(async { tokio::time::timeout(timeout, check).await }) {
+        Ok(Ok(pid)) => {
+            println!("{uri} is healthy (pid {pid})");
+            Ok(())
+        }
+        Ok(Err(e)) => {
+            eprintln!("{uri} is unhealthy: {e}");
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("{uri} is unhealthy: no answer within {timeout:?}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The API of virtual publishers. Nothing listens there, so subscribers that try to connect
+/// before playback starts fail right away.
+const SEED_API: &str = "http://localhost:0/";
+
+/// Runs `ros-core-rs bag ...` against the master at `uri`.
+fn bag_command(command: BagCommand, uri: &Url) -> anyhow::Result<()> {
+    let (seed, args) = match command {
+        BagCommand::Seed(args) => (true, args),
+        BagCommand::Unseed(args) => (false, args),
+    };
+    let mut topics = std::collections::BTreeMap::new();
+    for path in &args.files {
+        for connection in ros_core_rs::bag::read_connections(path)? {
+            topics.insert(connection.topic, connection.topic_type);
+        }
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let client = ros_core_rs::core::MasterClient::new(uri);
+    runtime.block_on(async {
+        for (topic, topic_type) in &topics {
+            let (code, message) = if seed {
+                let (code, message, _) = client
+                    .register_publisher(&args.node, topic, topic_type, SEED_API)
+                    .await?;
+                (code, message)
+            } else {
+                let (code, message, _) = client
+                    .unregister_publisher(&args.node, topic, SEED_API)
+                    .await?;
+                (code, message)
+            };
+            if code != 1 {
+                anyhow::bail!("Failed to update the publishers of {topic}: {message}");
+            }
+            println!("{topic} [{topic_type}]");
+        }
+        Ok(())
+    })
+}
+
+/// The URI nodes reach the master at if it is published as `host`.
+fn advertised_uri(scheme: &str, host: &str, port: u16) -> anyhow::Result<Url> {
+    let host = match host.parse::<std::net::Ipv6Addr>() {
+        Ok(ip) => format!("[{ip}]"),
+        Err(_) => host.to_owned(),
+    };
+    Url::parse(&format!("{scheme}://{host}:{port}/"))
+        .map_err(|e| anyhow::anyhow!("Invalid advertised host '{host}': {e}"))
+}
+
+fn master_uri() -> anyhow::Result<Url> {
+    match std::env::var("ROS_MASTER_URI") {
+        Ok(v) => Ok(Url::parse(v.as_str())?),
+        Err(std::env::VarError::NotPresent) => Ok(Url::parse("http://0.0.0.0:11311").unwrap()),
+        Err(v) => anyhow::bail!("Unkown error when parsing ROS_MASTER_URI: {}", v),
+    }
+}
+
+/// Serves `master` until SIGTERM or SIGINT (Ctrl-C) stop it gracefully, a second one exits
+/// right away. If it has a configuration file, the file is reloaded on SIGHUP. If `dump_params`
+/// is given, the parameters are written to it on SIGUSR1.
+async fn serve(
+    master: &ros_core_rs::core::Master,
+    reload_on_hangup: bool,
+    dump_params: Option<&Path>,
+) -> ros_core_rs::Result<()> {
+    let shutdown = CancellationToken::new();
+    let stop = |signal: &str| {
+        if shutdown.is_cancelled() {
+            log::warn!("Received {signal} again, exiting without waiting for the shutdown");
+            log::logger().flush();
+            std::process::exit(1);
+        }
+        log::info!("Received {signal}, shutting down");
+        #[cfg(unix)]
+        notify_systemd(sd_notify::NotifyState::Stopping);
+        shutdown.cancel();
+    };
+
+    #[cfg(unix)]
+    let signals = async {
+        use tokio::signal::unix::{signal, Signal, SignalKind};
+
+        fn listen(enabled: bool, kind: SignalKind, name: &str) -> Option<Signal> {
+            if !enabled {
+                return None;
+            }
+            signal(kind)
+                .inspect_err(|e| log::warn!("Cannot handle {name}: {e}"))
+                .ok()
+        }
+
+        async fn recv(signal: &mut Option<Signal>) -> Option<()> {
+            signal.as_mut()?.recv().await
+        }
+
+        let mut terminate = listen(true, SignalKind::terminate(), "SIGTERM");
+        let mut interrupt = listen(true, SignalKind::interrupt(), "SIGINT");
+        let mut hangup = listen(reload_on_hangup, SignalKind::hangup(), "SIGHUP");
+        let mut user1 = listen(
+            dump_params.is_some(),
+            SignalKind::user_defined1(),
+            "SIGUSR1",
+        );
+        loop {
+            tokio::select! {
+                Some(()) = recv(&mut terminate) => stop("SIGTERM"),
+                Some(()) = recv(&mut interrupt) => stop("SIGINT"),
+                Some(()) = recv(&mut hangup) => {
+                    if let Err(e) = master.reload_config() {
+                        log::error!("Failed to reload the configuration: {e}");
+                    }
+                }
+                Some(()) = recv(&mut user1) => write_params(master, dump_params),
+                else => std::future::pending().await,
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let signals = async {
+        let _ = (reload_on_hangup, dump_params);
+        loop {
+            match tokio::signal::ctrl_c().await {
+                Ok(()) => stop("Ctrl-C"),
+                Err(e) => {
+                    log::warn!("Cannot handle Ctrl-C: {e}");
+                    std::future::pending().await
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        result = master.serve_with_shutdown(shutdown.clone()) => result,
+        never = signals => never,
+    }
+}
+
+/// Reads a parameter encryption key, 32 bytes encoded as base64, from `path`.
+#[cfg(feature = "param-encryption")]
+fn read_param_key(path: &Path) -> anyhow::Result<[u8; 32]> {
+    use base64::Engine;
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(contents.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("{} must contain 32 bytes as base64", path.display()))
+}
+
+/// Tells systemd about the state of the master if it runs as a `Type=notify` service, i.e.
+/// NOTIFY_SOCKET is set.
+#[cfg(unix)]
+fn notify_systemd(state: sd_notify::NotifyState) {
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        log::warn!("Failed to notify systemd: {e}");
+    }
+}
+
+/// Writes the parameters of `master` to `path` as rosparam YAML, if given.
+fn write_params(master: &ros_core_rs::core::Master, path: Option<&Path>) {
+    let Some(path) = path else {
+        return;
+    };
+    let written = master.dump_params().and_then(|yaml| {
+        std::fs::write(path, yaml).map_err(|source| ros_core_rs::Error::Io {
+            path: path.to_owned(),
+            source,
+        })
+    });
+    match written {
+        Ok(()) => log::info!("Dumped the parameters to {}", path.display()),
+        Err(e) => log::error!("Failed to dump the parameters: {e}"),
+    }
+}
+
+/// Parses a `--capacity` argument of the form `nodes,topics`.
+fn parse_capacity_arg(spec: &str) -> Result<(usize, usize), String> {
+    let parse = || {
+        let (nodes, topics) = spec.split_once(',')?;
+        Some((nodes.parse().ok()?, topics.parse().ok()?))
+    };
+    parse().ok_or_else(|| "expected nodes,topics".to_owned())
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let args = match cli.command {
+        Some(Command::Graph(command)) => return graph_command(command, &master_uri()?),
+        Some(Command::Bag(command)) => return bag_command(command, &master_uri()?),
+        Some(Command::Topic(command)) => return topic_command(command, &master_uri()?),
+        Some(Command::Node(command)) => return node_command(command, &master_uri()?),
+        Some(Command::Service(command)) => return service_command(command, &master_uri()?),
+        Some(Command::Param(command)) => return param_command(command, &master_uri()?),
+        Some(Command::Healthcheck { uri, timeout }) => {
+            let uri = match uri {
+                Some(uri) => uri,
+                None => master_uri()?,
+            };
+            return healthcheck(&uri, Duration::from_secs(timeout));
+        }
+        None => cli.serve,
+    };
+
+    let uri = master_uri()?;
+    init_logging(args.log_target, args.log_format, &uri)?;
+
+    let mut socket_address = ros_core_rs::url_to_socket_addr(&uri)?;
+    if let Some(ip) = args.bind {
+        socket_address.set_ip(ip);
+    }
+    if let Some(port) = args.port {
+        socket_address.set_port(port);
+    }
+    let mut builder = ros_core_rs::core::MasterBuilder::new(socket_address);
+    if let Some(host) = &args.advertise_host {
+        let scheme = if args.tls_cert.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        builder = builder.advertised_uri(advertised_uri(scheme, host, socket_address.port())?);
+    }
+    if let Some(path) = args.param_key_file {
+        #[cfg(feature = "param-encryption")]
+        {
+            builder = builder.param_encryption_key(read_param_key(&path)?);
+        }
+        #[cfg(not(feature = "param-encryption"))]
+        anyhow::bail!(
+            "Cannot use the key in {}, ros-core-rs was built without the param-encryption feature",
+            path.display()
+        );
+    }
+    if let Some(bytes) = args.max_request_size {
+        builder = builder.max_request_size(bytes);
+    }
+    if let Some(seconds) = args.log_throttle {
+        builder = builder.log_throttle(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.prune_param_subscribers {
+        builder = builder.prune_param_subscribers_after(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.monitor_liveness {
+        builder = builder.monitor_liveness(Duration::from_secs(seconds), args.liveness_failures);
+    }
+    if let Some(seconds) = args.liveness_grace {
+        builder = builder.liveness_grace_period(Duration::from_secs(seconds));
+    }
+    for namespace in &args.liveness_exempt {
+        builder = builder.exempt_from_liveness(namespace);
+    }
+    if let Some(seconds) = args.forget_idle_topics {
+        builder = builder.forget_idle_topics_after(Duration::from_secs(seconds));
+    }
+    builder = builder.service_ownership(match args.service_ownership {
+        ServiceOwnershipArg::AllowMultiple => ros_core_rs::core::ServiceOwnership::AllowMultiple,
+        ServiceOwnershipArg::Reject => ros_core_rs::core::ServiceOwnership::Reject,
+        ServiceOwnershipArg::Replace => ros_core_rs::core::ServiceOwnership::Replace,
+        ServiceOwnershipArg::ReplaceAndShutdown => ros_core_rs::core::ServiceOwnership::ReplaceAndShutdown,
+    });
+    if args.forget_unused_topics {
+        builder = builder.topic_retention(ros_core_rs::core::TopicRetention::WhileUsed);
+    }
+    if let Some(limit) = args.max_param_subscriptions {
+        builder = builder.max_param_subscriptions(limit);
+    }
+    if let Some(len) = args.param_history {
+        builder = builder.param_history(len);
+    }
+    if let Some(dir) = args.export_dir {
+        builder = builder.export_snapshots(
+            dir,
+            Duration::from_secs(args.export_every),
+            args.export_keep,
+        );
+    }
+    if let Some(path) = args.export_services {
+        builder = builder.export_services(path);
+    }
+    if let Some(path) = args.expect_topology {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        builder = builder.expected_topology(GraphSnapshot::from_yaml(&contents)?);
+    }
+    if let Some(path) = args.warm_start {
+        let path = latest_snapshot(path)?;
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        log::info!("Recovering the nodes of {}", path.display());
+        builder = builder.warm_start(GraphSnapshot::from_yaml(&contents)?);
+    }
+    let reload_on_hangup = args.config.is_some();
+    if let Some(path) = args.config {
+        builder = builder.config_file(path)?;
+    }
+    if let Some((nodes, topics)) = args.capacity {
+        builder = builder.capacity(nodes, topics);
+    }
+    if args.shutdown_nodes_on_exit {
+        builder = builder.shutdown_nodes_on_exit("ROS master shutting down");
+    }
+    if args.order_publishers_by_latency {
+        builder = builder.order_publishers_by_latency();
+    }
+    if args.probe_service_providers {
+        builder = builder.probe_service_providers();
+    }
+    if args.dual_stack {
+        builder = builder.dual_stack();
+    }
+    if let Some(path) = args.unix_socket {
+        #[cfg(feature = "unix-socket")]
+        {
+            builder = builder.unix_socket(path);
+        }
+        #[cfg(not(feature = "unix-socket"))]
+        anyhow::bail!(
+            "Cannot listen on {}, ros-core-rs was built without the unix-socket feature",
+            path.display()
+        );
+    }
+    // clap makes sure that both or neither are given.
+    if let (Some(certificate), Some(key)) = (args.tls_cert, args.tls_key) {
+        #[cfg(feature = "tls-server")]
+        {
+            builder = builder.tls(certificate, key);
+        }
+        #[cfg(not(feature = "tls-server"))]
+        anyhow::bail!(
+            "Cannot serve TLS with {} and {}, ros-core-rs was built without the tls-server feature",
+            certificate.display(),
+            key.display()
+        );
+    }
+    if let Some(dir) = args.ui {
+        #[cfg(feature = "ui")]
+        {
+            builder = builder.ui_dir(dir);
+        }
+        #[cfg(not(feature = "ui"))]
+        anyhow::bail!(
+            "Cannot serve {}, ros-core-rs was built without the ui feature",
+            dir.display()
+        );
+    }
+    if !args.cors_origin.is_empty() || args.cors_methods.is_some() {
+        #[cfg(feature = "cors")]
+        {
+            let mut cors = ros_core_rs::core::CorsConfig {
+                allowed_origins: args.cors_origin,
+                ..Default::default()
+            };
+            if let Some(methods) = args.cors_methods {
+                cors.allowed_methods = methods.split(',').map(str::to_owned).collect();
+            }
+            builder = builder.cors(cors);
+        }
+        #[cfg(not(feature = "cors"))]
+        anyhow::bail!("ros-core-rs was built without the cors feature");
+    }
+    #[cfg(unix)]
+    {
+        builder = builder.on_ready(|_| notify_systemd(sd_notify::NotifyState::Ready));
+    }
+    let master = builder.build();
+    for (namespace, path) in args.params_files {
+        master.load_params(&namespace, &path)?;
+        log::info!("Loaded parameters from {} into {namespace}", path.display());
+    }
+
+    // Tokio starts one worker per CPU by default, which is more than the master needs on
+    // machines with many cores.
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(n) = args.worker_threads {
+        runtime.worker_threads(n.get());
+    }
+    let runtime = runtime.enable_all().build()?;
+    let result = runtime.block_on(serve(
+        &master,
+        reload_on_hangup,
+        args.dump_params.as_deref(),
+    ));
+    // A master that failed to start must not replace a previous dump with its empty parameters.
+    if result.is_ok() {
+        write_params(&master, args.dump_params.as_deref());
+        log::info!("Stopped");
+    }
+    log::logger().flush();
+    match result {
+        // Two cores started for the same robot must not split the graph, the later one gives way.
+        Err(e @ ros_core_rs::Error::AlreadyRunning { .. }) => {
+            eprintln!("{e}, exiting");
+            Ok(())
+        }
+        result => Ok(result?),
+    }
 }
@@ -39,6 +39,8 @@ impl TryToValue for ParamValue {
     }
 }
 
+// The tree is only modified through the master.
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
 impl ParamValue {
     pub(crate) fn get_keys(&self) -> Vec<String> {
         match self {
@@ -63,7 +65,7 @@ impl ParamValue {
         let mut hm = self;
         for e in key.into_iter() {
             let e = e.as_ref();
-            if e == "" {
+            if e.is_empty() {
                 continue;
             }
             match hm {
@@ -80,36 +82,30 @@ impl ParamValue {
         Some(hm.try_to_value().unwrap())
     }
 
-    pub(crate) fn remove<I, T>(&mut self, key: I)
+    /// Removes the value at `key`, whose empty segments are skipped like in [`ParamValue::get`].
+    /// Returns whether there was a value. The root of the tree can't be removed.
+    pub(crate) fn remove<I, T>(&mut self, key: I) -> bool
     where
         I: IntoIterator<Item = T>,
         T: AsRef<str>,
     {
-        let mut peekable = key.into_iter().peekable();
-        match self {
-            ParamValue::HashMap(inner) => {
-                let mut hm = inner;
-                loop {
-                    let current_key = peekable.next();
-                    let next_key = peekable.peek();
-                    match (current_key, next_key) {
-                        (Some(current_key), None) => {
-                            hm.remove(current_key.as_ref());
-                            return;
-                        }
-                        (None, None) => {
-                            let _ = mem::replace(self, ParamValue::HashMap(hashmap! {}));
-                            return;
-                        }
-                        (None, Some(_)) => unreachable!(),
-                        (Some(current_key), Some(_)) => match hm.get_mut(current_key.as_ref()) {
-                            Some(ParamValue::HashMap(new_hm)) => hm = new_hm,
-                            _ => return,
-                        },
-                    }
-                }
+        let mut segments: Vec<T> = key.into_iter().filter(|e| !e.as_ref().is_empty()).collect();
+        let Some(last) = segments.pop() else {
+            return false;
+        };
+        let mut hm = self;
+        for e in segments {
+            match hm {
+                ParamValue::HashMap(inner) => match inner.get_mut(e.as_ref()) {
+                    Some(inner_value) => hm = inner_value,
+                    None => return false,
+                },
+                _ => return false,
             }
-            _ => (),
+        }
+        match hm {
+            ParamValue::HashMap(inner) => inner.remove(last.as_ref()).is_some(),
+            _ => false,
         }
     }
 
@@ -169,4 +165,12 @@ fn test_param_tree() {
     tree.update_inner(["robot_configs"].iter(), Value::i4(23));
     let res = tree.get(["robot_configs"]).unwrap();
     assert_eq!(res, Value::i4(23));
+
+    assert!(tree.remove(["arms", "arm_left", "length", ""]));
+    assert!(!tree.remove(["arms", "arm_left", "length"]));
+    assert!(!tree.remove(["robot_id", "x"]));
+    assert!(!tree.remove([""]));
+    assert!(tree.get(["arms", "arm_left"]).is_some());
+    assert!(tree.remove(["arms"]));
+    assert!(tree.get(["arms"]).is_none());
 }
@@ -0,0 +1,238 @@
+//! Conversion of rosparam-style YAML documents into XML-RPC values that can be stored on the
+//! parameter server.
+use std::collections::HashMap;
+
+use dxr::{TryFromValue, TryToValue, Value};
+use serde_yaml::Value as Yaml;
+
+use crate::{Error, Result};
+
+/// Parses a rosparam YAML document into an XML-RPC value.
+///
+/// Mappings become structs, sequences become arrays, and scalars are converted to their XML-RPC
+/// counterpart. As in `rosparam`, angles can be given with the `!degrees` and `!radians` tags or
+/// as `deg(...)` and `rad(...)`, also as arithmetic expressions of `pi` such as `3*pi/4`, and are
+/// stored in radians.
+///
+/// # Examples
+///
+/// ```
+/// let value = ros_core_rs::rosparam::parse_yaml("planner: {rate: 10, enabled: true}").unwrap();
+/// let angle = ros_core_rs::rosparam::parse_yaml("rad(pi/2)").unwrap();
+/// assert_eq!(angle, dxr::Value::double(std::f64::consts::FRAC_PI_2));
+/// ```
+pub fn parse_yaml(contents: &str) -> Result<Value> {
+    let document: Yaml = serde_yaml::from_str(contents)?;
+    yaml_to_value(&document)
+}
+
+fn yaml_to_value(yaml: &Yaml) -> Result<Value> {
+    Ok(match yaml {
+        Yaml::Null => {
+            return Err(Error::InvalidParams(
+                "null values cannot be stored on the parameter server".to_owned(),
+            ))
+        }
+        Yaml::Bool(b) => Value::boolean(*b),
+        Yaml::Number(n) => match n.as_i64() {
+            Some(i) => Value::i4(i32::try_from(i).map_err(|_| {
+                Error::InvalidParams(format!("integer {i} does not fit into an XML-RPC int"))
+            })?),
+            None => Value::double(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Yaml::String(s) => match angle(s) {
+            Some(rad) => Value::double(rad),
+            None => Value::string(s.clone()),
+        },
+        Yaml::Sequence(seq) => seq
+            .iter()
+            .map(yaml_to_value)
+            .collect::<Result<Vec<_>>>()?
+            .try_to_value()?,
+        Yaml::Mapping(map) => {
+            let mut members = HashMap::with_capacity(map.len());
+            for (k, v) in map.iter() {
+                let k = match k {
+                    Yaml::String(s) => s.clone(),
+                    Yaml::Number(n) => n.to_string(),
+                    Yaml::Bool(b) => b.to_string(),
+                    _ => {
+                        return Err(Error::InvalidParams(format!(
+                            "unsupported parameter name {k:?}"
+                        )))
+                    }
+                };
+                members.insert(k, yaml_to_value(v)?);
+            }
+            members.try_to_value()?
+        }
+        Yaml::Tagged(tagged) => {
+            let number = match &tagged.value {
+                Yaml::String(s) => eval(s),
+                value => value.as_f64(),
+            };
+            match (tagged.tag.to_string().as_str(), number) {
+                ("!degrees", Some(deg)) => Value::double(deg.to_radians()),
+                ("!radians", Some(rad)) => Value::double(rad),
+                (tag, _) => {
+                    return Err(Error::InvalidParams(format!("unsupported YAML tag {tag}")))
+                }
+            }
+        }
+    })
+}
+
+/// Serializes a parameter value, e.g. the root of the parameter server, to a rosparam YAML
+/// document that [`parse_yaml`] and `rosparam load` read back. Dictionaries are sorted by key.
+///
+/// # Examples
+///
+/// ```
+/// use ros_core_rs::rosparam::{parse_yaml, to_yaml};
+///
+/// let value = parse_yaml("planner: {rate: 10, enabled: true}").unwrap();
+/// assert_eq!(to_yaml(&value).unwrap(), "planner:\n  enabled: true\n  rate: 10\n");
+/// ```
+pub fn to_yaml(value: &Value) -> Result<String> {
+    Ok(serde_yaml::to_string(&value_to_yaml(value, "")?)?)
+}
+
+/// Converts the parameter `key` to YAML.
+fn value_to_yaml(value: &Value, key: &str) -> Result<Yaml> {
+    if let Ok(members) = HashMap::<String, Value>::try_from_value(value) {
+        let mut members: Vec<_> = members.into_iter().collect();
+        members.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut mapping = serde_yaml::Mapping::with_capacity(members.len());
+        for (k, v) in members {
+            let yaml = value_to_yaml(&v, &format!("{key}/{k}"))?;
+            mapping.insert(Yaml::String(k), yaml);
+        }
+        return Ok(Yaml::Mapping(mapping));
+    }
+    if let Ok(items) = Vec::<Value>::try_from_value(value) {
+        return items
+            .iter()
+            .map(|v| value_to_yaml(v, key))
+            .collect::<Result<_>>()
+            .map(Yaml::Sequence);
+    }
+    if let Ok(i) = i32::try_from_value(value) {
+        return Ok(Yaml::Number(i.into()));
+    }
+    if let Ok(d) = f64::try_from_value(value) {
+        return Ok(Yaml::Number(d.into()));
+    }
+    if let Ok(b) = bool::try_from_value(value) {
+        return Ok(Yaml::Bool(b));
+    }
+    if let Ok(s) = String::try_from_value(value) {
+        return Ok(Yaml::String(s));
+    }
+    Err(Error::InvalidParams(format!(
+        "parameter {} has no rosparam YAML representation",
+        if key.is_empty() { "/" } else { key }
+    )))
+}
+
+/// Converts `deg(...)` and `rad(...)` to radians. Other strings are no angles.
+fn angle(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if let Some(expr) = s.strip_prefix("deg(").and_then(|s| s.strip_suffix(')')) {
+        return eval(expr).map(f64::to_radians);
+    }
+    s.strip_prefix("rad(")
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(eval)
+}
+
+/// Evaluates an angle expression of numbers, `pi`, `+`, `-`, `*`, `/` and parentheses.
+fn eval(expr: &str) -> Option<f64> {
+    let mut parser = Expr {
+        chars: expr.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+    };
+    let value = parser.sum()?;
+    (parser.pos == parser.chars.len()).then_some(value)
+}
+
+struct Expr {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Expr {
+    fn next_if(&mut self, c: char) -> bool {
+        let found = self.chars.get(self.pos) == Some(&c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Option<f64> {
+        let mut value = self.product()?;
+        loop {
+            if self.next_if('+') {
+                value += self.product()?;
+            } else if self.next_if('-') {
+                value -= self.product()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        loop {
+            if self.next_if('*') {
+                value *= self.factor()?;
+            } else if self.next_if('/') {
+                value /= self.factor()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<f64> {
+        if self.next_if('-') {
+            return self.factor().map(|v| -v);
+        }
+        if self.next_if('+') {
+            return self.factor();
+        }
+        if self.next_if('(') {
+            let value = self.sum()?;
+            return self.next_if(')').then_some(value);
+        }
+        if self.next_if('p') {
+            return self.next_if('i').then_some(std::f64::consts::PI);
+        }
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+}
+
+#[test]
+fn test_angles() {
+    use std::f64::consts::PI;
+
+    assert_eq!(angle("deg(180)"), Some(PI));
+    assert_eq!(angle("rad(3*pi/4)"), Some(3.0 * PI / 4.0));
+    assert_eq!(angle("rad(-(pi - 1) / 2)"), Some(-(PI - 1.0) / 2.0));
+    assert_eq!(angle("rad(pi/)"), None);
+    assert_eq!(angle("radius(2)"), None);
+    assert_eq!(angle("deg(90"), None);
+}
@@ -0,0 +1,778 @@
+//! A client for ROS services, which are called over TCP (ROSRPC) rather than XML-RPC.
+//!
+//! Requests and responses are converted from and to YAML with the service definitions found on
+//! `ROS_PACKAGE_PATH`, like `rosservice call` does:
+//!
+//! ```no_run
+//! use ros_core_rs::rosrpc::{MessageDefinitions, ServiceClient};
+//!
+//! let client = ServiceClient::new("rosrpc://localhost:45321", "/add_two_ints")?;
+//! let request = serde_yaml::from_str("{a: 1, b: 2}").unwrap();
+//! let response = client.call("/my_tool", &MessageDefinitions::from_env(), &request)?;
+//! println!("{}", serde_yaml::to_string(&response).unwrap());
+//! # Ok::<(), ros_core_rs::Error>(())
+//! ```
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_yaml::{Mapping, Value as Yaml};
+use url::Url;
+
+use crate::{Error, Result};
+
+/// Default for [`ServiceClient::with_timeout`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How deep package directories are searched for below the entries of `ROS_PACKAGE_PATH`, e.g.
+/// `src/<repository>/<package>` in a catkin workspace.
+const PACKAGE_SEARCH_DEPTH: usize = 4;
+
+/// A client of the ROS service `service`, provided at a `rosrpc://` URI as returned by
+/// `lookupService`.
+#[derive(Debug, Clone)]
+pub struct ServiceClient {
+    uri: String,
+    address: String,
+    service: String,
+    timeout: Duration,
+}
+
+impl ServiceClient {
+    /// Creates a client of `service` at `service_api`, e.g. `rosrpc://robot:45321`.
+    pub fn new(service_api: &str, service: &str) -> Result<Self> {
+        Self::with_timeout(service_api, service, DEFAULT_TIMEOUT)
+    }
+
+    /// Creates a client whose calls fail if the service doesn't answer within `timeout`.
+    pub fn with_timeout(service_api: &str, service: &str, timeout: Duration) -> Result<Self> {
+        let invalid = || Error::InvalidUri(service_api.to_owned());
+        let url = Url::parse(service_api).map_err(|_| invalid())?;
+        let (Some(host), Some(port)) = (url.host_str(), url.port()) else {
+            return Err(invalid());
+        };
+        if url.scheme() != "rosrpc" {
+            return Err(invalid());
+        }
+        Ok(Self {
+            uri: service_api.to_owned(),
+            address: format!("{host}:{port}"),
+            service: service.to_owned(),
+            timeout,
+        })
+    }
+
+    /// The URI of the node providing the service.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Asks the service for its connection header without calling it, which names the service
+    /// type in `type` and its checksum in `md5sum`.
+    pub fn probe(&self, caller_id: &str) -> Result<HashMap<String, String>> {
+        let mut stream = self.connect()?;
+        self.send_header(&mut stream, caller_id, "*", true)?;
+        self.read_header(&mut stream)
+    }
+
+    /// Calls the service with the encoded `request`, and returns the encoded response. `md5sum`
+    /// is the checksum of the service type, or `*` to accept any.
+    pub fn call_raw(&self, caller_id: &str, md5sum: &str, request: &[u8]) -> Result<Vec<u8>> {
+        let mut stream = self.connect()?;
+        self.send_header(&mut stream, caller_id, md5sum, false)?;
+        self.read_header(&mut stream)?;
+        let io = |e| self.unreachable(e);
+        stream
+            .write_all(&[&(request.len() as u32).to_le_bytes(), request].concat())
+            .map_err(io)?;
+        let mut ok = [0];
+        stream.read_exact(&mut ok).map_err(io)?;
+        let response = read_block(&mut stream).map_err(io)?;
+        if ok[0] == 0 {
+            return Err(Error::ServiceFailed {
+                service: self.service.clone(),
+                message: String::from_utf8_lossy(&response).into_owned(),
+            });
+        }
+        Ok(response)
+    }
+
+    /// Calls the service with `request`, a YAML mapping of the request fields, and returns the
+    /// response as a mapping. Missing request fields are zero. The type of the service is probed
+    /// first and its definition is looked up in `definitions`.
+    pub fn call(
+        &self,
+        caller_id: &str,
+        definitions: &MessageDefinitions,
+        request: &Yaml,
+    ) -> Result<Yaml> {
+        let header = self.probe(caller_id)?;
+        self.call_probed(caller_id, definitions, &header, request)
+    }
+
+    /// Calls the service like [`ServiceClient::call`], with the connection `header` returned by
+    /// [`ServiceClient::probe`] before instead of probing again.
+    pub fn call_probed(
+        &self,
+        caller_id: &str,
+        definitions: &MessageDefinitions,
+        header: &HashMap<String, String>,
+        request: &Yaml,
+    ) -> Result<Yaml> {
+        let (Some(service_type), Some(md5sum)) = (header.get("type"), header.get("md5sum")) else {
+            return Err(Error::InvalidMessage(format!(
+                "{} did not announce the type of {}",
+                self.uri, self.service
+            )));
+        };
+        let definition = definitions.service(service_type)?;
+        let mut encoded = Vec::new();
+        encode(definitions, &definition.request, request, "", &mut encoded)?;
+        let response = self.call_raw(caller_id, md5sum, &encoded)?;
+        let mut response = response.as_slice();
+        let decoded = decode(definitions, &definition.response, &mut response, "")?;
+        if !response.is_empty() {
+            return Err(Error::InvalidMessage(format!(
+                "the response of {} is longer than {service_type} describes",
+                self.service
+            )));
+        }
+        Ok(decoded)
+    }
+
+    fn connect(&self) -> Result<TcpStream> {
+        let io = |e| self.unreachable(e);
+        // Host names may resolve to addresses the service doesn't listen on, e.g. IPv6 ones.
+        let mut last_error = None;
+        for address in self.address.to_socket_addrs().map_err(io)? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout)).map_err(io)?;
+                    stream.set_write_timeout(Some(self.timeout)).map_err(io)?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.map_or_else(|| Error::InvalidUri(self.uri.clone()), io))
+    }
+
+    fn send_header(
+        &self,
+        stream: &mut TcpStream,
+        caller_id: &str,
+        md5sum: &str,
+        probe: bool,
+    ) -> Result<()> {
+        let mut fields = vec![
+            ("callerid", caller_id),
+            ("service", self.service.as_str()),
+            ("md5sum", md5sum),
+        ];
+        if probe {
+            fields.push(("probe", "1"));
+        }
+        let mut header = Vec::new();
+        for (name, value) in fields {
+            let field = format!("{name}={value}");
+            header.extend((field.len() as u32).to_le_bytes());
+            header.extend(field.as_bytes());
+        }
+        stream
+            .write_all(&[&(header.len() as u32).to_le_bytes(), header.as_slice()].concat())
+            .map_err(|e| self.unreachable(e))
+    }
+
+    /// Reads the connection header of the service, which names the error if the service refused
+    /// the connection.
+    fn read_header(&self, stream: &mut TcpStream) -> Result<HashMap<String, String>> {
+        let block = read_block(stream).map_err(|e| self.unreachable(e))?;
+        let mut block = block.as_slice();
+        let mut fields = HashMap::new();
+        while !block.is_empty() {
+            let field = read_bytes(&mut block, "the connection header")
+                .ok()
+                .and_then(|field| String::from_utf8(field.to_vec()).ok())
+                .ok_or_else(|| {
+                    Error::InvalidMessage(format!("{} sent an invalid connection header", self.uri))
+                })?;
+            if let Some((name, value)) = field.split_once('=') {
+                fields.insert(name.to_owned(), value.to_owned());
+            }
+        }
+        match fields.remove("error") {
+            Some(message) => Err(Error::ServiceFailed {
+                service: self.service.clone(),
+                message,
+            }),
+            None => Ok(fields),
+        }
+    }
+
+    fn unreachable(&self, source: std::io::Error) -> Error {
+        Error::UnreachableNode {
+            uri: self.uri.clone(),
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Reads a length prefixed block. It grows as it is received, so that a bogus length sent by the
+/// peer doesn't allocate up to 4 GiB up front.
+fn read_block(stream: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let mut block = Vec::new();
+    stream
+        .take(u32::from_le_bytes(len).into())
+        .read_to_end(&mut block)?;
+    if block.len() != u32::from_le_bytes(len) as usize {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(block)
+}
+
+/// The type of a field of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+    String,
+    Time,
+    Duration,
+    /// A nested message, by its full type name such as `geometry_msgs/Point`.
+    Message(String),
+}
+
+/// A field of a message, with `array` set for arrays, to their length if it is fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub field_type: FieldType,
+    pub array: Option<Option<usize>>,
+}
+
+/// The fields of a message type, as parsed from its `.msg` file. Constants are left out, as they
+/// aren't sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageDefinition {
+    pub fields: Vec<Field>,
+}
+
+impl MessageDefinition {
+    /// Parses the `.msg` file `source` of a message in `package`, which names messages of the
+    /// same package without their package.
+    pub fn parse(package: &str, source: &str) -> Result<Self> {
+        let mut fields = Vec::new();
+        for line in source.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.contains('=') {
+                continue;
+            }
+            let invalid = || Error::InvalidMessage(format!("invalid field definition {line:?}"));
+            let (field_type, name) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let (field_type, array) = match field_type.split_once('[') {
+                Some((field_type, len)) => {
+                    let len = len.strip_suffix(']').ok_or_else(invalid)?;
+                    let len = match len {
+                        "" => None,
+                        len => Some(len.parse().map_err(|_| invalid())?),
+                    };
+                    (field_type, Some(len))
+                }
+                None => (field_type, None),
+            };
+            fields.push(Field {
+                name: name.trim().to_owned(),
+                field_type: parse_type(package, field_type),
+                array,
+            });
+        }
+        Ok(Self { fields })
+    }
+}
+
+fn parse_type(package: &str, name: &str) -> FieldType {
+    match name {
+        "bool" => FieldType::Bool,
+        "int8" | "byte" => FieldType::Int8,
+        "uint8" | "char" => FieldType::UInt8,
+        "int16" => FieldType::Int16,
+        "uint16" => FieldType::UInt16,
+        "int32" => FieldType::Int32,
+        "uint32" => FieldType::UInt32,
+        "int64" => FieldType::Int64,
+        "uint64" => FieldType::UInt64,
+        "float32" => FieldType::Float32,
+        "float64" => FieldType::Float64,
+        "string" => FieldType::String,
+        "time" => FieldType::Time,
+        "duration" => FieldType::Duration,
+        "Header" => FieldType::Message("std_msgs/Header".to_owned()),
+        name if name.contains('/') => FieldType::Message(name.to_owned()),
+        name => FieldType::Message(format!("{package}/{name}")),
+    }
+}
+
+/// The request and response of a service type, as parsed from its `.srv` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceDefinition {
+    pub request: MessageDefinition,
+    pub response: MessageDefinition,
+}
+
+impl ServiceDefinition {
+    /// Parses the `.srv` file `source` of a service in `package`.
+    pub fn parse(package: &str, source: &str) -> Result<Self> {
+        let mut request = String::new();
+        let mut lines = source.lines();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+            request.push_str(line);
+            request.push('\n');
+        }
+        let response = lines.collect::<Vec<_>>().join("\n");
+        Ok(Self {
+            request: MessageDefinition::parse(package, &request)?,
+            response: MessageDefinition::parse(package, &response)?,
+        })
+    }
+}
+
+/// Finds message and service definitions in the packages on a search path. Message definitions
+/// are parsed once and cached, since nested messages are looked up for every field.
+#[derive(Debug, Clone, Default)]
+pub struct MessageDefinitions {
+    search_path: Vec<PathBuf>,
+    messages: Arc<Mutex<HashMap<String, Arc<MessageDefinition>>>>,
+}
+
+impl MessageDefinitions {
+    /// Searches the packages below `search_path`, e.g. `/opt/ros/noetic/share` or the `src`
+    /// directory of a catkin workspace.
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self {
+            search_path,
+            messages: Default::default(),
+        }
+    }
+
+    /// Searches the packages on `ROS_PACKAGE_PATH` and `ROSRUST_MSG_PATH`.
+    pub fn from_env() -> Self {
+        let search_path = ["ROS_PACKAGE_PATH", "ROSRUST_MSG_PATH"]
+            .into_iter()
+            .filter_map(std::env::var_os)
+            .flat_map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+            .collect();
+        Self::new(search_path)
+    }
+
+    /// Returns the definition of the message type `name`, e.g. `geometry_msgs/Point`.
+    pub fn message(&self, name: &str) -> Result<MessageDefinition> {
+        Ok(self.cached_message(name)?.as_ref().clone())
+    }
+
+    fn cached_message(&self, name: &str) -> Result<Arc<MessageDefinition>> {
+        if let Some(definition) = self.messages.lock().unwrap().get(name) {
+            return Ok(definition.clone());
+        }
+        let (package, source) = self.read(name, "msg")?;
+        let definition = Arc::new(MessageDefinition::parse(package, &source)?);
+        self.messages
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), definition.clone());
+        Ok(definition)
+    }
+
+    /// Returns the definition of the service type `name`, e.g. `std_srvs/SetBool`.
+    pub fn service(&self, name: &str) -> Result<ServiceDefinition> {
+        let (package, source) = self.read(name, "srv")?;
+        ServiceDefinition::parse(package, &source)
+    }
+
+    fn read<'a>(&self, name: &'a str, kind: &str) -> Result<(&'a str, String)> {
+        let Some((package, file)) = name.split_once('/') else {
+            return Err(Error::InvalidMessage(format!("invalid type name {name:?}")));
+        };
+        let file = Path::new(kind).join(format!("{file}.{kind}"));
+        for root in &self.search_path {
+            if let Some(path) = find_package(root, package, &file, PACKAGE_SEARCH_DEPTH) {
+                let source =
+                    std::fs::read_to_string(&path).map_err(|source| Error::Io { path, source })?;
+                return Ok((package, source));
+            }
+        }
+        Err(Error::InvalidMessage(format!(
+            "no definition of {name} found on ROS_PACKAGE_PATH"
+        )))
+    }
+}
+
+/// Finds `file` in the directory of `package` below `dir`.
+fn find_package(dir: &Path, package: &str, file: &Path, depth: usize) -> Option<PathBuf> {
+    let path = dir.join(package).join(file);
+    if path.is_file() {
+        return Some(path);
+    }
+    if depth == 0 {
+        return None;
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    entries
+        .iter()
+        .find_map(|entry| find_package(entry, package, file, depth - 1))
+}
+
+/// Appends the message `value` of the type `definition` to `out`. `path` names the message in
+/// errors.
+fn encode(
+    definitions: &MessageDefinitions,
+    definition: &MessageDefinition,
+    value: &Yaml,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let empty = Mapping::new();
+    let members = match value {
+        Yaml::Mapping(members) => members,
+        Yaml::Null => &empty,
+        _ => return Err(mismatch(path, "a mapping")),
+    };
+    if let Some(unknown) = members.keys().find(|key| {
+        !definition
+            .fields
+            .iter()
+            .any(|field| key.as_str() == Some(&field.name))
+    }) {
+        return Err(Error::InvalidMessage(format!(
+            "{} has no field {}",
+            if path.is_empty() { "the request" } else { path },
+            serde_yaml::to_string(unknown).unwrap_or_default().trim()
+        )));
+    }
+    for field in &definition.fields {
+        let path = format!(
+            "{path}{}{}",
+            if path.is_empty() { "" } else { "." },
+            field.name
+        );
+        let value = members.get(field.name.as_str()).unwrap_or(&Yaml::Null);
+        let Some(len) = field.array else {
+            encode_field(definitions, &field.field_type, value, &path, out)?;
+            continue;
+        };
+        let items = match value {
+            Yaml::Sequence(items) => items.as_slice(),
+            Yaml::Null => &[],
+            _ => return Err(mismatch(&path, "a list")),
+        };
+        match len {
+            Some(len) if items.is_empty() => {
+                for _ in 0..len {
+                    encode_field(definitions, &field.field_type, &Yaml::Null, &path, out)?;
+                }
+                continue;
+            }
+            Some(len) if items.len() != len => {
+                return Err(mismatch(&path, &format!("a list of {len} items")))
+            }
+            Some(_) => {}
+            None => out.extend((items.len() as u32).to_le_bytes()),
+        }
+        for (i, item) in items.iter().enumerate() {
+            encode_field(
+                definitions,
+                &field.field_type,
+                item,
+                &format!("{path}[{i}]"),
+                out,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends the field `value` of `field_type` to `out`, or its zero value if `value` is null.
+fn encode_field(
+    definitions: &MessageDefinitions,
+    field_type: &FieldType,
+    value: &Yaml,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    macro_rules! number {
+        ($as:ident, $t:ty, $expected:literal) => {{
+            let number = match value {
+                Yaml::Null => Some(<$t>::default()),
+                value => value.$as().and_then(|n| <$t>::try_from(n).ok()),
+            };
+            out.extend(
+                number
+                    .ok_or_else(|| mismatch(path, $expected))?
+                    .to_le_bytes(),
+            )
+        }};
+    }
+    match field_type {
+        FieldType::Bool => match value {
+            Yaml::Null => out.push(0),
+            Yaml::Bool(b) => out.push(*b as u8),
+            _ => return Err(mismatch(path, "a boolean")),
+        },
+        FieldType::Int8 => number!(as_i64, i8, "an int8"),
+        FieldType::UInt8 => number!(as_u64, u8, "a uint8"),
+        FieldType::Int16 => number!(as_i64, i16, "an int16"),
+        FieldType::UInt16 => number!(as_u64, u16, "a uint16"),
+        FieldType::Int32 => number!(as_i64, i32, "an int32"),
+        FieldType::UInt32 => number!(as_u64, u32, "a uint32"),
+        FieldType::Int64 => number!(as_i64, i64, "an int64"),
+        FieldType::UInt64 => number!(as_u64, u64, "a uint64"),
+        FieldType::Float32 => match value {
+            Yaml::Null => out.extend(0f32.to_le_bytes()),
+            value => {
+                let number = value.as_f64().ok_or_else(|| mismatch(path, "a number"))?;
+                out.extend((number as f32).to_le_bytes())
+            }
+        },
+        FieldType::Float64 => match value {
+            Yaml::Null => out.extend(0f64.to_le_bytes()),
+            value => out.extend(
+                value
+                    .as_f64()
+                    .ok_or_else(|| mismatch(path, "a number"))?
+                    .to_le_bytes(),
+            ),
+        },
+        FieldType::String => {
+            let s = match value {
+                Yaml::Null => "",
+                Yaml::String(s) => s,
+                _ => return Err(mismatch(path, "a string")),
+            };
+            out.extend((s.len() as u32).to_le_bytes());
+            out.extend(s.as_bytes());
+        }
+        FieldType::Time | FieldType::Duration => {
+            let (secs, nsecs) = match value {
+                Yaml::Null => (0, 0),
+                Yaml::Mapping(members) => {
+                    let part = |name| members.get(name).map_or(Some(0), Yaml::as_i64);
+                    match (part("secs"), part("nsecs")) {
+                        (Some(secs), Some(nsecs)) => (secs, nsecs),
+                        _ => return Err(mismatch(path, "secs and nsecs")),
+                    }
+                }
+                value => {
+                    let seconds = value
+                        .as_f64()
+                        .ok_or_else(|| mismatch(path, "seconds or secs and nsecs"))?;
+                    let nanos = (seconds * 1e9).round() as i64;
+                    (
+                        nanos.div_euclid(1_000_000_000),
+                        nanos.rem_euclid(1_000_000_000),
+                    )
+                }
+            };
+            // Times are unsigned and durations signed, but both have 32 bits per part.
+            out.extend((secs as u32).to_le_bytes());
+            out.extend((nsecs as u32).to_le_bytes());
+        }
+        FieldType::Message(name) => {
+            let definition = definitions.cached_message(name)?;
+            encode(definitions, &definition, value, path, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a message of the type `definition` from the front of `data`.
+fn decode(
+    definitions: &MessageDefinitions,
+    definition: &MessageDefinition,
+    data: &mut &[u8],
+    path: &str,
+) -> Result<Yaml> {
+    let mut members = Mapping::with_capacity(definition.fields.len());
+    for field in &definition.fields {
+        let path = format!(
+            "{path}{}{}",
+            if path.is_empty() { "" } else { "." },
+            field.name
+        );
+        let value = match field.array {
+            None => decode_field(definitions, &field.field_type, data, &path)?,
+            Some(len) => {
+                let len = match len {
+                    Some(len) => len,
+                    None => u32::from_le_bytes(take(data, &path)?) as usize,
+                };
+                let mut items = Vec::with_capacity(len.min(data.len()));
+                for _ in 0..len {
+                    items.push(decode_field(definitions, &field.field_type, data, &path)?);
+                }
+                Yaml::Sequence(items)
+            }
+        };
+        members.insert(Yaml::String(field.name.clone()), value);
+    }
+    Ok(Yaml::Mapping(members))
+}
+
+fn decode_field(
+    definitions: &MessageDefinitions,
+    field_type: &FieldType,
+    data: &mut &[u8],
+    path: &str,
+) -> Result<Yaml> {
+    macro_rules! number {
+        ($t:ty) => {
+            Yaml::Number(<$t>::from_le_bytes(take(data, path)?).into())
+        };
+    }
+    Ok(match field_type {
+        FieldType::Bool => Yaml::Bool(take::<1>(data, path)?[0] != 0),
+        FieldType::Int8 => number!(i8),
+        FieldType::UInt8 => number!(u8),
+        FieldType::Int16 => number!(i16),
+        FieldType::UInt16 => number!(u16),
+        FieldType::Int32 => number!(i32),
+        FieldType::UInt32 => number!(u32),
+        FieldType::Int64 => number!(i64),
+        FieldType::UInt64 => number!(u64),
+        FieldType::Float32 => Yaml::Number(f64::from(f32::from_le_bytes(take(data, path)?)).into()),
+        FieldType::Float64 => number!(f64),
+        FieldType::String => {
+            let bytes = read_bytes(data, path)?;
+            Yaml::String(String::from_utf8_lossy(bytes).into_owned())
+        }
+        FieldType::Time | FieldType::Duration => {
+            let secs = take(data, path)?;
+            let nsecs = take(data, path)?;
+            let (secs, nsecs): (i64, i64) = if *field_type == FieldType::Time {
+                (
+                    u32::from_le_bytes(secs).into(),
+                    u32::from_le_bytes(nsecs).into(),
+                )
+            } else {
+                (
+                    i32::from_le_bytes(secs).into(),
+                    i32::from_le_bytes(nsecs).into(),
+                )
+            };
+            let mut members = Mapping::new();
+            members.insert("secs".into(), secs.into());
+            members.insert("nsecs".into(), nsecs.into());
+            Yaml::Mapping(members)
+        }
+        FieldType::Message(name) => {
+            let definition = definitions.cached_message(name)?;
+            decode(definitions, &definition, data, path)?
+        }
+    })
+}
+
+fn take<const N: usize>(data: &mut &[u8], path: &str) -> Result<[u8; N]> {
+    let Some((bytes, rest)) = data.split_first_chunk::<N>() else {
+        return Err(Error::InvalidMessage(format!(
+            "the message ends within {path}"
+        )));
+    };
+    *data = rest;
+    Ok(*bytes)
+}
+
+/// Reads a length prefixed byte string from the front of `data`.
+fn read_bytes<'a>(data: &mut &'a [u8], path: &str) -> Result<&'a [u8]> {
+    let len = u32::from_le_bytes(take(data, path)?) as usize;
+    if data.len() < len {
+        return Err(Error::InvalidMessage(format!(
+            "the message ends within {path}"
+        )));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+
+fn mismatch(path: &str, expected: &str) -> Error {
+    Error::InvalidMessage(format!("{path} must be {expected}"))
+}
+
+#[test]
+fn test_encode_decode() {
+    let dir = std::env::temp_dir().join(format!("ros-core-rs-rosrpc-{}", std::process::id()));
+    let msgs = dir.join("ws/src/geometry/geometry_msgs/msg");
+    std::fs::create_dir_all(&msgs).unwrap();
+    std::fs::write(msgs.join("Point.msg"), "float64 x\nfloat64 y\nfloat64 z\n").unwrap();
+    let definitions = MessageDefinitions::new(vec![dir.join("ws")]);
+
+    let service = ServiceDefinition::parse(
+        "nav_msgs",
+        "# Plans a path\nuint8 MODE_FAST=1\nstring frame  # comment\n\
+         geometry_msgs/Point[] waypoints\nuint8 mode\nduration timeout\nint16[2] limits\n\
+         ---\nbool success\n",
+    )
+    .unwrap();
+    let names: Vec<_> = service
+        .request
+        .fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(names, ["frame", "waypoints", "mode", "timeout", "limits"]);
+    assert_eq!(service.request.fields[4].array, Some(Some(2)));
+    assert_eq!(service.response.fields[0].field_type, FieldType::Bool);
+
+    let request: Yaml = serde_yaml::from_str(
+        "{frame: map, waypoints: [{x: 1, y: 2.5}], timeout: 1.5, limits: [-1, 1]}",
+    )
+    .unwrap();
+    let mut encoded = Vec::new();
+    encode(&definitions, &service.request, &request, "", &mut encoded).unwrap();
+    assert_eq!(encoded.len(), 4 + 3 + 4 + 24 + 1 + 8 + 4);
+    let decoded = decode(&definitions, &service.request, &mut encoded.as_slice(), "").unwrap();
+    // Definitions are cached once read.
+    std::fs::remove_file(msgs.join("Point.msg")).unwrap();
+    assert_eq!(definitions.message("geometry_msgs/Point").unwrap().fields.len(), 3);
+    let expected: Yaml = serde_yaml::from_str(
+        "{frame: map, waypoints: [{x: 1.0, y: 2.5, z: 0.0}], mode: 0,
+          timeout: {secs: 1, nsecs: 500000000}, limits: [-1, 1]}",
+    )
+    .unwrap();
+    assert_eq!(decoded, expected);
+
+    let wrong: Yaml = serde_yaml::from_str("{mode: 256}").unwrap();
+    assert!(encode(&definitions, &service.request, &wrong, "", &mut Vec::new()).is_err());
+    let unknown: Yaml = serde_yaml::from_str("{speed: 1}").unwrap();
+    assert!(encode(
+        &definitions,
+        &service.request,
+        &unknown,
+        "",
+        &mut Vec::new()
+    )
+    .is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
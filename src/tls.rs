@@ -0,0 +1,87 @@
+//! TLS settings for `https://` masters and nodes, e.g. for robot fleets with a private PKI.
+use crate::{Error, Result};
+
+/// How [`MasterClient`](crate::core::MasterClient) and [`ClientApi`](crate::client_api::ClientApi)
+/// verify `https://` servers and authenticate themselves.
+///
+/// Without any settings, servers are verified against the built-in roots of the TLS backend.
+///
+/// # Example
+///
+/// ```no_run
+/// use ros_core_rs::core::MasterClient;
+/// use ros_core_rs::tls::TlsConfig;
+///
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let tls = TlsConfig::new()
+///     .root_certificates_pem(&std::fs::read("/etc/robot/ca.pem")?)?
+///     .identity_pem(
+///         &std::fs::read("/etc/robot/client.pem")?,
+///         &std::fs::read("/etc/robot/client.key")?,
+///     )?;
+/// let client = MasterClient::with_tls(&"https://master.fleet:11311".parse()?, &tls);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    root_certificates: Vec<reqwest::Certificate>,
+    identity: Option<reqwest::Identity>,
+    accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the certificates of the PEM bundle `pem` in addition to the built-in roots, e.g. the
+    /// CA of a private PKI.
+    pub fn root_certificates_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certificates = reqwest::Certificate::from_pem_bundle(pem)
+            .map_err(|e| Error::InvalidConfig(format!("invalid root certificates: {e}")))?;
+        self.root_certificates.extend(certificates);
+        Ok(self)
+    }
+
+    /// Authenticates with the PEM certificate chain `certificate` and its private key `key` to
+    /// servers that require client certificates. The native-tls backend only supports PKCS#8 keys.
+    pub fn identity_pem(mut self, certificate: &[u8], key: &[u8]) -> Result<Self> {
+        #[cfg(feature = "rustls-tls")]
+        let identity = reqwest::Identity::from_pem(&[key, b"\n", certificate].concat());
+        #[cfg(not(feature = "rustls-tls"))]
+        let identity = reqwest::Identity::from_pkcs8_pem(certificate, key);
+        let identity =
+            identity.map_err(|e| Error::InvalidConfig(format!("invalid client identity: {e}")))?;
+        self.identity = Some(identity);
+        Ok(self)
+    }
+
+    /// Accepts any server certificate, including self-signed and expired ones and those issued
+    /// for other hosts. Connections are still encrypted, but anybody on the network can intercept
+    /// them, so only use this on isolated networks or while debugging.
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        log::warn!("TLS certificate verification is disabled");
+        self.accept_invalid_certs = true;
+        self
+    }
+
+    /// Applies the settings to an HTTP client.
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        // Identities are loaded for rustls if it is enabled, so it must make the connections.
+        #[cfg(feature = "rustls-tls")]
+        {
+            builder = builder.use_rustls_tls();
+        }
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        if self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        builder
+    }
+}
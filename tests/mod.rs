@@ -2,4 +2,10 @@
 mod tests {
     // The examples in doc comments will be tested
     doctest!("../src/core.rs");
+    doctest!("../src/core/master.rs");
+    doctest!("../src/core/client.rs");
+    doctest!("../src/core/auth.rs");
+    doctest!("../src/core/config.rs");
+    doctest!("../src/core/pool.rs");
+    doctest!("../src/tls.rs");
 }